@@ -0,0 +1,142 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use taconite::*;
+
+const ENTITY_COUNT: usize = 100_000;
+
+struct Position {
+    x: f32,
+    y: f32,
+}
+
+struct Velocity {
+    x: f32,
+    y: f32,
+}
+
+/// Only on half of the entities, so queries have to skip some.
+struct Health;
+
+impl Component for Position {}
+impl Component for Velocity {}
+impl Component for Health {}
+
+/// Create a manager with every component stored as `kind`.
+fn world(kind: StorageKind) -> EntityManager {
+    let mut manager = EntityManager::default();
+    manager
+        .register_with_storage::<Position>(kind)
+        .register_with_storage::<Velocity>(kind)
+        .register_with_storage::<Health>(kind);
+
+    for i in 0..ENTITY_COUNT {
+        let entity = manager.create_entity();
+        manager
+            .add_component_to_entity(entity, Position { x: 0.0, y: 0.0 })
+            .add_component_to_entity(entity, Velocity { x: 1.0, y: 1.0 });
+
+        if i % 2 == 0 {
+            manager.add_component_to_entity(entity, Health);
+        }
+    }
+
+    manager
+}
+
+fn batch_world(kind: StorageKind) -> EntityManager {
+    let mut manager = EntityManager::default();
+    manager
+        .register_with_storage::<Position>(kind)
+        .register_with_storage::<Velocity>(kind)
+        .register_with_storage::<Health>(kind);
+
+    let entities = manager.spawn_batch(
+        (0..ENTITY_COUNT).map(|_| (Position { x: 0.0, y: 0.0 }, Velocity { x: 1.0, y: 1.0 })),
+    );
+    for entity in entities.into_iter().step_by(2) {
+        manager.add_component_to_entity(entity, Health);
+    }
+
+    manager
+}
+
+const KINDS: [(&str, StorageKind); 2] = [
+    ("table", StorageKind::Table),
+    ("sparse_set", StorageKind::SparseSet),
+];
+
+fn spawn(c: &mut Criterion) {
+    let mut group = c.benchmark_group("spawn_100k");
+    group.sample_size(10);
+    for (name, kind) in KINDS {
+        group.bench_function(name, |b| b.iter(|| black_box(world(kind))));
+        group.bench_function(format!("{name}_batch"), |b| {
+            b.iter(|| black_box(batch_world(kind)))
+        });
+    }
+    group.finish();
+}
+
+/// Despawns and respawns a tenth of the entities, like particles coming and going.
+fn churn(c: &mut Criterion) {
+    let mut group = c.benchmark_group("churn_10k_of_100k");
+
+    for (name, kind) in KINDS {
+        let mut manager = batch_world(kind);
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                let despawned = manager
+                    .query::<&Position>()
+                    .unwrap()
+                    .map(|(entity, _)| entity)
+                    .step_by(10)
+                    .collect::<Vec<_>>();
+                manager.despawn_batch(despawned.iter().copied());
+                black_box(
+                    manager.spawn_batch(
+                        despawned
+                            .iter()
+                            .map(|_| (Position { x: 0.0, y: 0.0 }, Velocity { x: 1.0, y: 1.0 })),
+                    ),
+                );
+            })
+        });
+    }
+
+    group.finish();
+}
+
+fn iterate(c: &mut Criterion) {
+    let mut group = c.benchmark_group("iterate_100k");
+
+    for (name, kind) in KINDS {
+        let mut manager = world(kind);
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                for (_, (velocity, mut position)) in
+                    manager.query::<(&Velocity, &mut Position)>().unwrap()
+                {
+                    position.x += velocity.x;
+                    position.y += velocity.y;
+                }
+            })
+        });
+
+        let mut accessor = EntityIdAccessor::default();
+        group.bench_function(format!("{name}_cached"), |b| {
+            b.iter(|| {
+                for (_, (velocity, mut position)) in accessor
+                    .query::<(&Velocity, &mut Position)>(&mut manager)
+                    .unwrap()
+                {
+                    position.x += velocity.x;
+                    position.y += velocity.y;
+                }
+            })
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, spawn, churn, iterate);
+criterion_main!(benches);
@@ -21,7 +21,8 @@ impl System for PrintPositionSystem {
         &mut self,
         manager: &mut EntityManager,
         _accessor: &mut EntityIdAccessor,
-        _input_handler: &InputHandler,
+        _commands: &mut Commands,
+        _resources: &mut Resources,
     ) -> Option<()> {
         for position in manager.borrow_components::<Position>()?.iter() {
             println!("Position: x: {:<10} y: {})", position.x, position.y);
@@ -36,16 +37,12 @@ impl System for MovementPositionSystem {
         &mut self,
         manager: &mut EntityManager,
         accessor: &mut EntityIdAccessor,
-        _input_handler: &InputHandler,
+        _commands: &mut Commands,
+        _resources: &mut Resources,
     ) -> Option<()> {
-        for id in accessor
-            .borrow_ids_for_pair::<Velocity, Position>(manager)?
-            .iter()
+        for (_, (velocity, mut position)) in
+            accessor.query::<(&Velocity, &mut Position)>(manager).ok()?
         {
-            let (velocity, mut position) = manager
-                .borrow_component_pair_mut::<Velocity, Position>(*id)
-                .unwrap();
-
             position.x += velocity.x;
             position.y += velocity.y;
         }
@@ -9,7 +9,8 @@ impl System for MovementSystem {
         &mut self,
         manager: &mut EntityManager,
         _accessor: &mut EntityIdAccessor,
-        _input_handler: &InputHandler,
+        _commands: &mut Commands,
+        _resources: &mut Resources,
     ) -> Option<()> {
         for transform in manager.borrow_components_mut::<Transform>()?.iter_mut() {
             transform.position.x += 1.;
@@ -25,22 +26,13 @@ impl RenderSystem for ComponentRenderer {
         &mut self,
         manager: &mut EntityManager,
         _accessor: &mut EntityIdAccessor,
-        canvas: &mut sdl2::render::Canvas<sdl2::video::Window>,
+        _resources: &Resources,
     ) -> Option<()> {
-        canvas.set_draw_color(Color::WHITE);
-        canvas.clear();
-
-        canvas.set_draw_color(Color::RGB(10, 10, 10));
-
         for transform in manager.borrow_components::<Transform>()?.iter() {
-            canvas
-                .fill_rect(Rect::new(
-                    (transform.position.x - 5.0) as i32,
-                    (transform.position.y - 5.0) as i32,
-                    10,
-                    10,
-                ))
-                .expect("Failed to draw to the canvas.");
+            println!(
+                "Drawing at x: {} y: {}",
+                transform.position.x, transform.position.y
+            );
         }
 
         Some(())
@@ -66,7 +58,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     taconite.add_render_system(ComponentRenderer {});
 
     taconite.start(WindowConfig {
-        name: "Render Test Example",
+        name: "Render Test Example".into(),
         ..Default::default()
     });
 
@@ -7,7 +7,8 @@ impl System for PrintTransformSystem {
         &mut self,
         manager: &mut EntityManager,
         _accessor: &mut EntityIdAccessor,
-        _input_handler: &InputHandler,
+        _commands: &mut Commands,
+        _resources: &mut Resources,
     ) -> Option<()> {
         for transform in manager.borrow_components::<Transform>()?.iter() {
             println!(
@@ -1,4 +1,6 @@
 /// Components for use within the engine
+use serde::{Deserialize, Serialize};
+use std::any::Any;
 use wgpu::{Color, Texture};
 
 use crate::ecs::*;
@@ -6,18 +8,52 @@ use crate::math::*;
 // use sdl2::{pixels::Color, render::Texture};
 
 /// A component that holds position and rotation data.
+/// For entities with a `Parent` this is relative to the parent.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize, Reflect)]
 pub struct Transform {
     pub position: Vector3,
     pub rotation: Vector3,
 }
 
+/// A component that holds the position and rotation of an entity in world space.
+/// The `TransformPropagationSystem` adds this to every entity with a `Transform`, and computes
+/// it from the `Transform` of the entity and its ancestors, so it shouldn't be changed by hand.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize, Reflect)]
+pub struct GlobalTransform {
+    pub position: Vector3,
+    pub rotation: Quaternion,
+}
+
+impl GlobalTransform {
+    /// Place a transform relative to this one.
+    /// The rotations are composed as quaternions, so the child is rotated by its own rotation
+    /// first and then by the rotation of the parent.
+    pub fn mul_transform(&self, transform: &Transform) -> Self {
+        Self {
+            position: self.position + self.rotation.rotate(transform.position),
+            rotation: self.rotation * Quaternion::from_euler(transform.rotation),
+        }
+    }
+}
+
+impl From<Transform> for GlobalTransform {
+    fn from(transform: Transform) -> Self {
+        Self {
+            position: transform.position,
+            rotation: Quaternion::from_euler(transform.rotation),
+        }
+    }
+}
+
 /// A bunch of shapes
 /// TODO: Add more shapes
+#[derive(Reflect)]
 pub enum Shapes {
     Rectangle,
 }
 
 /// A struct that contains a shape and a scale for a shape
+#[derive(Reflect)]
 pub struct Shape {
     pub shape: Shapes,
     pub position: Vector2,
@@ -72,6 +108,45 @@ pub struct Sprite {
     texture: Texture,
 }
 
+impl Reflect for Color {
+    fn type_info() -> TypeInfo {
+        TypeInfo::new::<Self>(TypeKind::Struct(vec![
+            FieldInfo::new::<f64>("r"),
+            FieldInfo::new::<f64>("g"),
+            FieldInfo::new::<f64>("b"),
+            FieldInfo::new::<f64>("a"),
+        ]))
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn fields(&self) -> Vec<(&'static str, &dyn Reflect)> {
+        vec![
+            ("r", &self.r),
+            ("g", &self.g),
+            ("b", &self.b),
+            ("a", &self.a),
+        ]
+    }
+
+    fn field_mut(&mut self, name: &str) -> Option<&mut dyn Reflect> {
+        match name {
+            "r" => Some(&mut self.r),
+            "g" => Some(&mut self.g),
+            "b" => Some(&mut self.b),
+            "a" => Some(&mut self.a),
+            _ => None,
+        }
+    }
+}
+
 impl Component for Transform {}
+impl Component for GlobalTransform {}
 impl Component for Sprite {}
 impl Component for Shape {}
@@ -0,0 +1,72 @@
+use std::any::TypeId;
+
+use super::{component::Component, entity::EntityId, entity_manager::EntityManager};
+
+/// A group of components that are added to an entity together, like
+/// `(Transform::default(), Shape::default())`.
+/// This is implemented for tuples of up to 16 components.
+pub trait Bundle: Send + Sync + 'static {
+    /// Add every component in the bundle to an entity, replacing the ones it already has.
+    fn add_to_entity(self, manager: &mut EntityManager, entity_id: EntityId);
+
+    /// Add the components in the bundle to an entity, leaving out the types in `skipped`.
+    fn add_to_entity_except(
+        self,
+        manager: &mut EntityManager,
+        entity_id: EntityId,
+        skipped: &[TypeId],
+    );
+
+    /// Get the types of the components in the bundle.
+    fn type_ids() -> Vec<TypeId>;
+
+    /// Make room for `additional` more of every component in the bundle, before spawning many
+    /// entities with it.
+    fn reserve(_manager: &mut EntityManager, _additional: usize) {}
+}
+
+macro_rules! impl_bundle_tuple {
+    ($($name:ident),*) => {
+        #[allow(non_snake_case, unused_variables)]
+        impl<$($name: 'static + Component),*> Bundle for ($($name,)*) {
+            fn add_to_entity(self, manager: &mut EntityManager, entity_id: EntityId) {
+                let ($($name,)*) = self;
+                $(manager.insert_component(entity_id, $name);)*
+            }
+
+            fn add_to_entity_except(
+                self,
+                manager: &mut EntityManager,
+                entity_id: EntityId,
+                skipped: &[TypeId],
+            ) {
+                let ($($name,)*) = self;
+                $(
+                    if !skipped.contains(&TypeId::of::<$name>()) {
+                        manager.insert_component(entity_id, $name);
+                    }
+                )*
+            }
+
+            fn type_ids() -> Vec<TypeId> {
+                vec![$(TypeId::of::<$name>()),*]
+            }
+
+            fn reserve(manager: &mut EntityManager, additional: usize) {
+                $(manager.reserve_components::<$name>(additional);)*
+            }
+        }
+    };
+}
+
+macro_rules! impl_bundle_tuples {
+    () => {
+        impl_bundle_tuple!();
+    };
+    ($first:ident $(, $rest:ident)*) => {
+        impl_bundle_tuple!($first $(, $rest)*);
+        impl_bundle_tuples!($($rest),*);
+    };
+}
+
+impl_bundle_tuples!(C1, C2, C3, C4, C5, C6, C7, C8, C9, C10, C11, C12, C13, C14, C15, C16);
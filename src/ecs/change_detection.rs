@@ -0,0 +1,88 @@
+use std::ops::{Deref, DerefMut};
+
+/// The frames a component was added and last changed in.
+#[derive(Clone, Copy, Debug)]
+pub struct ComponentTicks {
+    added: u64,
+    changed: u64,
+}
+
+impl ComponentTicks {
+    pub(crate) fn new(tick: u64) -> Self {
+        Self {
+            added: tick,
+            changed: tick,
+        }
+    }
+
+    pub fn added(&self) -> u64 {
+        self.added
+    }
+
+    pub fn changed(&self) -> u64 {
+        self.changed
+    }
+
+    /// Check if the component was added after `last_run`.
+    /// A system that has never run sees every component as added.
+    pub fn is_added(&self, last_run: Option<u64>) -> bool {
+        is_newer(self.added, last_run)
+    }
+
+    /// Check if the component was added or changed after `last_run`.
+    pub fn is_changed(&self, last_run: Option<u64>) -> bool {
+        is_newer(self.changed, last_run)
+    }
+
+    pub(crate) fn set_changed(&mut self, tick: u64) {
+        self.changed = tick;
+    }
+}
+
+pub(crate) fn is_newer(tick: u64, last_run: Option<u64>) -> bool {
+    last_run.is_none_or(|last_run| tick > last_run)
+}
+
+/// A mutable reference to a component that marks it as changed when it is written to.
+/// Reading through this doesn't count as a change.
+pub struct Mut<'a, T> {
+    value: &'a mut T,
+    ticks: &'a mut ComponentTicks,
+    tick: u64,
+}
+
+impl<'a, T> Mut<'a, T> {
+    pub(crate) fn new(value: &'a mut T, ticks: &'a mut ComponentTicks, tick: u64) -> Self {
+        Self { value, ticks, tick }
+    }
+
+    pub fn ticks(&self) -> ComponentTicks {
+        *self.ticks
+    }
+
+    /// Get the component without marking it as changed.
+    pub fn bypass_change_detection(&mut self) -> &mut T {
+        self.value
+    }
+
+    /// Unwrap into a plain mutable reference, marking the component as changed.
+    pub fn into_inner(self) -> &'a mut T {
+        self.ticks.set_changed(self.tick);
+        self.value
+    }
+}
+
+impl<T> Deref for Mut<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        self.value
+    }
+}
+
+impl<T> DerefMut for Mut<'_, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.ticks.set_changed(self.tick);
+        self.value
+    }
+}
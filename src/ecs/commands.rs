@@ -0,0 +1,254 @@
+use std::mem;
+
+use super::{
+    bundle::Bundle,
+    component::Component,
+    entity::EntityId,
+    entity_manager::{EntityManager, EntityReserver},
+    events::send_event,
+    observer::pending_trigger,
+    relation::Relationship,
+    resources::Resources,
+};
+
+enum Command {
+    Spawn(EntityId),
+    Despawn(EntityId),
+    Apply(Box<dyn FnOnce(&mut EntityManager) + Send + Sync>),
+}
+
+/// Records changes to entities and their components from inside a system.
+/// Nothing happens until the commands are applied, which `World::update` does after each
+/// system, so systems can spawn and despawn entities while iterating over a query.
+/// Get commands for an entity manager with `EntityManager::commands`.
+pub struct Commands {
+    queue: Vec<Command>,
+    reserver: EntityReserver,
+}
+
+impl Commands {
+    pub(crate) fn new(reserver: EntityReserver) -> Self {
+        Self {
+            queue: Vec::new(),
+            reserver,
+        }
+    }
+
+    /// Spawn a new entity. Components can be added to it through the returned `EntityCommands`.
+    /// Its ID is reserved right away, so later commands can use it, e.g. to give it a parent.
+    /// The ID is given back if the commands are dropped without being applied.
+    pub fn spawn(&mut self) -> EntityCommands<'_> {
+        let entity_id = self.reserver.reserve();
+        self.queue.push(Command::Spawn(entity_id));
+
+        EntityCommands {
+            commands: self,
+            entity_id,
+        }
+    }
+
+    /// Spawn a new entity with every component in a bundle.
+    /// More components can be added to it through the returned `EntityCommands`.
+    pub fn spawn_bundle<B: Bundle>(&mut self, bundle: B) -> EntityCommands<'_> {
+        let mut entity_commands = self.spawn();
+        entity_commands.insert_bundle(bundle);
+        entity_commands
+    }
+
+    /// Remove an entity along with all of its components and children.
+    pub fn despawn(&mut self, entity_id: EntityId) -> &mut Self {
+        self.queue.push(Command::Despawn(entity_id));
+        self
+    }
+
+    /// Spawn an entity for every bundle, making room for all of them up front.
+    pub fn spawn_batch<B: Bundle>(&mut self, bundles: impl IntoIterator<Item = B>) -> &mut Self {
+        let bundles = bundles.into_iter().collect::<Vec<_>>();
+        self.queue.push(Command::Apply(Box::new(move |manager| {
+            manager.spawn_batch(bundles);
+        })));
+        self
+    }
+
+    /// Remove every entity in `entity_ids`, along with their children.
+    /// Entities that are already gone are skipped.
+    pub fn despawn_batch(&mut self, entity_ids: impl IntoIterator<Item = EntityId>) -> &mut Self {
+        let entity_ids = entity_ids.into_iter().collect::<Vec<_>>();
+        self.queue.push(Command::Apply(Box::new(move |manager| {
+            manager.despawn_batch(entity_ids);
+        })));
+        self
+    }
+
+    /// Add a component to an existing entity, replacing the one it already has.
+    pub fn insert<T: 'static + Component>(
+        &mut self,
+        entity_id: EntityId,
+        component: T,
+    ) -> &mut Self {
+        self.queue.push(Command::Apply(Box::new(move |manager| {
+            manager.insert_component(entity_id, component);
+        })));
+        self
+    }
+
+    /// Remove a component from an existing entity.
+    pub fn remove<T: 'static + Component>(&mut self, entity_id: EntityId) -> &mut Self {
+        self.queue.push(Command::Apply(Box::new(move |manager| {
+            manager.remove_component_from_entity::<T>(entity_id);
+        })));
+        self
+    }
+
+    /// Enable or disable an entity, keeping its components.
+    pub fn set_entity_enabled(&mut self, entity_id: EntityId, enabled: bool) -> &mut Self {
+        self.queue.push(Command::Apply(Box::new(move |manager| {
+            manager.set_entity_enabled(entity_id, enabled);
+        })));
+        self
+    }
+
+    /// Tag an entity, giving it a `Tags` component if it doesn't have one.
+    pub fn add_tag(&mut self, entity_id: EntityId, tag: impl Into<String>) -> &mut Self {
+        let tag = tag.into();
+        self.queue.push(Command::Apply(Box::new(move |manager| {
+            manager.add_tag(entity_id, tag);
+        })));
+        self
+    }
+
+    /// Remove a tag from an entity.
+    pub fn remove_tag(&mut self, entity_id: EntityId, tag: impl Into<String>) -> &mut Self {
+        let tag = tag.into();
+        self.queue.push(Command::Apply(Box::new(move |manager| {
+            manager.remove_tag(entity_id, &tag);
+        })));
+        self
+    }
+
+    /// Trigger an event on an entity, running its observers once the commands are applied.
+    pub fn trigger<E: 'static + Send + Sync>(&mut self, event: E, target: EntityId) -> &mut Self {
+        self.queue.push(Command::Apply(Box::new(move |manager| {
+            manager.queue_trigger(pending_trigger(event, target));
+        })));
+        self
+    }
+
+    /// Send an event through the `Events<T>` resource once the commands are applied.
+    /// Parallel systems, which can't borrow resources mutably, send events this way.
+    pub fn send_event<T: 'static + Send + Sync>(&mut self, event: T) -> &mut Self {
+        self.change_resources(move |resources| send_event(resources, event))
+    }
+
+    /// Insert a resource once the commands are applied, replacing the previous resource of the
+    /// same type.
+    pub fn insert_resource<T: 'static + Send + Sync>(&mut self, resource: T) -> &mut Self {
+        self.change_resources(move |resources| {
+            resources.insert(resource);
+        })
+    }
+
+    /// Relate `source` to `target` by `R`.
+    pub fn relate<R: Relationship>(&mut self, source: EntityId, target: EntityId) -> &mut Self {
+        self.queue.push(Command::Apply(Box::new(move |manager| {
+            manager.relate::<R>(source, target);
+        })));
+        self
+    }
+
+    /// Stop relating `source` to `target` by `R`, if it is.
+    pub fn unrelate<R: Relationship>(&mut self, source: EntityId, target: EntityId) -> &mut Self {
+        self.queue.push(Command::Apply(Box::new(move |manager| {
+            manager.unrelate::<R>(source, target);
+        })));
+        self
+    }
+
+    /// Make `parent` the parent of `child`, replacing its previous parent.
+    pub fn set_parent(&mut self, child: EntityId, parent: EntityId) -> &mut Self {
+        self.queue.push(Command::Apply(Box::new(move |manager| {
+            manager.set_parent(child, parent);
+        })));
+        self
+    }
+
+    /// Detach an entity from its parent, if it has one.
+    pub fn remove_parent(&mut self, child: EntityId) -> &mut Self {
+        self.queue.push(Command::Apply(Box::new(move |manager| {
+            manager.remove_parent(child);
+        })));
+        self
+    }
+
+    fn change_resources(
+        &mut self,
+        change: impl FnOnce(&mut Resources) + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.queue.push(Command::Apply(Box::new(move |manager| {
+            manager.queue_resource_change(Box::new(change));
+        })));
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    /// Move the recorded commands into new commands, leaving these empty.
+    pub(crate) fn take(&mut self) -> Self {
+        Self {
+            queue: mem::take(&mut self.queue),
+            reserver: self.reserver.clone(),
+        }
+    }
+
+    /// Apply every recorded command in the order they were recorded in.
+    pub fn apply(&mut self, manager: &mut EntityManager) {
+        for command in self.queue.drain(..) {
+            match command {
+                Command::Spawn(entity_id) => manager.spawn_reserved(entity_id),
+                Command::Despawn(entity_id) => manager.remove_entity(entity_id),
+                Command::Apply(apply) => apply(manager),
+            }
+        }
+    }
+}
+
+// The IDs of entities that were never spawned are given back, so they can be reused.
+impl Drop for Commands {
+    fn drop(&mut self) {
+        for command in self.queue.iter() {
+            if let Command::Spawn(entity_id) = command {
+                self.reserver.release(*entity_id);
+            }
+        }
+    }
+}
+
+/// Adds components to an entity spawned through `Commands::spawn`.
+pub struct EntityCommands<'a> {
+    commands: &'a mut Commands,
+    entity_id: EntityId,
+}
+
+impl EntityCommands<'_> {
+    /// Get the ID the entity will have once it's spawned.
+    pub fn id(&self) -> EntityId {
+        self.entity_id
+    }
+
+    pub fn insert<T: 'static + Component>(&mut self, component: T) -> &mut Self {
+        self.commands.insert(self.entity_id, component);
+        self
+    }
+
+    pub fn insert_bundle<B: Bundle>(&mut self, bundle: B) -> &mut Self {
+        let entity_id = self.entity_id;
+        self.commands
+            .queue
+            .push(Command::Apply(Box::new(move |manager| {
+                bundle.add_to_entity(manager, entity_id);
+            })));
+        self
+    }
+}
@@ -1 +1,6 @@
-pub trait Component: Sized {}
+use super::storage::StorageKind;
+
+pub trait Component: Sized + Send + Sync {
+    /// How components of this type are stored, unless they're registered with another kind.
+    const STORAGE: StorageKind = StorageKind::Table;
+}
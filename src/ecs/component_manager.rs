@@ -1,16 +1,58 @@
-use super::component::Component;
+use super::{
+    change_detection::ComponentTicks,
+    commands::Commands,
+    component::Component,
+    entity::EntityId,
+    entity_manager::EntityManager,
+    hooks::ComponentHooks,
+    storage::{Storage, StorageKind},
+};
+use crate::errors::EcsError;
 use std::{
-    any::{Any, TypeId},
-    collections::HashMap,
+    any::{type_name, Any, TypeId},
+    cell::UnsafeCell,
+    marker::PhantomData,
+    mem,
+    ops::Deref,
+    sync::atomic::{AtomicIsize, Ordering},
 };
 use tracing::*;
 
-pub trait ComponentManagerT {
+pub trait ComponentManagerT: Send + Sync {
     fn as_any(&self) -> &dyn Any;
     fn as_mut_any(&mut self) -> &mut dyn Any;
 
-    fn component_exists(&self, entity_id: usize) -> bool;
-    fn remove(&mut self, entity_id: usize);
+    fn component_exists(&self, entity_id: EntityId) -> bool;
+    /// Get the number of enabled entities with this component.
+    fn component_count(&self) -> usize;
+    /// Get the enabled entities with this component.
+    fn entity_ids(&self) -> Box<dyn Iterator<Item = EntityId> + '_>;
+    fn set_enabled(&mut self, entity_id: EntityId, enabled: bool);
+    fn remove(&mut self, entity_id: EntityId, tick: u64, commands: &mut Commands);
+    /// Remove the component from an entity without running the `on_remove` hook.
+    fn discard(&mut self, entity_id: EntityId, tick: u64);
+    /// Get every entity with this component, including disabled ones.
+    fn all_entity_ids(&self) -> Vec<EntityId>;
+    /// Copy every component, if the components can be cloned.
+    fn snapshot(&self) -> Option<Box<dyn Any + Send + Sync>>;
+    fn is_cloneable(&self) -> bool;
+    /// Replace every component with the ones copied by `snapshot`, marking them as changed in
+    /// `tick`. The hooks run as if the components were replaced one by one, and record into
+    /// `commands`.
+    fn restore(&mut self, snapshot: &(dyn Any + Send + Sync), tick: u64, commands: &mut Commands);
+    /// Remove every component, running the `on_remove` hook for each of them.
+    fn clear(&mut self, tick: u64, commands: &mut Commands);
+    /// Take the component from an entity, running the `on_remove` hook, and add it to
+    /// `to_entity` in another entity manager, registering the component there if it isn't yet.
+    fn move_to(
+        &mut self,
+        entity_id: EntityId,
+        tick: u64,
+        commands: &mut Commands,
+        target: &mut EntityManager,
+        to_entity: EntityId,
+    );
+    fn clear_removed(&mut self, before: u64);
     fn get_type_id(&self) -> TypeId;
 }
 
@@ -23,12 +65,81 @@ impl<T: 'static + Component> ComponentManagerT for ComponentManager<T> {
         self as &mut dyn Any
     }
 
-    fn component_exists(&self, entity_id: usize) -> bool {
+    fn component_exists(&self, entity_id: EntityId) -> bool {
         cast_manager::<T>(self).component_exists(entity_id)
     }
 
-    fn remove(&mut self, entity_id: usize) {
-        cast_manager_mut::<T>(self).remove(entity_id);
+    fn component_count(&self) -> usize {
+        cast_manager::<T>(self).component_count()
+    }
+
+    fn entity_ids(&self) -> Box<dyn Iterator<Item = EntityId> + '_> {
+        cast_manager::<T>(self).storage.entity_ids()
+    }
+
+    fn set_enabled(&mut self, entity_id: EntityId, enabled: bool) {
+        cast_manager_mut::<T>(self).set_enabled(entity_id, enabled);
+    }
+
+    fn remove(&mut self, entity_id: EntityId, tick: u64, commands: &mut Commands) {
+        cast_manager_mut::<T>(self).remove(entity_id, tick, commands);
+    }
+
+    fn discard(&mut self, entity_id: EntityId, tick: u64) {
+        cast_manager_mut::<T>(self).discard(entity_id, tick);
+    }
+
+    fn all_entity_ids(&self) -> Vec<EntityId> {
+        cast_manager::<T>(self).all_entity_ids()
+    }
+
+    fn snapshot(&self) -> Option<Box<dyn Any + Send + Sync>> {
+        let manager = cast_manager::<T>(self);
+        let clone_storage = manager.clone_storage?;
+        Some(Box::new(clone_storage(&manager.storage)))
+    }
+
+    fn is_cloneable(&self) -> bool {
+        cast_manager::<T>(self).is_cloneable()
+    }
+
+    fn restore(&mut self, snapshot: &(dyn Any + Send + Sync), tick: u64, commands: &mut Commands) {
+        let manager = cast_manager_mut::<T>(self);
+        match (manager.clone_storage, snapshot.downcast_ref::<Storage<T>>()) {
+            (Some(clone_storage), Some(storage)) => {
+                manager.replace_storage(clone_storage(storage), tick, commands)
+            }
+            _ => error!(
+                "Failed to restore the {} components. They can't be cloned.",
+                type_name::<T>()
+            ),
+        }
+    }
+
+    fn clear(&mut self, tick: u64, commands: &mut Commands) {
+        let manager = cast_manager_mut::<T>(self);
+        let storage = Storage::new(manager.storage_kind());
+        manager.replace_storage(storage, tick, commands);
+    }
+
+    fn move_to(
+        &mut self,
+        entity_id: EntityId,
+        tick: u64,
+        commands: &mut Commands,
+        target: &mut EntityManager,
+        to_entity: EntityId,
+    ) {
+        let manager = cast_manager_mut::<T>(self);
+        let Some(component) = manager.take(entity_id, tick, commands) else {
+            return;
+        };
+        target.register_like(manager);
+        target.add_component_to_entity(to_entity, component);
+    }
+
+    fn clear_removed(&mut self, before: u64) {
+        cast_manager_mut::<T>(self).clear_removed(before);
     }
 
     fn get_type_id(&self) -> TypeId {
@@ -54,81 +165,378 @@ pub fn cast_manager_mut<T: 'static + Component>(
         .unwrap()
 }
 
+/// Holds a component manager and tracks how it is borrowed at runtime.
+/// This lets a query borrow several managers from a shared reference while making sure one
+/// manager is never borrowed mutably more than once (e.g. `(&mut Transform, &mut Transform)`).
+/// The borrow flag is atomic, so systems running in parallel can borrow managers too.
+pub(crate) struct ComponentCell {
+    /// The number of shared borrows, or -1 while mutably borrowed.
+    borrow: AtomicIsize,
+    manager: UnsafeCell<Box<dyn ComponentManagerT>>,
+}
+
+// SAFETY: The manager is only handed out across threads through the atomic borrow flag, or
+// through untracked reads of data that tracked borrows never write to.
+unsafe impl Sync for ComponentCell {}
+
+impl ComponentCell {
+    pub(crate) fn new(manager: Box<dyn ComponentManagerT>) -> Self {
+        Self {
+            borrow: AtomicIsize::new(0),
+            manager: UnsafeCell::new(manager),
+        }
+    }
+
+    /// Get the manager without tracking the borrow.
+    /// The returned reference must not be held while a mutable borrow is being used.
+    pub(crate) fn get(&self) -> &dyn ComponentManagerT {
+        // SAFETY: Mutable borrows only hand out references through `ManagerRefMut`, which
+        // callers don't use while this reference is alive.
+        unsafe { (*self.manager.get()).as_ref() }
+    }
+
+    pub(crate) fn get_mut(&mut self) -> &mut dyn ComponentManagerT {
+        self.manager.get_mut().as_mut()
+    }
+
+    pub(crate) fn try_borrow<T: 'static + Component>(&self) -> Result<ManagerRef<'_, T>, EcsError> {
+        self.borrow
+            .fetch_update(Ordering::Acquire, Ordering::Relaxed, |borrow| {
+                (borrow >= 0).then_some(borrow + 1)
+            })
+            .map_err(|_| EcsError::BorrowConflict(type_name::<T>()))?;
+
+        Ok(ManagerRef {
+            manager: cast_manager(self.get()),
+            borrow: &self.borrow,
+        })
+    }
+
+    pub(crate) fn try_borrow_mut<T: 'static + Component>(
+        &self,
+    ) -> Result<ManagerRefMut<'_, T>, EcsError> {
+        self.borrow
+            .compare_exchange(0, -1, Ordering::Acquire, Ordering::Relaxed)
+            .map_err(|_| EcsError::BorrowConflict(type_name::<T>()))?;
+
+        // SAFETY: The borrow flag guarantees this is the only live borrow of the manager.
+        let manager = cast_manager_mut::<T>(unsafe { (*self.manager.get()).as_mut() });
+        let (components, ticks) = manager.storage.as_mut_ptrs();
+
+        Ok(ManagerRefMut {
+            components,
+            ticks,
+            manager,
+            borrow: &self.borrow,
+            marker: PhantomData,
+        })
+    }
+}
+
+/// A tracked shared borrow of a component manager.
+pub struct ManagerRef<'a, T: Component> {
+    manager: &'a ComponentManager<T>,
+    borrow: &'a AtomicIsize,
+}
+
+impl<'a, T: Component> ManagerRef<'a, T> {
+    pub(crate) fn manager(&self) -> &'a ComponentManager<T> {
+        self.manager
+    }
+}
+
+impl<T: Component> Deref for ManagerRef<'_, T> {
+    type Target = ComponentManager<T>;
+
+    fn deref(&self) -> &Self::Target {
+        self.manager
+    }
+}
+
+impl<T: Component> Drop for ManagerRef<'_, T> {
+    fn drop(&mut self) {
+        self.borrow.fetch_sub(1, Ordering::Release);
+    }
+}
+
+/// A tracked mutable borrow of a component manager.
+/// This keeps raw pointers to the components so mutable references to different components can
+/// be handed out while the borrow is alive.
+pub struct ManagerRefMut<'a, T: Component> {
+    manager: *const ComponentManager<T>,
+    components: *mut T,
+    ticks: *mut ComponentTicks,
+    borrow: &'a AtomicIsize,
+    marker: PhantomData<&'a mut ComponentManager<T>>,
+}
+
+impl<T: Component> ManagerRefMut<'_, T> {
+    /// Get raw pointers to the component of an entity and its change ticks.
+    pub(crate) fn component_ptr(
+        &self,
+        entity_id: EntityId,
+    ) -> Option<(*mut T, *mut ComponentTicks)> {
+        // SAFETY: The manager can't be moved or resized while it's borrowed.
+        let index = unsafe { (*self.manager).index_of(entity_id)? };
+        // SAFETY: `index` comes from the manager, so it's within both lists.
+        unsafe { Some((self.components.add(index), self.ticks.add(index))) }
+    }
+}
+
+impl<T: Component> Drop for ManagerRefMut<'_, T> {
+    fn drop(&mut self) {
+        self.borrow.store(0, Ordering::Release);
+    }
+}
+
+/// Copies the storage of a component type that is `Clone`.
+type CloneStorage<T> = fn(&Storage<T>) -> Storage<T>;
+
+/// Stores every component of one type, laid out according to its `StorageKind`.
+/// It can't see an entity's other components, like its `Name`, so it quietly ignores entities
+/// that don't have a component. `EntityManager` checks first, and logs those errors along with
+/// the name of the entity.
 pub struct ComponentManager<T: Component> {
-    components: Vec<T>,
-    entity_ids: Vec<usize>,
-    entity_id_map: HashMap<usize, usize>,
+    storage: Storage<T>,
+    removed: Vec<(EntityId, u64)>,
+    hooks: ComponentHooks<T>,
+    /// Copies the storage for snapshots. This is only set for components that are `Clone`.
+    clone_storage: Option<CloneStorage<T>>,
 }
 
 #[allow(clippy::new_without_default)]
 impl<T: Component> ComponentManager<T> {
+    /// Create a manager with the storage kind `T` asks for.
     pub fn new() -> Self {
+        Self::with_storage(T::STORAGE)
+    }
+
+    /// Create a manager with a certain storage kind.
+    /// Only zero-sized components can be stored as tags, others fall back to a table.
+    pub fn with_storage(kind: StorageKind) -> Self {
+        let kind = match kind {
+            StorageKind::Tag if mem::size_of::<T>() != 0 => {
+                error!(
+                    "Failed to store {} as a tag. Only zero-sized components can be tags.",
+                    type_name::<T>()
+                );
+                StorageKind::Table
+            }
+            kind => kind,
+        };
+
         Self {
-            components: Vec::new(),
-            entity_ids: Vec::new(),
-            entity_id_map: HashMap::new(),
+            storage: Storage::new(kind),
+            removed: Vec::new(),
+            hooks: ComponentHooks::default(),
+            clone_storage: None,
         }
     }
 
-    pub fn component_exists(&self, entity_id: usize) -> bool {
-        self.entity_id_map.contains_key(&entity_id)
+    pub fn storage_kind(&self) -> StorageKind {
+        self.storage.kind()
+    }
+
+    /// Make room for at least `additional` more components, so adding them doesn't reallocate.
+    pub fn reserve(&mut self, additional: usize) {
+        self.storage.reserve(additional);
+    }
+
+    /// Include the components in snapshots.
+    pub fn set_cloneable(&mut self)
+    where
+        T: Clone,
+    {
+        self.clone_storage = Some(Storage::clone);
+    }
+
+    pub fn is_cloneable(&self) -> bool {
+        self.clone_storage.is_some()
     }
 
-    pub fn add(&mut self, entity_id: usize, component: T) {
+    pub fn set_hooks(&mut self, hooks: ComponentHooks<T>) {
+        self.hooks = hooks;
+    }
+
+    pub fn component_exists(&self, entity_id: EntityId) -> bool {
+        self.storage.slot(entity_id).is_some()
+    }
+
+    /// Add a component to an entity, unless it already has one.
+    /// `tick` is the current frame, which the component is marked as added in. The `on_add`
+    /// and `on_insert` hooks record into `commands`.
+    pub fn add(&mut self, entity_id: EntityId, component: T, tick: u64, commands: &mut Commands) {
         if self.component_exists(entity_id) {
-            error!("Failed to add component. Already taken.");
             return;
         }
 
-        self.components.push(component);
-        self.entity_ids.push(entity_id);
+        let slot = self
+            .storage
+            .push(entity_id, component, ComponentTicks::new(tick));
 
-        self.entity_id_map
-            .insert(entity_id, self.components.len() - 1);
+        let component = self.storage.get(slot);
+        self.hooks.run_on_add(component, entity_id, commands);
+        self.hooks.run_on_insert(component, entity_id, commands);
     }
 
-    pub fn remove(&mut self, entity_id: usize) {
-        if !self.component_exists(entity_id) {
-            error!("Failed to remove component. Does not exist.");
+    /// Replace the component of an entity, or add it if the entity doesn't have one.
+    /// `tick` is the current frame, which the component is marked as changed in. A replaced
+    /// component goes through the `on_remove` hook before the new one goes through `on_insert`.
+    pub fn insert(
+        &mut self,
+        entity_id: EntityId,
+        component: T,
+        tick: u64,
+        commands: &mut Commands,
+    ) {
+        let Some(slot) = self.storage.slot(entity_id) else {
+            self.add(entity_id, component, tick, commands);
             return;
-        }
+        };
 
-        let index = *self.entity_id_map.get(&entity_id).unwrap();
+        self.hooks
+            .run_on_remove(self.storage.get(slot), entity_id, commands);
+        *self.storage.get_mut(slot) = component;
+        self.storage.ticks_mut(slot).set_changed(tick);
+        self.hooks
+            .run_on_insert(self.storage.get(slot), entity_id, commands);
+    }
 
-        self.entity_id_map
-            .insert(*self.entity_ids.last().unwrap(), index);
-        self.components.swap_remove(index);
-        self.entity_ids.swap_remove(index);
-        self.entity_id_map.remove(&entity_id);
+    /// Remove the component from an entity, if it has one.
+    /// `tick` is the current frame, which the removal is recorded in. The `on_remove` hook
+    /// records into `commands`.
+    pub fn remove(&mut self, entity_id: EntityId, tick: u64, commands: &mut Commands) {
+        let Some(slot) = self.storage.slot(entity_id) else {
+            return;
+        };
+
+        self.hooks
+            .run_on_remove(self.storage.get(slot), entity_id, commands);
+        self.discard(entity_id, tick);
+    }
+
+    /// Remove the component from an entity and hand it back.
+    /// `tick` is the current frame, which the removal is recorded in. The `on_remove` hook
+    /// records into `commands`.
+    pub fn take(&mut self, entity_id: EntityId, tick: u64, commands: &mut Commands) -> Option<T> {
+        let slot = self.storage.slot(entity_id)?;
+        self.hooks
+            .run_on_remove(self.storage.get(slot), entity_id, commands);
+        let component = self.storage.remove(entity_id)?;
+        self.removed.push((entity_id, tick));
+        Some(component)
     }
 
-    pub fn borrow_component(&self, entity_id: usize) -> Option<&T> {
-        if !self.component_exists(entity_id) {
-            error!("Can't borrow the component. It does not exist.");
-            return None;
+    /// Replace every component with the ones in `storage`, marking them as changed in `tick`.
+    /// Every component that's dropped goes through `on_remove`, and every new one through
+    /// `on_insert`, after `on_add` if its entity didn't have one.
+    fn replace_storage(&mut self, storage: Storage<T>, tick: u64, commands: &mut Commands) {
+        let old_storage = mem::replace(&mut self.storage, storage);
+        self.storage.set_all_changed(tick);
+
+        for entity_id in old_storage.all_entity_ids() {
+            if let Some(slot) = old_storage.slot(entity_id) {
+                self.hooks
+                    .run_on_remove(old_storage.get(slot), entity_id, commands);
+            }
+            if self.storage.slot(entity_id).is_none() {
+                self.removed.push((entity_id, tick));
+            }
+        }
+        for entity_id in self.storage.all_entity_ids() {
+            let Some(slot) = self.storage.slot(entity_id) else {
+                continue;
+            };
+            let component = self.storage.get(slot);
+            if old_storage.slot(entity_id).is_none() {
+                self.hooks.run_on_add(component, entity_id, commands);
+            }
+            self.hooks.run_on_insert(component, entity_id, commands);
         }
+    }
 
-        Some(&self.components[*self.entity_id_map.get(&entity_id).unwrap()])
+    /// Create an empty manager with the same storage kind, which is cloneable if this one is.
+    /// Hooks aren't carried over.
+    pub fn new_like(&self) -> Self {
+        let mut manager = Self::with_storage(self.storage_kind());
+        manager.clone_storage = self.clone_storage;
+        manager
     }
 
-    pub fn borrow_component_mut(&mut self, entity_id: usize) -> Option<&mut T> {
-        if !self.component_exists(entity_id) {
-            error!("Can't borrow the component. It does not exist.");
-            return None;
+    /// Remove the component from an entity without running the `on_remove` hook.
+    pub fn discard(&mut self, entity_id: EntityId, tick: u64) {
+        if self.storage.remove(entity_id).is_some() {
+            self.removed.push((entity_id, tick));
         }
+    }
+
+    /// Move the component of an entity among the enabled or disabled components, if it has one.
+    pub fn set_enabled(&mut self, entity_id: EntityId, enabled: bool) {
+        let Some(slot) = self.storage.slot(entity_id) else {
+            return;
+        };
+
+        self.storage.set_enabled(slot, enabled);
+    }
+
+    pub fn borrow_component(&self, entity_id: EntityId) -> Option<&T> {
+        let slot = self.storage.slot(entity_id)?;
+        Some(self.storage.get(slot))
+    }
+
+    /// Mutably borrow the component of an entity.
+    /// `tick` is the current frame, which the component is marked as changed in.
+    pub fn borrow_component_mut(&mut self, entity_id: EntityId, tick: u64) -> Option<&mut T> {
+        let slot = self.storage.slot(entity_id)?;
+        self.storage.ticks_mut(slot).set_changed(tick);
+        Some(self.storage.get_mut(slot))
+    }
+
+    /// Get the change ticks of an entity's component.
+    pub fn ticks(&self, entity_id: EntityId) -> Option<ComponentTicks> {
+        let slot = self.storage.slot(entity_id)?;
+        // SAFETY: `slot` comes from the storage, so it's within the ticks. This reads through a
+        // raw pointer, as a query may be handing out mutable ticks of other entities.
+        Some(unsafe { *self.storage.ticks_ptr().add(slot) })
+    }
+
+    /// Get the entities this component was removed from, and the frames they were removed in.
+    pub fn borrow_removed(&self) -> &Vec<(EntityId, u64)> {
+        &self.removed
+    }
+
+    /// Forget removals from before the frame `before`.
+    pub fn clear_removed(&mut self, before: u64) {
+        self.removed.retain(|(_, tick)| *tick >= before);
+    }
+
+    fn index_of(&self, entity_id: EntityId) -> Option<usize> {
+        self.storage.slot(entity_id)
+    }
+
+    /// Get the number of enabled entities with this component.
+    pub fn component_count(&self) -> usize {
+        self.storage.enabled_count()
+    }
 
-        Some(&mut self.components[*self.entity_id_map.get(&entity_id).unwrap()])
+    /// Get the entities with this component, leaving out disabled entities.
+    pub fn entity_ids(&self) -> impl Iterator<Item = EntityId> + '_ {
+        self.storage.entity_ids()
     }
 
-    pub fn borrow_entity_ids(&self) -> &Vec<usize> {
-        &self.entity_ids
+    /// Get every entity with this component, including disabled ones.
+    pub fn all_entity_ids(&self) -> Vec<EntityId> {
+        self.storage.all_entity_ids()
     }
 
-    pub fn borrow_components(&self) -> &Vec<T> {
-        &self.components
+    /// Borrow the components of every enabled entity.
+    pub fn borrow_components(&self) -> &[T] {
+        self.storage.components()
     }
 
-    pub fn borrow_components_mut(&mut self) -> &mut Vec<T> {
-        &mut self.components
+    /// Mutably borrow the components of every enabled entity.
+    /// As there's no way to tell which ones get written to, they're all marked as changed.
+    pub fn borrow_components_mut(&mut self, tick: u64) -> &mut [T] {
+        self.storage.components_mut(tick)
     }
 }
@@ -0,0 +1,80 @@
+use std::{fmt, sync::Arc};
+
+use super::resources::Resources;
+
+type ConditionFn = dyn Fn(&Resources, Option<u64>) -> bool + Send + Sync;
+
+/// Decides whether a system runs, from the resources and the frame the system last ran in.
+/// A system with conditions only runs when all of them hold, and a skipped system still sees
+/// every change since it last ran once it does run.
+///
+/// Any `Fn(&Resources, Option<u64>) -> bool` can be a condition, like `resource_changed::<T>`
+/// or the closure returned by `in_state`.
+#[derive(Clone)]
+pub struct RunCondition {
+    condition: Arc<ConditionFn>,
+}
+
+impl RunCondition {
+    pub fn new(
+        condition: impl Fn(&Resources, Option<u64>) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            condition: Arc::new(condition),
+        }
+    }
+
+    pub(crate) fn check(&self, resources: &Resources, last_run: Option<u64>) -> bool {
+        (self.condition)(resources, last_run)
+    }
+}
+
+impl fmt::Debug for RunCondition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("RunCondition")
+    }
+}
+
+/// A resource holding the state the game is in, like a menu or a level being played.
+/// Systems can be made to run only in some states with `in_state`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct State<S>(S);
+
+impl<S> State<S> {
+    pub fn new(state: S) -> Self {
+        Self(state)
+    }
+
+    pub fn get(&self) -> &S {
+        &self.0
+    }
+
+    pub fn set(&mut self, state: S) {
+        self.0 = state;
+    }
+}
+
+/// Run the system only while the `State<S>` resource is `state`.
+pub fn in_state<S>(state: S) -> impl Fn(&Resources, Option<u64>) -> bool + Send + Sync
+where
+    S: 'static + PartialEq + Send + Sync,
+{
+    move |resources, _| {
+        resources
+            .get::<State<S>>()
+            .is_some_and(|current| *current.get() == state)
+    }
+}
+
+/// Run the system only while the resource `T` exists.
+pub fn resource_exists<T: 'static + Send + Sync>(resources: &Resources, _: Option<u64>) -> bool {
+    resources.contains::<T>()
+}
+
+/// Run the system only if the resource `T` was inserted or changed since the system last ran.
+pub fn resource_changed<T: 'static + Send + Sync>(
+    resources: &Resources,
+    last_run: Option<u64>,
+) -> bool {
+    resources.is_changed::<T>(last_run)
+}
@@ -1,23 +1,117 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// A handle to an entity.
+/// This pairs the index of the entity with the generation it was created in, so a handle to a
+/// removed entity won't match a new entity that reused the same index.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct EntityId {
+    index: usize,
+    generation: u32,
+}
+
+impl EntityId {
+    pub(crate) fn new(index: usize, generation: u32) -> Self {
+        Self { index, generation }
+    }
+
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+}
+
+impl fmt::Display for EntityId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}v{}", self.index, self.generation)
+    }
+}
+
+#[derive(Clone)]
 pub struct Entity {
     alive: bool,
+    enabled: bool,
+    generation: u32,
+    /// The generation the slot moves onto when the entity is destroyed. This is only ahead of
+    /// `generation + 1` after a restore went back past the generations handed out since.
+    next_generation: u32,
 }
 
 impl Default for Entity {
     fn default() -> Self {
-        Self { alive: true }
+        Self {
+            alive: true,
+            enabled: true,
+            generation: 0,
+            next_generation: 1,
+        }
     }
 }
 
 impl Entity {
+    /// A slot for an entity whose ID is reserved, but which isn't created yet.
+    pub(crate) fn reserved() -> Self {
+        Self {
+            alive: false,
+            enabled: true,
+            generation: 0,
+            next_generation: 1,
+        }
+    }
+
+    /// A slot with no entity, which is reused in `generation`.
+    pub(crate) fn free(generation: u32) -> Self {
+        Self {
+            alive: false,
+            enabled: true,
+            generation,
+            next_generation: generation.wrapping_add(1),
+        }
+    }
+
     pub fn is_alive(&self) -> bool {
         self.alive
     }
 
-    pub fn enable(&mut self) {
+    /// Check if the entity is enabled. Queries skip disabled entities.
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+
+    pub(crate) fn next_generation(&self) -> u32 {
+        self.next_generation
+    }
+
+    pub(crate) fn set_next_generation(&mut self, next_generation: u32) {
+        self.next_generation = next_generation;
+    }
+
+    /// Brings a destroyed entity back for reuse, in the generation it was destroyed into.
+    pub fn recycle(&mut self) {
         self.alive = true;
+        self.enabled = true;
     }
 
-    pub fn disable(&mut self) {
+    /// Destroys the entity and moves it onto the next generation, so any handles to it go stale.
+    pub fn destroy(&mut self) {
         self.alive = false;
+        self.generation = self.next_generation;
+        self.next_generation = self.generation.wrapping_add(1);
+    }
+
+    pub fn enable(&mut self) {
+        self.enabled = true;
+    }
+
+    /// Disables the entity, keeping its components.
+    pub fn disable(&mut self) {
+        self.enabled = false;
     }
 }
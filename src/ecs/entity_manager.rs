@@ -1,242 +1,433 @@
 use crate::ecs::{
+    bundle::Bundle,
+    change_detection::is_newer,
+    commands::Commands,
     component::Component,
-    component_manager::{cast_manager, cast_manager_mut, ComponentManager, ComponentManagerT},
-    entity::Entity,
+    component_manager::{
+        cast_manager, cast_manager_mut, ComponentCell, ComponentManager, ManagerRef, ManagerRefMut,
+    },
+    entity::{Entity, EntityId},
+    hierarchy::{is_hierarchy, Children, Parent},
+    hooks::ComponentHooks,
+    name::{EntityLabel, Name, NameIndex, Tags},
+    observer::PendingTrigger,
+    query::{
+        query_component_types, query_excluded_component_types, QueryData, QueryFilter, QueryIter,
+    },
+    relation::{RelatedBy, Relation, Relationship},
+    resources::ResourceChange,
+    snapshot::{Snapshot, SnapshotData},
+    storage::StorageKind,
 };
+use crate::errors::EcsError;
 use std::{
-    any::TypeId,
-    collections::{hash_map, HashMap},
+    any::{type_name, TypeId},
+    borrow::Cow,
+    collections::{HashMap, HashSet, VecDeque},
+    sync::{Arc, Mutex},
     vec,
 };
 use tracing::*;
-struct Entities {
-    entities: Vec<Entity>,
-    available_indexes: Vec<usize>,
+/// Hands out entity IDs. This is shared with every `Commands`, so commands can reserve the ID
+/// of an entity they spawn before it's created.
+#[derive(Clone, Default)]
+struct EntityAllocator {
+    /// The IDs removed entities' indexes are reused with, oldest first, so an index is reused
+    /// as late as possible.
+    available_ids: VecDeque<EntityId>,
+    /// The number of indexes handed out, including the reserved ones.
+    len: usize,
 }
 
-impl Entities {
-    fn new() -> Self {
-        Entities {
-            entities: vec![],
-            available_indexes: vec![],
+impl EntityAllocator {
+    fn allocate(&mut self) -> EntityId {
+        if let Some(entity_id) = self.available_ids.pop_front() {
+            return entity_id;
         }
-    }
 
-    fn entity_exists(&self, entity_id: usize) -> bool {
-        entity_id < self.entities.len() && self.entities[entity_id].is_alive()
+        self.len += 1;
+        EntityId::new(self.len - 1, 0)
     }
+}
 
-    fn create(&mut self) -> usize {
-        if !self.available_indexes.is_empty() {
-            let index = self.available_indexes.remove(0);
-            self.entities[index].enable();
-            return index;
-        }
+/// A handle to the allocator of an entity manager, for reserving entity IDs without borrowing
+/// the manager.
+#[derive(Clone, Default)]
+pub(crate) struct EntityReserver {
+    allocator: Arc<Mutex<EntityAllocator>>,
+}
 
-        self.entities.push(Entity::default());
+impl EntityReserver {
+    /// Reserve the ID of an entity that's created later with `EntityManager::spawn_reserved`.
+    pub(crate) fn reserve(&self) -> EntityId {
+        self.allocator.lock().unwrap().allocate()
+    }
 
-        self.entities.len() - 1
+    /// Give back a reserved ID whose entity was never created.
+    pub(crate) fn release(&self, entity_id: EntityId) {
+        self.allocator
+            .lock()
+            .unwrap()
+            .available_ids
+            .push_back(entity_id);
     }
+}
 
-    fn remove(&mut self, entity_id: usize) {
-        if !self.entity_exists(entity_id) {
-            error!("Can't remove this ID, it is not a used ID.");
-            return;
-        }
+pub(crate) struct Entities {
+    entities: Vec<Entity>,
+    reserver: EntityReserver,
+}
 
-        self.entities[entity_id].disable();
-        self.available_indexes.push(entity_id);
+// Cloning copies the allocator, so a copy kept in a snapshot doesn't hand out IDs along with
+// the original.
+impl Clone for Entities {
+    fn clone(&self) -> Self {
+        let allocator = self.reserver.allocator.lock().unwrap().clone();
+        Self {
+            entities: self.entities.clone(),
+            reserver: EntityReserver {
+                allocator: Arc::new(Mutex::new(allocator)),
+            },
+        }
     }
 }
 
-#[derive(Default)]
-pub struct EntityIdAccessor {
-    caching_map: HashMap<TypeId, Vec<usize>>,
-    updated_map: HashMap<TypeId, u64>,
-}
+impl Entities {
+    pub(crate) fn new() -> Self {
+        Entities {
+            entities: vec![],
+            reserver: EntityReserver::default(),
+        }
+    }
 
-impl EntityIdAccessor {
-    pub fn borrow_ids<T: 'static + Component>(
-        &mut self,
-        manager: &EntityManager,
-    ) -> Option<&Vec<usize>> {
-        if !manager.has_component_manager::<T>() {
-            return None;
+    pub(crate) fn entity_exists(&self, entity_id: EntityId) -> bool {
+        match self.entities.get(entity_id.index()) {
+            Some(entity) => entity.is_alive() && entity.generation() == entity_id.generation(),
+            None => false,
         }
+    }
 
-        let type_id = TypeId::of::<T>();
-        let needs_update = if let hash_map::Entry::Vacant(e) = self.caching_map.entry(type_id) {
-            e.insert(Vec::new());
-            true
-        } else {
-            let updated_frame = *self.updated_map.get(&type_id).unwrap();
-            manager.get_updated_frame::<T>() != updated_frame
-        };
+    pub(crate) fn create(&mut self) -> EntityId {
+        let entity_id = self.reserver.reserve();
+        self.create_reserved(entity_id);
+        entity_id
+    }
 
-        if needs_update {
-            let src = &manager.borrow_entity_ids::<T>().unwrap();
-            let dst = self.caching_map.get_mut(&type_id).unwrap();
-            dst.clear();
-            for id in src.iter() {
-                dst.push(*id);
-            }
-            self.updated_map.insert(type_id, manager.get_frame());
+    /// Create the entity of a reserved ID, and get whether it was reserved.
+    pub(crate) fn create_reserved(&mut self, entity_id: EntityId) -> bool {
+        let index = entity_id.index();
+        if index >= self.reserver.allocator.lock().unwrap().len {
+            return false;
+        }
+        if index >= self.entities.len() {
+            // Reserved entities that aren't created yet can come before this one.
+            self.entities.resize_with(index + 1, Entity::reserved);
         }
 
-        self.caching_map.get(&type_id)
+        let entity = &mut self.entities[index];
+        if entity.is_alive() || entity.generation() != entity_id.generation() {
+            return false;
+        }
+        entity.recycle();
+        true
     }
 
-    pub fn borrow_ids_for_pair<T1: 'static + Component, T2: 'static + Component>(
-        &mut self,
-        manager: &EntityManager,
-    ) -> Option<&Vec<usize>> {
-        if !manager.has_component_manager::<T1>() || !manager.has_component_manager::<T2>() {
-            return None;
+    pub(crate) fn reserver(&self) -> &EntityReserver {
+        &self.reserver
+    }
+
+    /// Go back to the entities in `other`, keeping the allocator shared with commands.
+    /// No slot goes back to a generation handed out here or in `other`, so IDs of entities that
+    /// were created since `other` was copied, or removed before, stay stale.
+    pub(crate) fn restore(&mut self, other: &Entities) {
+        let mut allocator = self.reserver.allocator.lock().unwrap();
+        let other_allocator = other.reserver.allocator.lock().unwrap();
+        let len = allocator.len.max(other_allocator.len);
+        let available = allocator.available_ids.iter().copied().collect();
+        let other_available = other_allocator.available_ids.iter().copied().collect();
+
+        let entities = (0..len)
+            .map(|index| {
+                let next_generation = self
+                    .next_unused_generation(index, allocator.len, &available)
+                    .max(other.next_unused_generation(
+                        index,
+                        other_allocator.len,
+                        &other_available,
+                    ));
+                match other.entities.get(index) {
+                    Some(entity) if entity.is_alive() => {
+                        let mut entity = entity.clone();
+                        entity.set_next_generation(next_generation);
+                        entity
+                    }
+                    _ => Entity::free(next_generation),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        // Indexes that were free in `other` are reused in the same order, before the rest.
+        let mut queued = vec![false; len];
+        let mut available_ids = VecDeque::new();
+        let free_indexes = other_allocator
+            .available_ids
+            .iter()
+            .map(|entity_id| entity_id.index())
+            .chain(0..len);
+        for index in free_indexes {
+            if !entities[index].is_alive() && !queued[index] {
+                queued[index] = true;
+                available_ids.push_back(EntityId::new(index, entities[index].generation()));
+            }
         }
 
-        let type_id = TypeId::of::<(T1, T2)>();
-        let needs_update =
-            if let std::collections::hash_map::Entry::Vacant(e) = self.caching_map.entry(type_id) {
-                e.insert(Vec::new());
-                true
-            } else {
-                let updated_frame = *self.updated_map.get(&type_id).unwrap();
-                manager.get_updated_frame::<T1>() != updated_frame
-                    || manager.get_updated_frame::<T2>() != updated_frame
-            };
+        allocator.available_ids = available_ids;
+        allocator.len = len;
+        drop(allocator);
+        self.entities = entities;
+    }
 
-        if needs_update {
-            // TODO: Can be optimized if iterating a shorter array
-            let src = &manager.borrow_entity_ids::<T1>().unwrap();
-            let manager2 = manager.borrow_component_manager::<T2>();
-            let dst = self.caching_map.get_mut(&type_id).unwrap();
-            dst.clear();
-            for id in src.iter() {
-                if manager2.component_exists(*id) {
-                    dst.push(*id);
+    /// Get the lowest generation of a slot that no ID was handed out with.
+    fn next_unused_generation(
+        &self,
+        index: usize,
+        len: usize,
+        available_ids: &HashSet<EntityId>,
+    ) -> u32 {
+        match self.entities.get(index) {
+            Some(entity) if entity.is_alive() => entity.next_generation(),
+            // A free slot's ID can be reserved without being created yet.
+            Some(entity) => {
+                match available_ids.contains(&EntityId::new(index, entity.generation())) {
+                    true => entity.generation(),
+                    false => entity.generation().wrapping_add(1),
                 }
             }
-            self.updated_map.insert(type_id, manager.get_frame());
+            // Reserved in the first generation, but not created yet.
+            None if index < len => 1,
+            None => 0,
         }
+    }
 
-        self.caching_map.get(&type_id)
+    /// Make room for `additional` more entities on top of the removed ones that can be reused.
+    pub(crate) fn reserve(&mut self, additional: usize) {
+        let available = self.reserver.allocator.lock().unwrap().available_ids.len();
+        self.entities.reserve(additional.saturating_sub(available));
     }
 
-    pub fn borrow_ids_for_triple<
-        T1: 'static + Component,
-        T2: 'static + Component,
-        T3: 'static + Component,
-    >(
-        &mut self,
-        manager: &EntityManager,
-    ) -> Option<&Vec<usize>> {
-        if !manager.has_component_manager::<T1>()
-            || !manager.has_component_manager::<T2>()
-            || !manager.has_component_manager::<T3>()
-        {
-            return None;
+    pub(crate) fn ids(&self) -> Vec<EntityId> {
+        self.entities
+            .iter()
+            .enumerate()
+            .filter(|(_, entity)| entity.is_alive())
+            .map(|(index, entity)| EntityId::new(index, entity.generation()))
+            .collect()
+    }
+
+    /// Get the entities that are alive and enabled.
+    pub(crate) fn enabled_ids(&self) -> Vec<EntityId> {
+        self.entities
+            .iter()
+            .enumerate()
+            .filter(|(_, entity)| entity.is_alive() && entity.is_enabled())
+            .map(|(index, entity)| EntityId::new(index, entity.generation()))
+            .collect()
+    }
+
+    pub(crate) fn is_enabled(&self, entity_id: EntityId) -> bool {
+        self.entity_exists(entity_id) && self.entities[entity_id.index()].is_enabled()
+    }
+
+    pub(crate) fn set_enabled(&mut self, entity_id: EntityId, enabled: bool) {
+        if !self.entity_exists(entity_id) {
+            error!("Can't enable or disable this ID, it is not a used ID.");
+            return;
         }
 
-        let type_id = TypeId::of::<(T1, T2, T3)>();
-        let needs_update =
-            if let std::collections::hash_map::Entry::Vacant(e) = self.caching_map.entry(type_id) {
-                e.insert(Vec::new());
-                true
-            } else {
-                let updated_frame = *self.updated_map.get(&type_id).unwrap();
-                manager.get_updated_frame::<T1>() != updated_frame
-                    || manager.get_updated_frame::<T2>() != updated_frame
-                    || manager.get_updated_frame::<T3>() != updated_frame
-            };
+        let entity = &mut self.entities[entity_id.index()];
+        match enabled {
+            true => entity.enable(),
+            false => entity.disable(),
+        }
+    }
 
-        if needs_update {
-            // TODO: Can be optimized if iterating the shortest array
-            let src = &manager.borrow_entity_ids::<T1>().unwrap();
-            let manager2 = manager.borrow_component_manager::<T2>();
-            let manager3 = manager.borrow_component_manager::<T3>();
-            let dst = self.caching_map.get_mut(&type_id).unwrap();
-            dst.clear();
-            for id in src.iter() {
-                if manager2.component_exists(*id) && manager3.component_exists(*id) {
-                    dst.push(*id);
-                }
-            }
-            self.updated_map.insert(type_id, manager.get_frame());
+    pub(crate) fn remove(&mut self, entity_id: EntityId) {
+        if !self.entity_exists(entity_id) {
+            error!("Can't remove this ID, it is not a used ID.");
+            return;
         }
 
-        self.caching_map.get(&type_id)
+        let entity = &mut self.entities[entity_id.index()];
+        entity.destroy();
+        self.reserver
+            .allocator
+            .lock()
+            .unwrap()
+            .available_ids
+            .push_back(EntityId::new(entity_id.index(), entity.generation()));
+    }
+}
+
+/// The component types a cached query needs, and the ones it rules out.
+type QueryKey = (Vec<TypeId>, Vec<TypeId>);
+
+/// Caches the entities matching each query, so systems don't have to look them up every frame.
+/// Every system gets its own accessor, which also remembers the frame the system last ran in
+/// for the `Added` and `Changed` filters.
+#[derive(Default)]
+pub struct EntityIdAccessor {
+    caching_map: HashMap<QueryKey, Vec<EntityId>>,
+    updated_map: HashMap<QueryKey, u64>,
+    last_run: Option<u64>,
+}
+
+impl EntityIdAccessor {
+    /// Query the entities that have every component in `Q`, along with those components.
+    /// This takes in a reference or a tuple of references, e.g. `(&Velocity, &mut Position)`.
+    pub fn query<'a, Q: QueryData>(
+        &'a mut self,
+        manager: &'a mut EntityManager,
+    ) -> Result<QueryIter<'a, Q>, EcsError> {
+        self.query_filtered::<Q, ()>(manager)
+    }
+
+    /// Query the entities that have every component in `Q` and match the filter `F`, e.g.
+    /// `query_filtered::<&Transform, Changed<Transform>>` for the transforms that changed since
+    /// the system last ran.
+    pub fn query_filtered<'a, Q: QueryData, F: QueryFilter>(
+        &'a mut self,
+        manager: &'a mut EntityManager,
+    ) -> Result<QueryIter<'a, Q, F>, EcsError> {
+        self.query_shared::<Q, F>(manager)
+    }
+
+    /// Query through a shared reference to the manager.
+    /// Every component borrow is tracked, so this is what systems running in parallel use.
+    pub(crate) fn query_shared<'a, Q: QueryData, F: QueryFilter>(
+        &'a mut self,
+        manager: &'a EntityManager,
+    ) -> Result<QueryIter<'a, Q, F>, EcsError> {
+        let last_run = self.last_run;
+        let entity_ids = self.borrow_ids(
+            manager,
+            &query_component_types::<Q, F>(),
+            &query_excluded_component_types::<F>(),
+        )?;
+
+        Ok(QueryIter::new(
+            Cow::Borrowed(entity_ids.as_slice()),
+            Q::init_fetch(manager)?,
+            F::init_fetch(manager, last_run)?,
+        ))
+    }
+
+    /// Get the entities that had a `T` component removed since the system last ran.
+    pub fn removed<'a, T: 'static + Component>(
+        &self,
+        manager: &'a EntityManager,
+    ) -> impl Iterator<Item = EntityId> + 'a {
+        manager.removed_components::<T>(self.last_run)
+    }
+
+    /// Get the frame the system using this accessor last ran in.
+    pub fn last_run(&self) -> Option<u64> {
+        self.last_run
     }
 
-    pub fn borrow_ids_for_quad<
-        T1: 'static + Component,
-        T2: 'static + Component,
-        T3: 'static + Component,
-        T4: 'static + Component,
-    >(
+    pub(crate) fn set_last_run(&mut self, frame: u64) {
+        self.last_run = Some(frame);
+    }
+
+    fn borrow_ids(
         &mut self,
         manager: &EntityManager,
-    ) -> Option<&Vec<usize>> {
-        if !manager.has_component_manager::<T1>()
-            || !manager.has_component_manager::<T2>()
-            || !manager.has_component_manager::<T3>()
-            || !manager.has_component_manager::<T4>()
-        {
-            return None;
-        }
-
-        let type_id = TypeId::of::<(T1, T2, T3, T4)>();
-        let needs_update =
-            if let std::collections::hash_map::Entry::Vacant(e) = self.caching_map.entry(type_id) {
-                e.insert(Vec::new());
-                true
-            } else {
-                let updated_frame = *self.updated_map.get(&type_id).unwrap();
-                manager.get_updated_frame::<T1>() != updated_frame
-                    || manager.get_updated_frame::<T2>() != updated_frame
-                    || manager.get_updated_frame::<T3>() != updated_frame
-                    || manager.get_updated_frame::<T4>() != updated_frame
-            };
+        types: &[(TypeId, &'static str)],
+        excluded_types: &[(TypeId, &'static str)],
+    ) -> Result<&Vec<EntityId>, EcsError> {
+        let type_ids = |types: &[(TypeId, &'static str)]| {
+            types
+                .iter()
+                .map(|(type_id, _)| *type_id)
+                .collect::<Vec<_>>()
+        };
+        let key = (type_ids(types), type_ids(excluded_types));
+
+        // Adding or removing an excluded component changes the matching entities just as much.
+        // A query that needs no components matches every entity, so it changes along with them.
+        let entity_type = TypeId::of::<Entity>();
+        let needs_update = match self.updated_map.get(&key) {
+            Some(version) => key
+                .0
+                .iter()
+                .chain(key.1.iter())
+                .chain(key.0.is_empty().then_some(&entity_type))
+                .any(|type_id| manager.get_structure_version(type_id) > *version),
+            None => true,
+        };
 
         if needs_update {
-            // TODO: Can be optimized if iterating the shortest array
-            let src = &manager.borrow_entity_ids::<T1>().unwrap();
-            let manager2 = manager.borrow_component_manager::<T2>();
-            let manager3 = manager.borrow_component_manager::<T3>();
-            let manager4 = manager.borrow_component_manager::<T4>();
-            let dst = self.caching_map.get_mut(&type_id).unwrap();
-            dst.clear();
-            for id in src.iter() {
-                if manager2.component_exists(*id)
-                    && manager3.component_exists(*id)
-                    && manager4.component_exists(*id)
-                {
-                    dst.push(*id);
-                }
-            }
-            self.updated_map.insert(type_id, manager.get_frame());
+            let entity_ids = manager.matching_entity_ids(types, excluded_types)?;
+            self.updated_map
+                .insert(key.clone(), manager.structure_version);
+            self.caching_map.insert(key.clone(), entity_ids);
         }
 
-        self.caching_map.get(&type_id)
+        Ok(self.caching_map.get(&key).unwrap())
     }
 }
 
 pub struct EntityManager {
     entities: Entities,
-    manager_map: HashMap<TypeId, Box<dyn ComponentManagerT>>,
-    frame: u64,                              // Rename
-    updated_frame_map: HashMap<TypeId, u64>, // Rename
+    manager_map: HashMap<TypeId, ComponentCell>,
+    frame: u64,
+    /// Counts every time a component is added or removed, so cached queries know when the
+    /// entities with a component changed.
+    structure_version: u64,
+    structure_version_map: HashMap<TypeId, u64>,
+    /// Commands recorded by component hooks, waiting to be applied.
+    hook_commands: Commands,
+    /// While above 0, an outer operation is still running and applies the hook commands once
+    /// it's done.
+    defer_hook_commands: u32,
+    /// Looks up entities by their `Name` and `Tags`.
+    name_index: NameIndex,
+    /// Triggers sent through commands, waiting for the world to run their observers.
+    pending_triggers: VecDeque<PendingTrigger>,
+    /// Resource changes sent through commands, waiting for the world to make them.
+    resource_changes: VecDeque<ResourceChange>,
+    /// Drops the relationships of a removed entity, for every relationship type in use.
+    relationship_cleanups: HashMap<TypeId, fn(&mut EntityManager, EntityId)>,
 }
 
 impl Default for EntityManager {
     fn default() -> Self {
-        Self {
-            entities: Entities::new(),
+        let entities = Entities::new();
+        let hook_commands = Commands::new(entities.reserver().clone());
+        let mut entity_manager = Self {
+            entities,
             manager_map: HashMap::new(),
 
             frame: 0,
 
-            updated_frame_map: HashMap::new(),
-        }
+            structure_version: 0,
+            structure_version_map: HashMap::new(),
+
+            hook_commands,
+            defer_hook_commands: 0,
+
+            name_index: NameIndex::default(),
+            pending_triggers: VecDeque::new(),
+            resource_changes: VecDeque::new(),
+            relationship_cleanups: HashMap::new(),
+        };
+
+        entity_manager
+            .register_cloneable::<Parent>()
+            .register_cloneable::<Children>()
+            .register_cloneable::<Name>()
+            .register_cloneable::<Tags>();
+
+        entity_manager
     }
 }
 
@@ -245,290 +436,981 @@ impl EntityManager {
         self.frame += 1;
     }
 
-    fn get_frame(&self) -> u64 {
+    /// Get the current frame, which components are marked as added, changed or removed in.
+    pub fn get_frame(&self) -> u64 {
         self.frame
     }
 
-    fn get_updated_frame<T: 'static + Component>(&self) -> u64 {
-        *self.updated_frame_map.get(&TypeId::of::<T>()).unwrap()
+    fn get_structure_version(&self, type_id: &TypeId) -> u64 {
+        self.structure_version_map
+            .get(type_id)
+            .copied()
+            .unwrap_or_default()
     }
 
+    fn bump_structure_version(&mut self, type_id: TypeId) {
+        self.structure_version += 1;
+        self.structure_version_map
+            .insert(type_id, self.structure_version);
+    }
+
+    /// Register a component, stored the way `T::STORAGE` asks for.
     pub fn register<T: 'static + Component>(&mut self) -> &mut Self {
+        self.register_with_storage::<T>(T::STORAGE)
+    }
+
+    /// Register a component with a certain storage kind.
+    pub fn register_with_storage<T: 'static + Component>(
+        &mut self,
+        kind: StorageKind,
+    ) -> &mut Self {
         // TODO: Error handling if already registered?
         if !self.has_component_manager::<T>() {
             let type_id = TypeId::of::<T>();
-            self.manager_map
-                .insert(type_id, Box::new(ComponentManager::<T>::new()));
-            self.updated_frame_map.insert(type_id, self.get_frame());
+            self.manager_map.insert(
+                type_id,
+                ComponentCell::new(Box::new(ComponentManager::<T>::with_storage(kind))),
+            );
         }
         self
     }
 
-    pub fn create_entity(&mut self) -> usize {
+    /// Register a component that is included in snapshots, stored the way `T::STORAGE` asks
+    /// for. If it's already registered, it's included from now on.
+    pub fn register_cloneable<T: 'static + Component + Clone>(&mut self) -> &mut Self {
+        self.register::<T>();
+        self.borrow_component_manager_mut::<T>().set_cloneable();
+        self
+    }
+
+    /// Register a component the way it is in another entity manager, if it isn't registered.
+    pub(crate) fn register_like<T: 'static + Component>(&mut self, manager: &ComponentManager<T>) {
+        if !self.has_component_manager::<T>() {
+            self.manager_map.insert(
+                TypeId::of::<T>(),
+                ComponentCell::new(Box::new(manager.new_like())),
+            );
+        }
+    }
+
+    /// Set the hooks that run when a `T` component is added, replaced or removed, replacing the
+    /// previous ones.
+    pub fn set_component_hooks<T: 'static + Component>(
+        &mut self,
+        hooks: ComponentHooks<T>,
+    ) -> &mut Self {
+        if !self.has_component_manager::<T>() {
+            error!(
+                "Failed to set the hooks of {}. It is not registered.",
+                type_name::<T>()
+            );
+            return self;
+        }
+
+        self.borrow_component_manager_mut::<T>().set_hooks(hooks);
+        self
+    }
+
+    pub fn create_entity(&mut self) -> EntityId {
+        self.bump_structure_version(TypeId::of::<Entity>());
         self.entities.create()
     }
 
-    pub fn remove_entity(&mut self, entity_id: usize) {
+    /// Create an entity with an ID reserved by `Commands::spawn`.
+    pub(crate) fn spawn_reserved(&mut self, entity_id: EntityId) {
+        if !self.entities.create_reserved(entity_id) {
+            error!("Failed to spawn entity {entity_id}. Its ID was not reserved by this entity manager.");
+            return;
+        }
+        self.bump_structure_version(TypeId::of::<Entity>());
+    }
+
+    /// Get commands that can be applied to this entity manager.
+    pub fn commands(&self) -> Commands {
+        Commands::new(self.entities.reserver().clone())
+    }
+
+    pub(crate) fn entity_ids(&self) -> Vec<EntityId> {
+        self.entities.ids()
+    }
+
+    pub fn entity_exists(&self, entity_id: EntityId) -> bool {
+        self.entities.entity_exists(entity_id)
+    }
+
+    /// Check if an entity exists and is enabled.
+    pub fn is_entity_enabled(&self, entity_id: EntityId) -> bool {
+        self.entities.is_enabled(entity_id)
+    }
+
+    /// Find an entity by its `Name`. If several entities have the name, the one that got it first
+    /// is returned. Disabled entities are found too.
+    pub fn find_by_name(&self, name: &str) -> Option<EntityId> {
+        self.name_index.entities_named(name).find(|entity_id| {
+            self.get_component_quietly::<Name>(*entity_id)
+                .is_some_and(|entity_name| entity_name.as_str() == name)
+        })
+    }
+
+    /// Get the entities tagged with `tag`, ordered by ID. Disabled entities are included.
+    pub fn entities_with_tag<'a>(&'a self, tag: &'a str) -> impl Iterator<Item = EntityId> + 'a {
+        self.name_index
+            .entities_with_tag(tag)
+            .filter(move |entity_id| {
+                self.get_component_quietly::<Tags>(*entity_id)
+                    .is_some_and(|tags| tags.contains(tag))
+            })
+    }
+
+    /// Tag an entity, giving it a `Tags` component if it doesn't have one.
+    pub fn add_tag(&mut self, entity_id: EntityId, tag: impl Into<String>) -> &mut Self {
+        if !self.entity_exists(entity_id) {
+            error!("Failed to tag entity {entity_id}. It does not exist.");
+            return self;
+        }
+
+        let mut tags = self
+            .get_component_quietly::<Tags>(entity_id)
+            .cloned()
+            .unwrap_or_default();
+        if tags.insert(tag.into()) {
+            self.insert_component(entity_id, tags);
+        }
+        self
+    }
+
+    /// Remove a tag from an entity. The entity keeps its `Tags` component even if it's empty.
+    pub fn remove_tag(&mut self, entity_id: EntityId, tag: &str) -> &mut Self {
+        let Some(mut tags) = self.get_component_quietly::<Tags>(entity_id).cloned() else {
+            return self;
+        };
+
+        if tags.remove(tag) {
+            self.insert_component(entity_id, tags);
+        }
+        self
+    }
+
+    /// Check if an entity is tagged with `tag`.
+    pub fn has_tag(&self, entity_id: EntityId, tag: &str) -> bool {
+        self.get_component_quietly::<Tags>(entity_id)
+            .is_some_and(|tags| tags.contains(tag))
+    }
+
+    /// Copy every entity along with its cloneable components.
+    /// Components registered without `register_cloneable` are left out.
+    pub fn snapshot(&self) -> Snapshot {
+        let storages = self
+            .manager_map
+            .iter()
+            .filter_map(|(type_id, manager)| Some((*type_id, manager.get().snapshot()?)))
+            .collect();
+
+        Snapshot::new(SnapshotData {
+            entities: self.entities.clone(),
+            storages,
+        })
+    }
+
+    /// Bring the entities and cloneable components back to how they were in a snapshot.
+    /// Entities that didn't exist in the snapshot are removed, and the ones that did keep the
+    /// components that aren't in snapshots. Cloneable components registered after the snapshot
+    /// was taken are removed. Restored components are marked as changed.
+    /// Hooks run for every component that's dropped or brought back, as if it was removed,
+    /// replaced or added, and their commands are applied once everything is restored. Entities
+    /// that those commands spawn are kept, so the result can hold entities the snapshot didn't.
+    pub fn restore(&mut self, snapshot: &Snapshot) {
+        let frame = self.get_frame();
+        let data = snapshot.data();
+        self.entities.restore(&data.entities);
+
+        let mut changed_types = vec![TypeId::of::<Entity>()];
+        for manager in self.manager_map.values_mut() {
+            let manager = manager.get_mut();
+            match data.storages.get(&manager.get_type_id()) {
+                Some(storage) => manager.restore(storage.as_ref(), frame, &mut self.hook_commands),
+                // Cloneable components that aren't in the snapshot were registered after it was
+                // taken, like the components of a relationship that was first used since.
+                None if manager.is_cloneable() => manager.clear(frame, &mut self.hook_commands),
+                None => {
+                    for entity_id in manager.all_entity_ids() {
+                        match self.entities.entity_exists(entity_id) {
+                            true => {
+                                manager.set_enabled(entity_id, self.entities.is_enabled(entity_id))
+                            }
+                            false => manager.remove(entity_id, frame, &mut self.hook_commands),
+                        }
+                    }
+                }
+            }
+            changed_types.push(manager.get_type_id());
+        }
+        for type_id in changed_types {
+            self.bump_structure_version(type_id);
+        }
+
+        self.rebuild_name_index();
+        self.apply_hook_commands();
+    }
+
+    /// Fill the name and tag lookup from scratch.
+    fn rebuild_name_index(&mut self) {
+        let mut name_index = NameIndex::default();
+        if let Some(names) = self.try_borrow_component_manager_untracked::<Name>() {
+            for entity_id in names.all_entity_ids() {
+                if let Some(name) = names.borrow_component(entity_id) {
+                    name_index.insert_name(name, entity_id);
+                }
+            }
+        }
+        if let Some(tags) = self.try_borrow_component_manager_untracked::<Tags>() {
+            for entity_id in tags.all_entity_ids() {
+                if let Some(entity_tags) = tags.borrow_component(entity_id) {
+                    name_index.insert_tags(entity_tags, entity_id);
+                }
+            }
+        }
+        self.name_index = name_index;
+    }
+
+    /// Get an entity ID along with the entity's name, for logging.
+    pub(crate) fn entity_label(&self, entity_id: EntityId) -> EntityLabel<'_> {
+        EntityLabel {
+            entity_id,
+            name: self.get_component_quietly::<Name>(entity_id),
+        }
+    }
+
+    /// Log an error and get `true` if `T` is `Parent` or `Children`, which only `set_parent`
+    /// and `remove_parent` can change, so the two always agree.
+    fn rejects_hierarchy_edit<T: 'static>(&self, action: &str, entity_id: EntityId) -> bool {
+        if !is_hierarchy::<T>() {
+            return false;
+        }
+        error!(
+            "Failed to {action} the {} of entity {}. Use `set_parent` or `remove_parent`.",
+            type_name::<T>(),
+            self.entity_label(entity_id)
+        );
+        true
+    }
+
+    fn log_missing_component<T: 'static + Component>(&self, entity_id: EntityId) {
+        error!(
+            "Entity {} does not have a {}.",
+            self.entity_label(entity_id),
+            type_name::<T>()
+        );
+    }
+
+    /// Keep the name and tag lookup up to date when a `T` component of an entity is added or
+    /// replaced (`added` is `true`), or is about to be replaced or removed (`false`).
+    fn update_name_index<T: 'static + Component>(&mut self, entity_id: EntityId, added: bool) {
+        let type_id = TypeId::of::<T>();
+        if type_id == TypeId::of::<Name>() {
+            let Some(name) = self.get_component_quietly::<Name>(entity_id).cloned() else {
+                return;
+            };
+            match added {
+                true => self.name_index.insert_name(&name, entity_id),
+                false => self.name_index.remove_name(&name, entity_id),
+            }
+        } else if type_id == TypeId::of::<Tags>() {
+            let Some(tags) = self.get_component_quietly::<Tags>(entity_id).cloned() else {
+                return;
+            };
+            match added {
+                true => self.name_index.insert_tags(&tags, entity_id),
+                false => self.name_index.remove_tags(&tags, entity_id),
+            }
+        }
+    }
+
+    /// Enable or disable an entity.
+    /// Disabled entities keep their components, but are skipped by queries, `query_entity` and
+    /// `borrow_components` until they're enabled again. `borrow_component` still reaches them by
+    /// their ID.
+    pub fn set_entity_enabled(&mut self, entity_id: EntityId, enabled: bool) -> &mut Self {
+        if !self.entity_exists(entity_id) {
+            error!(
+                "Failed to enable or disable entity {}. It does not exist.",
+                self.entity_label(entity_id)
+            );
+            return self;
+        }
+        if self.is_entity_enabled(entity_id) == enabled {
+            return self;
+        }
+
+        self.entities.set_enabled(entity_id, enabled);
+        let mut changed_types = vec![TypeId::of::<Entity>()];
+        for manager in self.manager_map.values_mut() {
+            let manager = manager.get_mut();
+            if manager.component_exists(entity_id) {
+                manager.set_enabled(entity_id, enabled);
+                changed_types.push(manager.get_type_id());
+            }
+        }
+        // The entity leaves or joins every query matching its components.
+        for type_id in changed_types {
+            self.bump_structure_version(type_id);
+        }
+
+        self
+    }
+
+    /// Remove an entity, along with its children and their children.
+    pub fn remove_entity(&mut self, entity_id: EntityId) {
+        if !self.entity_exists(entity_id) {
+            error!("Failed to remove entity {entity_id}. It does not exist.");
+            return;
+        }
+        self.update_name_index::<Name>(entity_id, false);
+        self.update_name_index::<Tags>(entity_id, false);
+
+        // Hooks run as the components are removed, but their commands wait until the entity
+        // and all of its children are gone.
+        self.defer_hook_commands += 1;
+
+        let cleanups: Vec<_> = self.relationship_cleanups.values().copied().collect();
+        for cleanup in cleanups {
+            cleanup(self, entity_id);
+        }
+
+        self.remove_parent(entity_id);
+        if let Some(children) = self.get_component_quietly::<Children>(entity_id) {
+            for child in children.clone().iter() {
+                self.remove_entity(*child);
+            }
+        }
+
         let frame = self.get_frame();
-        for (_, manager) in self.manager_map.iter_mut() {
+        let mut removed_types = Vec::new();
+        for manager in self.manager_map.values_mut() {
+            let manager = manager.get_mut();
             if manager.component_exists(entity_id) {
-                manager.remove(entity_id);
-                self.updated_frame_map
-                    .insert(manager.get_type_id(), frame + 1);
+                manager.remove(entity_id, frame, &mut self.hook_commands);
+                removed_types.push(manager.get_type_id());
             }
         }
+        for type_id in removed_types {
+            self.bump_structure_version(type_id);
+        }
         self.entities.remove(entity_id);
+        self.bump_structure_version(TypeId::of::<Entity>());
+
+        self.defer_hook_commands -= 1;
+        self.apply_hook_commands();
+    }
+
+    /// Move an entity to another entity manager, along with all of its components and
+    /// children, and get its ID there.
+    /// The entity is detached from its parent, and its relationships are dropped since the
+    /// other entities stay behind. Components that aren't registered in `target` are registered
+    /// the way they are here, without their hooks.
+    pub fn move_entity(
+        &mut self,
+        entity_id: EntityId,
+        target: &mut EntityManager,
+    ) -> Option<EntityId> {
+        if !self.entity_exists(entity_id) {
+            error!("Failed to move entity {entity_id}. It does not exist.");
+            return None;
+        }
+        self.update_name_index::<Name>(entity_id, false);
+        self.update_name_index::<Tags>(entity_id, false);
+
+        self.defer_hook_commands += 1;
+
+        let cleanups: Vec<_> = self.relationship_cleanups.values().copied().collect();
+        for cleanup in cleanups {
+            cleanup(self, entity_id);
+        }
+
+        // The children detach as they move, and are attached again on the other side.
+        self.remove_parent(entity_id);
+        let children = self
+            .get_component_quietly::<Children>(entity_id)
+            .cloned()
+            .unwrap_or_default();
+        let moved_children: Vec<_> = children
+            .iter()
+            .filter_map(|child| self.move_entity(*child, target))
+            .collect();
+
+        let moved_id = target.create_entity();
+        target.set_entity_enabled(moved_id, self.is_entity_enabled(entity_id));
+
+        let frame = self.get_frame();
+        let mut moved_types = Vec::new();
+        for manager in self.manager_map.values_mut() {
+            let manager = manager.get_mut();
+            if manager.component_exists(entity_id) {
+                manager.move_to(entity_id, frame, &mut self.hook_commands, target, moved_id);
+                moved_types.push(manager.get_type_id());
+            }
+        }
+        for type_id in moved_types {
+            self.bump_structure_version(type_id);
+        }
+        self.entities.remove(entity_id);
+        self.bump_structure_version(TypeId::of::<Entity>());
+
+        for child in moved_children {
+            target.set_parent(child, moved_id);
+        }
+
+        self.defer_hook_commands -= 1;
+        self.apply_hook_commands();
+
+        Some(moved_id)
     }
 
     pub fn add_component_to_entity<T: 'static + Component>(
         &mut self,
-        entity_id: usize,
+        entity_id: EntityId,
+        component: T,
+    ) -> &mut Self {
+        if !self.rejects_hierarchy_edit::<T>("add", entity_id) {
+            self.add_component(entity_id, component);
+        }
+        self
+    }
+
+    /// Add a component to an entity, even if it's part of the hierarchy.
+    fn add_component<T: 'static + Component>(
+        &mut self,
+        entity_id: EntityId,
         component: T,
     ) -> &mut Self {
         if !self.has_component_manager::<T>() {
-            // @TODO: Better error handling
-            error!("Failed to add component to entity. It does not exist.");
+            error!(
+                "Failed to add component to entity {}. {} is not registered.",
+                self.entity_label(entity_id),
+                type_name::<T>()
+            );
+            return self;
+        }
+        if !self.entity_exists(entity_id) {
+            error!("Failed to add component to entity {entity_id}. The entity does not exist.");
             return self;
         }
-        self.borrow_component_manager_mut::<T>()
-            .add(entity_id, component);
-        self.updated_frame_map
-            .insert(TypeId::of::<T>(), self.get_frame());
+        if self.get_component_quietly::<T>(entity_id).is_some() {
+            error!(
+                "Failed to add component to entity {}. It already has a {}.",
+                self.entity_label(entity_id),
+                type_name::<T>()
+            );
+            return self;
+        }
+        let frame = self.get_frame();
+        let enabled = self.is_entity_enabled(entity_id);
+        let (manager, commands) = self.component_manager_and_hook_commands::<T>();
+        manager.add(entity_id, component, frame, commands);
+        if !enabled {
+            manager.set_enabled(entity_id, false);
+        }
+        self.bump_structure_version(TypeId::of::<T>());
+        self.update_name_index::<T>(entity_id, true);
+        self.apply_hook_commands();
 
         self
     }
 
-    fn borrow_entity_ids<T: 'static + Component>(&self) -> Option<&Vec<usize>> {
+    /// Add a component to an entity, replacing the one it already has.
+    pub fn insert_component<T: 'static + Component>(
+        &mut self,
+        entity_id: EntityId,
+        component: T,
+    ) -> &mut Self {
+        if self.rejects_hierarchy_edit::<T>("insert", entity_id) {
+            return self;
+        }
         if !self.has_component_manager::<T>() {
-            error!("Failed to get component from entity. It does not exist.");
-            println!("Unknown component");
-            return None;
+            error!(
+                "Failed to insert component into entity {}. {} is not registered.",
+                self.entity_label(entity_id),
+                type_name::<T>()
+            );
+            return self;
+        }
+        if !self.entity_exists(entity_id) {
+            error!(
+                "Failed to insert component into entity {entity_id}. The entity does not exist."
+            );
+            return self;
         }
-        Some(self.borrow_component_manager::<T>().borrow_entity_ids())
+        self.update_name_index::<T>(entity_id, false);
+        let frame = self.get_frame();
+        let enabled = self.is_entity_enabled(entity_id);
+        let (manager, commands) = self.component_manager_and_hook_commands::<T>();
+        let added = !manager.component_exists(entity_id);
+        manager.insert(entity_id, component, frame, commands);
+        if added && !enabled {
+            manager.set_enabled(entity_id, false);
+        }
+        if added {
+            self.bump_structure_version(TypeId::of::<T>());
+        }
+        self.update_name_index::<T>(entity_id, true);
+        self.apply_hook_commands();
+
+        self
     }
 
-    pub fn borrow_component<T: 'static + Component>(&self, entity_id: usize) -> Option<&T> {
-        match self.has_component_manager::<T>() {
-            true => self
-                .borrow_component_manager::<T>()
-                .borrow_component(entity_id),
-            false => None,
-        }
+    /// Spawn an entity with every component in a bundle.
+    pub fn spawn<B: Bundle>(&mut self, bundle: B) -> EntityId {
+        let entity_id = self.create_entity();
+        bundle.add_to_entity(self, entity_id);
+        entity_id
     }
 
-    pub fn borrow_component_mut<T: 'static + Component>(
+    /// Spawn an entity for every bundle, making room for all of them up front.
+    /// Hook commands are applied once every entity has been spawned.
+    pub fn spawn_batch<B: Bundle>(
         &mut self,
-        entity_id: usize,
-    ) -> Option<&mut T> {
-        match self.has_component_manager::<T>() {
-            true => self
-                .borrow_component_manager_mut::<T>()
-                .borrow_component_mut(entity_id),
-            false => None,
+        bundles: impl IntoIterator<Item = B>,
+    ) -> Vec<EntityId> {
+        let bundles = bundles.into_iter();
+        let count = bundles.size_hint().0;
+        self.entities.reserve(count);
+        B::reserve(self, count);
+
+        self.defer_hook_commands += 1;
+        let entity_ids = bundles.map(|bundle| self.spawn(bundle)).collect();
+        self.defer_hook_commands -= 1;
+        self.apply_hook_commands();
+
+        entity_ids
+    }
+
+    /// Remove every entity in `entity_ids`, along with their children.
+    /// Entities that are already gone, like children of entities earlier in the batch, are
+    /// skipped. Hook commands are applied once every entity has been removed.
+    pub fn despawn_batch(&mut self, entity_ids: impl IntoIterator<Item = EntityId>) {
+        self.defer_hook_commands += 1;
+        for entity_id in entity_ids {
+            if self.entity_exists(entity_id) {
+                self.remove_entity(entity_id);
+            }
         }
+        self.defer_hook_commands -= 1;
+        self.apply_hook_commands();
     }
 
-    pub fn borrow_components<T: 'static + Component>(&self) -> Option<&Vec<T>> {
-        match self.has_component_manager::<T>() {
-            true => Some(self.borrow_component_manager::<T>().borrow_components()),
-            false => None,
+    /// Make room for `additional` more `T` components.
+    pub fn reserve_components<T: 'static + Component>(&mut self, additional: usize) -> &mut Self {
+        if self.has_component_manager::<T>() {
+            self.borrow_component_manager_mut::<T>().reserve(additional);
         }
+        self
     }
 
-    pub fn borrow_components_mut<T: 'static + Component>(&mut self) -> Option<&mut Vec<T>> {
-        match self.has_component_manager::<T>() {
-            true => Some(
-                self.borrow_component_manager_mut::<T>()
-                    .borrow_components_mut(),
-            ),
-            false => None,
+    /// Make `parent` the parent of `child`, replacing its previous parent.
+    /// This fails if `parent` is `child` or one of its descendants.
+    pub fn set_parent(&mut self, child: EntityId, parent: EntityId) -> &mut Self {
+        if !self.entity_exists(child) || !self.entity_exists(parent) {
+            error!(
+                "Failed to make {} the parent of {}. The entity does not exist.",
+                self.entity_label(parent),
+                self.entity_label(child)
+            );
+            return self;
         }
+
+        let mut ancestor = Some(parent);
+        while let Some(entity_id) = ancestor {
+            if entity_id == child {
+                error!(
+                    "Failed to make {} the parent of {}. It would be its own ancestor.",
+                    self.entity_label(parent),
+                    self.entity_label(child)
+                );
+                return self;
+            }
+            ancestor = self
+                .get_component_quietly::<Parent>(entity_id)
+                .map(Parent::get);
+        }
+
+        self.remove_parent(child);
+        self.add_component(child, Parent::new(parent));
+        match self.get_component_quietly::<Children>(parent).is_some() {
+            true => {
+                if let Some(children) = self.component_mut::<Children>(parent) {
+                    children.push(child);
+                }
+            }
+            false => {
+                let mut children = Children::default();
+                children.push(child);
+                self.add_component(parent, children);
+            }
+        }
+
+        self
     }
 
-    pub fn borrow_components_pair_mut<T1: 'static + Component, T2: 'static + Component>(
-        &mut self,
-    ) -> Option<(&mut Vec<T1>, &mut Vec<T2>)> {
-        if !self.has_component_manager::<T1>() || !self.has_component_manager::<T2>() {
-            return None;
+    /// Detach an entity from its parent, if it has one.
+    pub fn remove_parent(&mut self, child: EntityId) -> &mut Self {
+        let Some(parent) = self.get_component_quietly::<Parent>(child).map(Parent::get) else {
+            return self;
+        };
+
+        self.remove_component::<Parent>(child);
+        if let Some(children) = self.component_mut::<Children>(parent) {
+            children.remove(child);
+            if children.is_empty() {
+                self.remove_component::<Children>(parent);
+            }
         }
 
-        let type_id1 = TypeId::of::<T1>();
-        let type_id2 = TypeId::of::<T2>();
+        self
+    }
+
+    /// Relate `source` to `target` by `R`, like a ship that is docked at a station.
+    /// For an exclusive relationship this replaces the entity `source` was related to.
+    pub fn relate<R: Relationship>(&mut self, source: EntityId, target: EntityId) -> &mut Self {
+        if !self.entity_exists(source) || !self.entity_exists(target) {
+            error!(
+                "Failed to relate {} to {} by {}. The entity does not exist.",
+                self.entity_label(source),
+                self.entity_label(target),
+                type_name::<R>()
+            );
+            return self;
+        }
+        self.register_relationship::<R>();
+        if self.is_related::<R>(source, target) {
+            return self;
+        }
 
-        let manager1 = cast_manager_mut_unsafe(self.manager_map.get(&type_id1).unwrap());
-        let manager2 = cast_manager_mut_unsafe(self.manager_map.get(&type_id2).unwrap());
+        if R::EXCLUSIVE {
+            let previous: Vec<_> = self.related::<R>(source).collect();
+            for previous in previous {
+                self.unrelate::<R>(source, previous);
+            }
+        }
 
-        Some((
-            manager1.borrow_components_mut(),
-            manager2.borrow_components_mut(),
-        ))
+        match self.get_component_quietly::<Relation<R>>(source).is_some() {
+            true => {
+                if let Some(relation) = self.borrow_component_mut::<Relation<R>>(source) {
+                    relation.push(target);
+                }
+            }
+            false => {
+                let mut relation = Relation::<R>::default();
+                relation.push(target);
+                self.add_component_to_entity(source, relation);
+            }
+        }
+        match self.get_component_quietly::<RelatedBy<R>>(target).is_some() {
+            true => {
+                if let Some(related_by) = self.borrow_component_mut::<RelatedBy<R>>(target) {
+                    related_by.push(source);
+                }
+            }
+            false => {
+                let mut related_by = RelatedBy::<R>::default();
+                related_by.push(source);
+                self.add_component_to_entity(target, related_by);
+            }
+        }
+
+        self
     }
 
-    pub fn borrow_components_triple_mut<
-        T1: 'static + Component,
-        T2: 'static + Component,
-        T3: 'static + Component,
-    >(
-        &mut self,
-    ) -> Option<(&mut Vec<T1>, &mut Vec<T2>, &mut Vec<T3>)> {
-        if !self.has_component_manager::<T1>()
-            || !self.has_component_manager::<T2>()
-            || !self.has_component_manager::<T3>()
-        {
-            return None;
+    /// Stop relating `source` to `target` by `R`, if it is.
+    pub fn unrelate<R: Relationship>(&mut self, source: EntityId, target: EntityId) -> &mut Self {
+        if !self.is_related::<R>(source, target) {
+            return self;
+        }
+
+        if let Some(relation) = self.borrow_component_mut::<Relation<R>>(source) {
+            relation.remove(target);
+            if relation.is_empty() {
+                self.remove_component_from_entity::<Relation<R>>(source);
+            }
+        }
+        if let Some(related_by) = self.borrow_component_mut::<RelatedBy<R>>(target) {
+            related_by.remove(source);
+            if related_by.is_empty() {
+                self.remove_component_from_entity::<RelatedBy<R>>(target);
+            }
         }
 
-        let type_id1 = TypeId::of::<T1>();
-        let type_id2 = TypeId::of::<T2>();
-        let type_id3 = TypeId::of::<T3>();
+        self
+    }
 
-        let manager1 = cast_manager_mut_unsafe(self.manager_map.get(&type_id1).unwrap());
-        let manager2 = cast_manager_mut_unsafe(self.manager_map.get(&type_id2).unwrap());
-        let manager3 = cast_manager_mut_unsafe(self.manager_map.get(&type_id3).unwrap());
+    /// Check if `source` is related to `target` by `R`.
+    pub fn is_related<R: Relationship>(&self, source: EntityId, target: EntityId) -> bool {
+        self.get_component_quietly::<Relation<R>>(source)
+            .is_some_and(|relation| relation.contains(target))
+    }
 
-        Some((
-            manager1.borrow_components_mut(),
-            manager2.borrow_components_mut(),
-            manager3.borrow_components_mut(),
-        ))
+    /// Get the entities `source` is related to by `R`, in the order they were related.
+    pub fn related<R: Relationship>(
+        &self,
+        source: EntityId,
+    ) -> impl Iterator<Item = EntityId> + '_ {
+        self.get_component_quietly::<Relation<R>>(source)
+            .into_iter()
+            .flat_map(|relation| relation.iter().copied())
     }
-    #[allow(clippy::type_complexity)]
-    pub fn borrow_components_quad_mut<
-        T1: 'static + Component,
-        T2: 'static + Component,
-        T3: 'static + Component,
-        T4: 'static + Component,
-    >(
-        &mut self,
-    ) -> Option<(&mut Vec<T1>, &mut Vec<T2>, &mut Vec<T3>, &mut Vec<T4>)> {
-        if !self.has_component_manager::<T1>()
-            || !self.has_component_manager::<T2>()
-            || !self.has_component_manager::<T3>()
-            || !self.has_component_manager::<T4>()
-        {
-            return None;
+
+    /// Get the entities related to `target` by `R`, in the order they were related.
+    pub fn related_to<R: Relationship>(
+        &self,
+        target: EntityId,
+    ) -> impl Iterator<Item = EntityId> + '_ {
+        self.get_component_quietly::<RelatedBy<R>>(target)
+            .into_iter()
+            .flat_map(|related_by| related_by.iter().copied())
+    }
+
+    /// Register the components of a relationship the first time it's used, so a removed
+    /// entity's relationships of this kind are dropped too.
+    fn register_relationship<R: Relationship>(&mut self) {
+        if self.relationship_cleanups.contains_key(&TypeId::of::<R>()) {
+            return;
         }
 
-        let type_id1 = TypeId::of::<T1>();
-        let type_id2 = TypeId::of::<T2>();
-        let type_id3 = TypeId::of::<T3>();
-        let type_id4 = TypeId::of::<T4>();
+        self.register_cloneable::<Relation<R>>()
+            .register_cloneable::<RelatedBy<R>>();
+        self.relationship_cleanups
+            .insert(TypeId::of::<R>(), Self::remove_relationships::<R>);
+    }
 
-        let manager1 = cast_manager_mut_unsafe(self.manager_map.get(&type_id1).unwrap());
-        let manager2 = cast_manager_mut_unsafe(self.manager_map.get(&type_id2).unwrap());
-        let manager3 = cast_manager_mut_unsafe(self.manager_map.get(&type_id3).unwrap());
-        let manager4 = cast_manager_mut_unsafe(self.manager_map.get(&type_id4).unwrap());
+    /// Drop every `R` relationship an entity is on either side of.
+    fn remove_relationships<R: Relationship>(&mut self, entity_id: EntityId) {
+        let targets: Vec<_> = self.related::<R>(entity_id).collect();
+        for target in targets {
+            self.unrelate::<R>(entity_id, target);
+        }
+        let sources: Vec<_> = self.related_to::<R>(entity_id).collect();
+        for source in sources {
+            self.unrelate::<R>(source, entity_id);
+        }
+    }
 
-        Some((
-            manager1.borrow_components_mut(),
-            manager2.borrow_components_mut(),
-            manager3.borrow_components_mut(),
-            manager4.borrow_components_mut(),
-        ))
+    /// Borrow a component without logging an error if the entity doesn't have it.
+    pub(crate) fn get_component_quietly<T: 'static + Component>(
+        &self,
+        entity_id: EntityId,
+    ) -> Option<&T> {
+        self.try_borrow_component_manager_untracked::<T>()?
+            .borrow_component(entity_id)
     }
 
-    pub fn borrow_component_pair_mut<T1: 'static + Component, T2: 'static + Component>(
+    pub fn remove_component_from_entity<T: 'static + Component>(
         &mut self,
-        entity_id: usize,
-    ) -> Option<(&mut T1, &mut T2)> {
-        if !self.has_component_manager::<T1>() || !self.has_component_manager::<T2>() {
-            return None;
+        entity_id: EntityId,
+    ) -> &mut Self {
+        if !self.rejects_hierarchy_edit::<T>("remove", entity_id) {
+            self.remove_component::<T>(entity_id);
         }
+        self
+    }
 
-        let type_id1 = TypeId::of::<T1>();
-        let type_id2 = TypeId::of::<T2>();
+    /// Remove a component from an entity, even if it's part of the hierarchy.
+    fn remove_component<T: 'static + Component>(&mut self, entity_id: EntityId) -> &mut Self {
+        if !self.has_component_manager::<T>() {
+            error!(
+                "Failed to remove component from entity {}. {} is not registered.",
+                self.entity_label(entity_id),
+                type_name::<T>()
+            );
+            return self;
+        }
+        if !self.entity_exists(entity_id) {
+            error!(
+                "Failed to remove component from entity {entity_id}. The entity does not exist."
+            );
+            return self;
+        }
+        if self.get_component_quietly::<T>(entity_id).is_none() {
+            self.log_missing_component::<T>(entity_id);
+            return self;
+        }
+        self.update_name_index::<T>(entity_id, false);
+        let frame = self.get_frame();
+        let (manager, commands) = self.component_manager_and_hook_commands::<T>();
+        manager.remove(entity_id, frame, commands);
+        self.bump_structure_version(TypeId::of::<T>());
+        self.apply_hook_commands();
 
-        let manager1 = cast_manager_mut_unsafe(self.manager_map.get(&type_id1).unwrap());
-        let manager2 = cast_manager_mut_unsafe(self.manager_map.get(&type_id2).unwrap());
+        self
+    }
 
-        if !manager1.component_exists(entity_id) || !manager2.component_exists(entity_id) {
-            return None;
+    pub fn borrow_component<T: 'static + Component>(&self, entity_id: EntityId) -> Option<&T> {
+        let component = self.get_component_quietly::<T>(entity_id);
+        if component.is_none() && self.has_component_manager::<T>() {
+            self.log_missing_component::<T>(entity_id);
         }
-
-        Some((
-            manager1.borrow_component_mut(entity_id).unwrap(),
-            manager2.borrow_component_mut(entity_id).unwrap(),
-        ))
+        component
     }
 
-    pub fn borrow_component_triple_mut<
-        T1: 'static + Component,
-        T2: 'static + Component,
-        T3: 'static + Component,
-    >(
+    pub fn borrow_component_mut<T: 'static + Component>(
         &mut self,
-        entity_id: usize,
-    ) -> Option<(&mut T1, &mut T2, &mut T3)> {
-        if !self.has_component_manager::<T1>()
-            || !self.has_component_manager::<T2>()
-            || !self.has_component_manager::<T3>()
-        {
+        entity_id: EntityId,
+    ) -> Option<&mut T> {
+        if self.rejects_hierarchy_edit::<T>("change", entity_id) {
             return None;
         }
+        self.component_mut(entity_id)
+    }
 
-        let type_id1 = TypeId::of::<T1>();
-        let type_id2 = TypeId::of::<T2>();
-        let type_id3 = TypeId::of::<T3>();
-
-        let manager1 = cast_manager_mut_unsafe(self.manager_map.get(&type_id1).unwrap());
-        let manager2 = cast_manager_mut_unsafe(self.manager_map.get(&type_id2).unwrap());
-        let manager3 = cast_manager_mut_unsafe(self.manager_map.get(&type_id3).unwrap());
-
-        if !manager1.component_exists(entity_id)
-            || !manager2.component_exists(entity_id)
-            || !manager3.component_exists(entity_id)
+    /// Mutably borrow a component of an entity, even if it's part of the hierarchy.
+    fn component_mut<T: 'static + Component>(&mut self, entity_id: EntityId) -> Option<&mut T> {
+        let frame = self.get_frame();
+        if self.has_component_manager::<T>() && self.get_component_quietly::<T>(entity_id).is_none()
         {
+            self.log_missing_component::<T>(entity_id);
             return None;
         }
+        match self.has_component_manager::<T>() {
+            true => self
+                .borrow_component_manager_mut::<T>()
+                .borrow_component_mut(entity_id, frame),
+            false => None,
+        }
+    }
 
-        Some((
-            manager1.borrow_component_mut(entity_id).unwrap(),
-            manager2.borrow_component_mut(entity_id).unwrap(),
-            manager3.borrow_component_mut(entity_id).unwrap(),
-        ))
+    /// Borrow the `T` components of every enabled entity.
+    pub fn borrow_components<T: 'static + Component>(&self) -> Option<&[T]> {
+        match self.has_component_manager::<T>() {
+            true => Some(self.borrow_component_manager::<T>().borrow_components()),
+            false => None,
+        }
     }
 
-    pub fn borrow_component_quad_mut<
-        T1: 'static + Component,
-        T2: 'static + Component,
-        T3: 'static + Component,
-        T4: 'static + Component,
-    >(
-        &mut self,
-        entity_id: usize,
-    ) -> Option<(&mut T1, &mut T2, &mut T3, &mut T4)> {
-        if !self.has_component_manager::<T1>()
-            || !self.has_component_manager::<T2>()
-            || !self.has_component_manager::<T3>()
-            || !self.has_component_manager::<T4>()
-        {
+    /// Mutably borrow the `T` components of every enabled entity.
+    /// This marks all of them as changed, so prefer a query when only some are written to.
+    pub fn borrow_components_mut<T: 'static + Component>(&mut self) -> Option<&mut [T]> {
+        if is_hierarchy::<T>() {
+            error!(
+                "Failed to borrow every {}. Use `set_parent` or `remove_parent`.",
+                type_name::<T>()
+            );
             return None;
         }
+        let frame = self.get_frame();
+        match self.has_component_manager::<T>() {
+            true => Some(
+                self.borrow_component_manager_mut::<T>()
+                    .borrow_components_mut(frame),
+            ),
+            false => None,
+        }
+    }
 
-        let type_id1 = TypeId::of::<T1>();
-        let type_id2 = TypeId::of::<T2>();
-        let type_id3 = TypeId::of::<T3>();
-        let type_id4 = TypeId::of::<T4>();
+    /// Query the entities that have every component in `Q`, along with those components.
+    /// Unlike `EntityIdAccessor::query` the matching entities aren't cached.
+    pub fn query<Q: QueryData>(&mut self) -> Result<QueryIter<'_, Q>, EcsError> {
+        self.query_filtered::<Q, ()>()
+    }
 
-        let manager1 = cast_manager_mut_unsafe(self.manager_map.get(&type_id1).unwrap());
-        let manager2 = cast_manager_mut_unsafe(self.manager_map.get(&type_id2).unwrap());
-        let manager3 = cast_manager_mut_unsafe(self.manager_map.get(&type_id3).unwrap());
-        let manager4 = cast_manager_mut_unsafe(self.manager_map.get(&type_id4).unwrap());
+    /// Query the entities that have every component in `Q` and match the filter `F`.
+    /// There's no system to remember a last run here, so `Added` and `Changed` match every
+    /// entity with the component.
+    pub fn query_filtered<Q: QueryData, F: QueryFilter>(
+        &mut self,
+    ) -> Result<QueryIter<'_, Q, F>, EcsError> {
+        let entity_ids = self.matching_entity_ids(
+            &query_component_types::<Q, F>(),
+            &query_excluded_component_types::<F>(),
+        )?;
+
+        Ok(QueryIter::new(
+            Cow::Owned(entity_ids),
+            Q::init_fetch(self)?,
+            F::init_fetch(self, None)?,
+        ))
+    }
 
-        if !manager1.component_exists(entity_id)
-            || !manager2.component_exists(entity_id)
-            || !manager3.component_exists(entity_id)
-            || !manager4.component_exists(entity_id)
-        {
-            return None;
+    /// Get the entities that had a `T` component removed after the frame `since`.
+    pub fn removed_components<T: 'static + Component>(
+        &self,
+        since: Option<u64>,
+    ) -> impl Iterator<Item = EntityId> + '_ {
+        self.try_borrow_component_manager_untracked::<T>()
+            .into_iter()
+            .flat_map(|manager| manager.borrow_removed().iter())
+            .filter(move |(_, frame)| is_newer(*frame, since))
+            .map(|(entity_id, _)| *entity_id)
+    }
+
+    /// Forget component removals from before the frame `before`.
+    pub fn clear_removed_components(&mut self, before: u64) {
+        for manager in self.manager_map.values_mut() {
+            manager.get_mut().clear_removed(before);
         }
+    }
 
-        Some((
-            manager1.borrow_component_mut(entity_id).unwrap(),
-            manager2.borrow_component_mut(entity_id).unwrap(),
-            manager3.borrow_component_mut(entity_id).unwrap(),
-            manager4.borrow_component_mut(entity_id).unwrap(),
-        ))
+    /// Borrow the components in `Q` for a single entity, e.g. `(&Velocity, &mut Position)`.
+    /// Like `query`, this skips disabled entities. Use `borrow_component` to reach their
+    /// components.
+    pub fn query_entity<Q: QueryData>(
+        &mut self,
+        entity_id: EntityId,
+    ) -> Result<Q::Item<'_>, EcsError> {
+        if !self.entity_exists(entity_id) {
+            return Err(EcsError::NoSuchEntity(entity_id));
+        }
+        if !self.is_entity_enabled(entity_id) {
+            return Err(EcsError::DisabledEntity(entity_id));
+        }
+
+        let mut fetch = Q::init_fetch(self)?;
+        // SAFETY: Only one item is fetched.
+        unsafe { Q::fetch(&mut fetch, entity_id) }.ok_or(EcsError::QueryMismatch(entity_id))
+    }
+
+    /// Collect the entities that have a component of every type in `types`, and none of the
+    /// types in `excluded_types`.
+    /// This must be done before any component manager is mutably borrowed by a query.
+    fn matching_entity_ids(
+        &self,
+        types: &[(TypeId, &'static str)],
+        excluded_types: &[(TypeId, &'static str)],
+    ) -> Result<Vec<EntityId>, EcsError> {
+        let borrow_managers = |types: &[(TypeId, &'static str)]| {
+            types
+                .iter()
+                .map(|(type_id, name)| {
+                    self.manager_map
+                        .get(type_id)
+                        .map(|manager| manager.get())
+                        .ok_or(EcsError::UnregisteredComponent(name))
+                })
+                .collect::<Result<Vec<_>, _>>()
+        };
+        let managers = borrow_managers(types)?;
+        let excluded_managers = borrow_managers(excluded_types)?;
+
+        let matches = |entity_id: &EntityId| {
+            managers
+                .iter()
+                .all(|manager| manager.component_exists(*entity_id))
+                && !excluded_managers
+                    .iter()
+                    .any(|manager| manager.component_exists(*entity_id))
+        };
+
+        Ok(
+            match managers
+                .iter()
+                .min_by_key(|manager| manager.component_count())
+            {
+                Some(smallest) => smallest.entity_ids().filter(matches).collect(),
+                None => self
+                    .entities
+                    .enabled_ids()
+                    .into_iter()
+                    .filter(matches)
+                    .collect(),
+            },
+        )
     }
 
     fn has_component_manager<T: 'static + Component>(&self) -> bool {
@@ -538,20 +1420,85 @@ impl EntityManager {
 
     fn borrow_component_manager<T: 'static + Component>(&self) -> &ComponentManager<T> {
         let type_id = TypeId::of::<T>();
-        cast_manager(self.manager_map.get(&type_id).unwrap().as_ref())
+        cast_manager(self.manager_map.get(&type_id).unwrap().get())
     }
 
     fn borrow_component_manager_mut<T: 'static + Component>(&mut self) -> &mut ComponentManager<T> {
         let type_id = TypeId::of::<T>();
-        cast_manager_mut(self.manager_map.get_mut(&type_id).unwrap().as_mut())
+        cast_manager_mut(self.manager_map.get_mut(&type_id).unwrap().get_mut())
+    }
+
+    /// Borrow a component manager along with the commands its hooks record into.
+    fn component_manager_and_hook_commands<T: 'static + Component>(
+        &mut self,
+    ) -> (&mut ComponentManager<T>, &mut Commands) {
+        let type_id = TypeId::of::<T>();
+        let manager = cast_manager_mut(self.manager_map.get_mut(&type_id).unwrap().get_mut());
+        (manager, &mut self.hook_commands)
+    }
+
+    pub(crate) fn queue_trigger(&mut self, trigger: PendingTrigger) {
+        self.pending_triggers.push_back(trigger);
+    }
+
+    pub(crate) fn take_pending_trigger(&mut self) -> Option<PendingTrigger> {
+        self.pending_triggers.pop_front()
+    }
+
+    pub(crate) fn queue_resource_change(&mut self, change: ResourceChange) {
+        self.resource_changes.push_back(change);
+    }
+
+    pub(crate) fn take_resource_change(&mut self) -> Option<ResourceChange> {
+        self.resource_changes.pop_front()
     }
-}
 
-#[allow(clippy::mut_from_ref, clippy::borrowed_box)]
-fn cast_manager_mut_unsafe<T: 'static + Component>(
-    manager: &Box<dyn ComponentManagerT>,
-) -> &mut ComponentManager<T> {
-    let ptr =
-        cast_manager(manager.as_ref()) as *const ComponentManager<T> as *mut ComponentManager<T>;
-    unsafe { &mut *ptr }
+    /// Apply the commands recorded by hooks, along with any commands their hooks record.
+    fn apply_hook_commands(&mut self) {
+        if self.defer_hook_commands > 0 {
+            return;
+        }
+
+        self.defer_hook_commands += 1;
+        while !self.hook_commands.is_empty() {
+            let mut commands = self.hook_commands.take();
+            commands.apply(self);
+        }
+        self.defer_hook_commands -= 1;
+    }
+
+    /// Borrow a component manager without tracking the borrow.
+    /// This is only for reading change ticks, which is allowed while a query mutably borrows it.
+    pub(crate) fn borrow_component_manager_untracked<T: 'static + Component>(
+        &self,
+    ) -> Result<&ComponentManager<T>, EcsError> {
+        self.try_borrow_component_manager_untracked::<T>()
+            .ok_or(EcsError::UnregisteredComponent(type_name::<T>()))
+    }
+
+    fn try_borrow_component_manager_untracked<T: 'static + Component>(
+        &self,
+    ) -> Option<&ComponentManager<T>> {
+        Some(cast_manager(
+            self.manager_map.get(&TypeId::of::<T>())?.get(),
+        ))
+    }
+
+    pub(crate) fn try_borrow_component_manager<T: 'static + Component>(
+        &self,
+    ) -> Result<ManagerRef<'_, T>, EcsError> {
+        self.manager_map
+            .get(&TypeId::of::<T>())
+            .ok_or(EcsError::UnregisteredComponent(type_name::<T>()))?
+            .try_borrow::<T>()
+    }
+
+    pub(crate) fn try_borrow_component_manager_mut<T: 'static + Component>(
+        &self,
+    ) -> Result<ManagerRefMut<'_, T>, EcsError> {
+        self.manager_map
+            .get(&TypeId::of::<T>())
+            .ok_or(EcsError::UnregisteredComponent(type_name::<T>()))?
+            .try_borrow_mut::<T>()
+    }
 }
@@ -0,0 +1,132 @@
+use std::{any::type_name, marker::PhantomData};
+use tracing::*;
+
+use super::resources::Resources;
+
+/// A queue of events of one type, like collisions or damage.
+/// Events are double buffered: every update moves the current events into the previous buffer
+/// and drops the old previous ones, so an event lives for two updates. That way a system that
+/// runs before the sender in the next update still sees it.
+///
+/// Add the queue with `World::add_event`, then send events through the `Events<T>` resource and
+/// read them with an `EventReader<T>`.
+pub struct Events<T> {
+    previous: Vec<T>,
+    current: Vec<T>,
+    /// The ID of the first event in `previous`.
+    previous_start: usize,
+    /// The ID of the first event in `current`.
+    current_start: usize,
+}
+
+impl<T> Default for Events<T> {
+    fn default() -> Self {
+        Self {
+            previous: Vec::new(),
+            current: Vec::new(),
+            previous_start: 0,
+            current_start: 0,
+        }
+    }
+}
+
+impl<T> Events<T> {
+    pub fn send(&mut self, event: T) {
+        self.current.push(event);
+    }
+
+    /// Get the number of events ever sent, which is also the ID of the next event.
+    pub fn event_count(&self) -> usize {
+        self.current_start + self.current.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.previous.is_empty() && self.current.is_empty()
+    }
+
+    /// Drop the events from the previous update, and move the current events in their place.
+    /// `World::update` does this at the start of every update.
+    pub fn update(&mut self) {
+        self.previous = std::mem::take(&mut self.current);
+        self.previous_start = self.current_start;
+        self.current_start = self.previous_start + self.previous.len();
+    }
+
+    /// Drop every event.
+    pub fn clear(&mut self) {
+        self.update();
+        self.update();
+    }
+
+    fn events_since(&self, event_id: usize) -> impl Iterator<Item = &T> {
+        let previous_skip = event_id.saturating_sub(self.previous_start);
+        let current_skip = event_id.saturating_sub(self.current_start);
+
+        self.previous
+            .iter()
+            .skip(previous_skip)
+            .chain(self.current.iter().skip(current_skip))
+    }
+}
+
+/// Send an event through the `Events<T>` resource, if its events were added.
+pub(crate) fn send_event<T: 'static + Send + Sync>(resources: &mut Resources, event: T) {
+    match resources.get_mut::<Events<T>>() {
+        Some(events) => events.send(event),
+        None => error!(
+            "Failed to send {}. Its events weren't added.",
+            type_name::<T>()
+        ),
+    }
+}
+
+/// Reads events, remembering which ones it has already read.
+/// Every reader has its own cursor, so several systems can each read every event once.
+pub struct EventReader<T> {
+    last_event_count: usize,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Default for EventReader<T> {
+    fn default() -> Self {
+        Self {
+            last_event_count: 0,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<T> EventReader<T> {
+    /// Read the events sent since this reader last read.
+    pub fn read<'a>(&mut self, events: &'a Events<T>) -> impl Iterator<Item = &'a T> {
+        // A new reader starts from the oldest event still around, so it hasn't missed anything.
+        if self.last_event_count != 0 && self.last_event_count < events.previous_start {
+            warn!(
+                "Missed {} events of type {}. Read them at least every other update.",
+                events.previous_start - self.last_event_count,
+                std::any::type_name::<T>()
+            );
+        }
+
+        let event_id = self.last_event_count;
+        self.last_event_count = events.event_count();
+        events.events_since(event_id)
+    }
+
+    /// Read the events from the `Events<T>` resource, if it exists.
+    pub fn read_resource<'a>(&mut self, resources: &'a Resources) -> impl Iterator<Item = &'a T>
+    where
+        T: 'static + Send + Sync,
+    {
+        resources
+            .get::<Events<T>>()
+            .map(|events| self.read(events))
+            .into_iter()
+            .flatten()
+    }
+
+    /// Skip every unread event.
+    pub fn clear(&mut self, events: &Events<T>) {
+        self.last_event_count = events.event_count();
+    }
+}
@@ -0,0 +1,82 @@
+use serde::{Deserialize, Serialize};
+use std::any::TypeId;
+
+use super::{
+    component::Component,
+    entity::EntityId,
+    scene::{EntityMap, MapEntities},
+};
+use crate::errors::SceneError;
+
+/// The parent of an entity.
+/// This is maintained by `set_parent` and `remove_parent` on the world, along with the
+/// parent's `Children`, so it can't be added, removed or changed by hand.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Parent(EntityId);
+
+impl Parent {
+    pub(crate) fn new(entity_id: EntityId) -> Self {
+        Self(entity_id)
+    }
+
+    pub fn get(&self) -> EntityId {
+        self.0
+    }
+}
+
+/// The children of an entity, in the order they were added in.
+/// Removing an entity removes all of its children too. Like `Parent`, this is only changed by
+/// `set_parent` and `remove_parent`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Children(Vec<EntityId>);
+
+impl Children {
+    pub fn iter(&self) -> impl Iterator<Item = &EntityId> {
+        self.0.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn contains(&self, entity_id: EntityId) -> bool {
+        self.0.contains(&entity_id)
+    }
+
+    pub(crate) fn push(&mut self, entity_id: EntityId) {
+        self.0.push(entity_id);
+    }
+
+    pub(crate) fn remove(&mut self, entity_id: EntityId) {
+        self.0.retain(|child| *child != entity_id);
+    }
+}
+
+/// Check if `T` is `Parent` or `Children`, which only `set_parent` and `remove_parent` change.
+pub(crate) fn is_hierarchy<T: 'static>() -> bool {
+    let type_id = TypeId::of::<T>();
+    type_id == TypeId::of::<Parent>() || type_id == TypeId::of::<Children>()
+}
+
+impl MapEntities for Parent {
+    fn map_entities(&mut self, entity_map: &EntityMap) -> Result<(), SceneError> {
+        self.0 = entity_map.map(self.0)?;
+        Ok(())
+    }
+}
+
+impl MapEntities for Children {
+    fn map_entities(&mut self, entity_map: &EntityMap) -> Result<(), SceneError> {
+        for child in self.0.iter_mut() {
+            *child = entity_map.map(*child)?;
+        }
+        Ok(())
+    }
+}
+
+impl Component for Parent {}
+impl Component for Children {}
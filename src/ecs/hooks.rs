@@ -0,0 +1,82 @@
+use super::{commands::Commands, component::Component, entity::EntityId};
+
+/// Runs when a component changes hands, with the component, the entity it belongs to and a
+/// command buffer for any follow-up changes.
+pub type ComponentHook<T> = Box<dyn Fn(&T, EntityId, &mut Commands) + Send + Sync>;
+
+/// Callbacks for the lifecycle of a component type, set with `World::set_component_hooks`.
+/// Commands recorded by hooks are applied right after the change that triggered them.
+pub struct ComponentHooks<T: Component> {
+    on_add: Option<ComponentHook<T>>,
+    on_insert: Option<ComponentHook<T>>,
+    on_remove: Option<ComponentHook<T>>,
+}
+
+impl<T: Component> Default for ComponentHooks<T> {
+    fn default() -> Self {
+        Self {
+            on_add: None,
+            on_insert: None,
+            on_remove: None,
+        }
+    }
+}
+
+impl<T: Component> ComponentHooks<T> {
+    /// Run `hook` when an entity that didn't have the component gets it.
+    pub fn on_add(
+        mut self,
+        hook: impl Fn(&T, EntityId, &mut Commands) + Send + Sync + 'static,
+    ) -> Self {
+        self.on_add = Some(Box::new(hook));
+        self
+    }
+
+    /// Run `hook` whenever the component is added to an entity or replaces its previous one.
+    /// When it replaces one, `on_remove` runs for the previous component first.
+    pub fn on_insert(
+        mut self,
+        hook: impl Fn(&T, EntityId, &mut Commands) + Send + Sync + 'static,
+    ) -> Self {
+        self.on_insert = Some(Box::new(hook));
+        self
+    }
+
+    /// Run `hook` right before the component is removed from an entity or replaced by another
+    /// one, including when the entity itself is removed.
+    pub fn on_remove(
+        mut self,
+        hook: impl Fn(&T, EntityId, &mut Commands) + Send + Sync + 'static,
+    ) -> Self {
+        self.on_remove = Some(Box::new(hook));
+        self
+    }
+
+    pub(crate) fn run_on_add(&self, component: &T, entity_id: EntityId, commands: &mut Commands) {
+        if let Some(hook) = &self.on_add {
+            hook(component, entity_id, commands);
+        }
+    }
+
+    pub(crate) fn run_on_insert(
+        &self,
+        component: &T,
+        entity_id: EntityId,
+        commands: &mut Commands,
+    ) {
+        if let Some(hook) = &self.on_insert {
+            hook(component, entity_id, commands);
+        }
+    }
+
+    pub(crate) fn run_on_remove(
+        &self,
+        component: &T,
+        entity_id: EntityId,
+        commands: &mut Commands,
+    ) {
+        if let Some(hook) = &self.on_remove {
+            hook(component, entity_id, commands);
+        }
+    }
+}
@@ -1,13 +1,49 @@
+mod bundle;
+mod change_detection;
+mod commands;
 mod component;
 mod component_manager;
+mod condition;
 mod entity;
 mod entity_manager;
+mod events;
+mod hierarchy;
+mod hooks;
+mod name;
+mod observer;
+mod prefab;
+mod query;
+mod reflect;
+mod relation;
+mod resources;
+mod scene;
+mod schedule;
+mod snapshot;
+mod storage;
 mod system;
+mod time;
 mod world;
 
+pub use bundle::*;
+pub use change_detection::*;
+pub use commands::*;
 pub use component::*;
 pub use component_manager::*;
+pub use condition::*;
 pub use entity::*;
 pub use entity_manager::*;
+pub use events::*;
+pub use hierarchy::*;
+pub use hooks::*;
+pub use name::*;
+pub use query::*;
+pub use reflect::*;
+pub use relation::*;
+pub use resources::*;
+pub use scene::*;
+pub use schedule::*;
+pub use snapshot::*;
+pub use storage::*;
 pub use system::*;
+pub use time::*;
 pub use world::*;
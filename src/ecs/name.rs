@@ -0,0 +1,155 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeSet, HashMap},
+    fmt,
+};
+
+use super::{component::Component, entity::EntityId};
+
+/// The name of an entity, like "player".
+/// Entities can be found by name with `World::find_by_name`, and their names show up in the
+/// errors logged about them. Names don't have to be unique.
+///
+/// Rename an entity by inserting a new `Name`, so the lookup stays up to date.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Name(String);
+
+impl Name {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self(name.into())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<&str> for Name {
+    fn from(name: &str) -> Self {
+        Self::new(name)
+    }
+}
+
+impl From<String> for Name {
+    fn from(name: String) -> Self {
+        Self(name)
+    }
+}
+
+impl fmt::Display for Name {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// String tags of an entity, like "enemy" or "flying".
+/// Entities can be found by tag with `World::entities_with_tag`.
+///
+/// Change the tags of an entity with `World::add_tag` and `World::remove_tag`, or by inserting
+/// new `Tags`, so the lookup stays up to date.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Tags(BTreeSet<String>);
+
+impl Tags {
+    pub fn new<S: Into<String>>(tags: impl IntoIterator<Item = S>) -> Self {
+        Self(tags.into_iter().map(Into::into).collect())
+    }
+
+    pub fn contains(&self, tag: &str) -> bool {
+        self.0.contains(tag)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        self.0.iter().map(String::as_str)
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub(crate) fn insert(&mut self, tag: String) -> bool {
+        self.0.insert(tag)
+    }
+
+    pub(crate) fn remove(&mut self, tag: &str) -> bool {
+        self.0.remove(tag)
+    }
+}
+
+impl Component for Name {}
+impl Component for Tags {}
+
+/// Looks up entities by their `Name` and `Tags`.
+/// The entity manager keeps this up to date as the components are added, replaced and removed.
+#[derive(Default)]
+pub(crate) struct NameIndex {
+    names: HashMap<String, Vec<EntityId>>,
+    tags: HashMap<String, BTreeSet<EntityId>>,
+}
+
+impl NameIndex {
+    pub(crate) fn insert_name(&mut self, name: &Name, entity_id: EntityId) {
+        let entity_ids = self.names.entry(name.0.clone()).or_default();
+        if !entity_ids.contains(&entity_id) {
+            entity_ids.push(entity_id);
+        }
+    }
+
+    pub(crate) fn remove_name(&mut self, name: &Name, entity_id: EntityId) {
+        if let Some(entity_ids) = self.names.get_mut(name.as_str()) {
+            entity_ids.retain(|id| *id != entity_id);
+            if entity_ids.is_empty() {
+                self.names.remove(name.as_str());
+            }
+        }
+    }
+
+    pub(crate) fn insert_tags(&mut self, tags: &Tags, entity_id: EntityId) {
+        for tag in tags.iter() {
+            self.tags
+                .entry(tag.to_string())
+                .or_default()
+                .insert(entity_id);
+        }
+    }
+
+    pub(crate) fn remove_tags(&mut self, tags: &Tags, entity_id: EntityId) {
+        for tag in tags.iter() {
+            if let Some(entity_ids) = self.tags.get_mut(tag) {
+                entity_ids.remove(&entity_id);
+                if entity_ids.is_empty() {
+                    self.tags.remove(tag);
+                }
+            }
+        }
+    }
+
+    /// Get the entities named `name`, in the order they were given the name.
+    pub(crate) fn entities_named(&self, name: &str) -> impl Iterator<Item = EntityId> + '_ {
+        self.names.get(name).into_iter().flatten().copied()
+    }
+
+    /// Get the entities tagged with `tag`, ordered by ID.
+    pub(crate) fn entities_with_tag(&self, tag: &str) -> impl Iterator<Item = EntityId> + '_ {
+        self.tags.get(tag).into_iter().flatten().copied()
+    }
+}
+
+/// Shows an entity ID along with the name of the entity, if it has one, like `3v0 "player"`.
+pub(crate) struct EntityLabel<'a> {
+    pub(crate) entity_id: EntityId,
+    pub(crate) name: Option<&'a Name>,
+}
+
+impl fmt::Display for EntityLabel<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.name {
+            Some(name) => write!(f, "{} {:?}", self.entity_id, name.as_str()),
+            None => write!(f, "{}", self.entity_id),
+        }
+    }
+}
@@ -0,0 +1,156 @@
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+};
+
+use super::{
+    entity::EntityId,
+    entity_manager::EntityManager,
+    hierarchy::Parent,
+    resources::Resources,
+    system::{Observer, Trigger},
+};
+
+/// Runs the observers of a triggered event. Triggers from commands wait in the entity manager
+/// until the world gets to them.
+pub(crate) type PendingTrigger =
+    Box<dyn FnOnce(&mut Observers, &mut EntityManager, &mut Resources) + Send + Sync>;
+
+/// Queue an `E` event for the observers of `target`.
+pub(crate) fn pending_trigger<E: 'static + Send + Sync>(
+    event: E,
+    target: EntityId,
+) -> PendingTrigger {
+    Box::new(move |observers, manager, resources| {
+        observers.trigger(&event, target, manager, resources);
+    })
+}
+
+/// The observers of one event type.
+struct ObserverList<E> {
+    global: Vec<Box<dyn Observer<E>>>,
+    by_entity: HashMap<EntityId, Vec<Box<dyn Observer<E>>>>,
+}
+
+trait ObserverListT: Send {
+    fn as_mut_any(&mut self) -> &mut dyn Any;
+    /// Drop the observers of entities that no longer exist.
+    fn retain_existing(&mut self, manager: &EntityManager);
+}
+
+impl<E: 'static> ObserverListT for ObserverList<E> {
+    fn as_mut_any(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn retain_existing(&mut self, manager: &EntityManager) {
+        self.by_entity
+            .retain(|entity_id, _| manager.entity_exists(*entity_id));
+    }
+}
+
+/// Every observer, by the event type it observes.
+#[derive(Default)]
+pub(crate) struct Observers {
+    lists: HashMap<TypeId, Box<dyn ObserverListT>>,
+}
+
+impl Observers {
+    fn list_mut<E: 'static>(&mut self) -> &mut ObserverList<E> {
+        self.lists
+            .entry(TypeId::of::<E>())
+            .or_insert_with(|| {
+                Box::new(ObserverList::<E> {
+                    global: Vec::new(),
+                    by_entity: HashMap::new(),
+                })
+            })
+            .as_mut_any()
+            .downcast_mut::<ObserverList<E>>()
+            .unwrap()
+    }
+
+    pub(crate) fn add<E: 'static>(&mut self, observer: Box<dyn Observer<E>>) {
+        self.list_mut::<E>().global.push(observer);
+    }
+
+    pub(crate) fn add_to_entity<E: 'static>(
+        &mut self,
+        entity_id: EntityId,
+        observer: Box<dyn Observer<E>>,
+    ) {
+        self.list_mut::<E>()
+            .by_entity
+            .entry(entity_id)
+            .or_default()
+            .push(observer);
+    }
+
+    /// Drop the observers of entities that no longer exist.
+    pub(crate) fn retain_existing(&mut self, manager: &EntityManager) {
+        for list in self.lists.values_mut() {
+            list.retain_existing(manager);
+        }
+    }
+
+    /// Run the observers of `target` and its ancestors, until one stops the propagation, and
+    /// then the observers watching every entity.
+    /// The commands they record are applied right after.
+    pub(crate) fn trigger<E: 'static>(
+        &mut self,
+        event: &E,
+        target: EntityId,
+        manager: &mut EntityManager,
+        resources: &mut Resources,
+    ) {
+        if !manager.entity_exists(target) {
+            return;
+        }
+        let Some(list) = self.lists.get_mut(&TypeId::of::<E>()) else {
+            return;
+        };
+        let list = list.as_mut_any().downcast_mut::<ObserverList<E>>().unwrap();
+
+        let mut commands = manager.commands();
+        let mut trigger = Trigger::new(event, target);
+        let mut entity = Some(target);
+        while let Some(entity_id) = entity {
+            trigger.set_entity(entity_id);
+            for observer in list.by_entity.get_mut(&entity_id).into_iter().flatten() {
+                observer.trigger(&mut trigger, manager, &mut commands, resources);
+            }
+            if !trigger.propagates() {
+                break;
+            }
+            entity = manager
+                .get_component_quietly::<Parent>(entity_id)
+                .map(Parent::get);
+        }
+
+        trigger.set_entity(target);
+        for observer in list.global.iter_mut() {
+            observer.trigger(&mut trigger, manager, &mut commands, resources);
+        }
+
+        commands.apply(manager);
+    }
+}
+
+/// Make the pending resource changes and run every pending trigger, along with the triggers
+/// and resource changes their observers send. Resource changes are made first, so observers
+/// see the resources the commands before them inserted.
+pub(crate) fn run_pending_triggers(
+    observers: &mut Observers,
+    manager: &mut EntityManager,
+    resources: &mut Resources,
+) {
+    loop {
+        while let Some(change) = manager.take_resource_change() {
+            change(resources);
+        }
+        let Some(trigger) = manager.take_pending_trigger() else {
+            return;
+        };
+        trigger(observers, manager, resources);
+    }
+}
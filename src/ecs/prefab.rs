@@ -0,0 +1,81 @@
+use std::{any::TypeId, collections::HashMap};
+use tracing::*;
+
+use super::{bundle::Bundle, entity::EntityId, entity_manager::EntityManager};
+
+/// Adds some of a prefab's components to an entity, leaving out the types that are overridden.
+pub(crate) type PrefabInsert = Box<dyn Fn(&mut EntityManager, EntityId, &[TypeId]) + Send + Sync>;
+
+/// A template of components that can be spawned many times.
+pub(crate) struct Prefab {
+    inserts: Vec<PrefabInsert>,
+}
+
+impl Prefab {
+    pub(crate) fn from_bundle<B: Bundle + Clone>(bundle: B) -> Self {
+        Self {
+            inserts: vec![Box::new(move |manager, entity_id, overridden| {
+                bundle
+                    .clone()
+                    .add_to_entity_except(manager, entity_id, overridden);
+            })],
+        }
+    }
+
+    pub(crate) fn from_inserts(inserts: Vec<PrefabInsert>) -> Self {
+        Self { inserts }
+    }
+
+    /// Add the prefab's components to an entity, except for the types in `overridden`, so the
+    /// components replacing them are the only ones added.
+    pub(crate) fn add_to_entity(
+        &self,
+        manager: &mut EntityManager,
+        entity_id: EntityId,
+        overridden: &[TypeId],
+    ) {
+        for insert in self.inserts.iter() {
+            insert(manager, entity_id, overridden);
+        }
+    }
+}
+
+/// The prefabs of a world by name.
+#[derive(Default)]
+pub(crate) struct Prefabs {
+    prefabs: HashMap<String, Prefab>,
+}
+
+impl Prefabs {
+    /// Add a prefab, replacing the previous prefab with the same name.
+    pub(crate) fn insert(&mut self, name: String, prefab: Prefab) {
+        self.prefabs.insert(name, prefab);
+    }
+
+    pub(crate) fn contains(&self, name: &str) -> bool {
+        self.prefabs.contains_key(name)
+    }
+
+    pub(crate) fn get(&self, name: &str) -> Option<&Prefab> {
+        self.prefabs.get(name)
+    }
+
+    /// Spawn an entity with the components of a prefab, with the components in `overrides`
+    /// added instead of the prefab's of the same types.
+    pub(crate) fn spawn<B: Bundle>(
+        &self,
+        manager: &mut EntityManager,
+        name: &str,
+        overrides: B,
+    ) -> Option<EntityId> {
+        let Some(prefab) = self.get(name) else {
+            error!("Failed to spawn the prefab `{name}`. It does not exist.");
+            return None;
+        };
+
+        let entity_id = manager.create_entity();
+        prefab.add_to_entity(manager, entity_id, &B::type_ids());
+        overrides.add_to_entity(manager, entity_id);
+        Some(entity_id)
+    }
+}
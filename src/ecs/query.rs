@@ -0,0 +1,444 @@
+use super::{
+    change_detection::Mut,
+    component::Component,
+    component_manager::{ComponentManager, ManagerRef, ManagerRefMut},
+    entity::EntityId,
+    entity_manager::EntityManager,
+    hierarchy::is_hierarchy,
+};
+use crate::errors::EcsError;
+use std::{
+    any::{type_name, TypeId},
+    borrow::Cow,
+    marker::PhantomData,
+};
+
+/// Describes the data a query fetches for every matching entity.
+/// This is implemented for `&T`, `&mut T`, `Option` and tuples of those, so a query can ask for
+/// any number of components, e.g. `(&Velocity, &mut Position, Option<&Shape>)`. `&mut T` hands
+/// out a `Mut<T>`, which marks the component as changed when written to. `Option<&T>` matches
+/// entities with or without a `T`.
+pub trait QueryData {
+    /// What the query hands out for each entity.
+    type Item<'a>;
+    /// The borrowed component managers the items are fetched from.
+    type Fetch<'a>;
+
+    /// Collects the component types (and their names) an entity needs to match this query.
+    fn component_types(types: &mut Vec<(TypeId, &'static str)>);
+
+    /// Collects every component type this query borrows, including the ones an entity doesn't
+    /// need to have.
+    fn accessed_component_types(types: &mut Vec<(TypeId, &'static str)>) {
+        Self::component_types(types);
+    }
+
+    /// Collects the component types this query borrows mutably.
+    fn mutable_component_types(_types: &mut Vec<(TypeId, &'static str)>) {}
+
+    /// Borrows the component managers needed by this query.
+    /// This fails if a component isn't registered, or if the query would borrow the same
+    /// component mutably more than once.
+    fn init_fetch(manager: &EntityManager) -> Result<Self::Fetch<'_>, EcsError>;
+
+    /// Fetches the item for an entity, or `None` if the entity doesn't match.
+    ///
+    /// # Safety
+    /// The caller must not fetch the same entity twice while a previous item is still alive.
+    unsafe fn fetch<'a>(fetch: &mut Self::Fetch<'a>, entity_id: EntityId)
+        -> Option<Self::Item<'a>>;
+}
+
+impl<T: 'static + Component> QueryData for &T {
+    type Item<'a> = &'a T;
+    type Fetch<'a> = ManagerRef<'a, T>;
+
+    fn component_types(types: &mut Vec<(TypeId, &'static str)>) {
+        types.push((TypeId::of::<T>(), type_name::<T>()));
+    }
+
+    fn init_fetch(manager: &EntityManager) -> Result<Self::Fetch<'_>, EcsError> {
+        manager.try_borrow_component_manager::<T>()
+    }
+
+    unsafe fn fetch<'a>(
+        fetch: &mut Self::Fetch<'a>,
+        entity_id: EntityId,
+    ) -> Option<Self::Item<'a>> {
+        fetch.manager().borrow_component(entity_id)
+    }
+}
+
+impl<T: 'static + Component> QueryData for &mut T {
+    type Item<'a> = Mut<'a, T>;
+    type Fetch<'a> = (ManagerRefMut<'a, T>, u64);
+
+    fn component_types(types: &mut Vec<(TypeId, &'static str)>) {
+        types.push((TypeId::of::<T>(), type_name::<T>()));
+    }
+
+    fn mutable_component_types(types: &mut Vec<(TypeId, &'static str)>) {
+        types.push((TypeId::of::<T>(), type_name::<T>()));
+    }
+
+    fn init_fetch(manager: &EntityManager) -> Result<Self::Fetch<'_>, EcsError> {
+        if is_hierarchy::<T>() {
+            return Err(EcsError::HierarchyComponent(type_name::<T>()));
+        }
+        Ok((
+            manager.try_borrow_component_manager_mut::<T>()?,
+            manager.get_frame(),
+        ))
+    }
+
+    unsafe fn fetch<'a>(
+        fetch: &mut Self::Fetch<'a>,
+        entity_id: EntityId,
+    ) -> Option<Self::Item<'a>> {
+        let (manager, tick) = fetch;
+        let (component, ticks) = manager.component_ptr(entity_id)?;
+        Some(Mut::new(&mut *component, &mut *ticks, *tick))
+    }
+}
+
+impl<Q: QueryData> QueryData for Option<Q> {
+    type Item<'a> = Option<Q::Item<'a>>;
+    type Fetch<'a> = Q::Fetch<'a>;
+
+    fn component_types(_types: &mut Vec<(TypeId, &'static str)>) {}
+
+    fn accessed_component_types(types: &mut Vec<(TypeId, &'static str)>) {
+        Q::accessed_component_types(types);
+    }
+
+    fn mutable_component_types(types: &mut Vec<(TypeId, &'static str)>) {
+        Q::mutable_component_types(types);
+    }
+
+    fn init_fetch(manager: &EntityManager) -> Result<Self::Fetch<'_>, EcsError> {
+        Q::init_fetch(manager)
+    }
+
+    unsafe fn fetch<'a>(
+        fetch: &mut Self::Fetch<'a>,
+        entity_id: EntityId,
+    ) -> Option<Self::Item<'a>> {
+        Some(Q::fetch(fetch, entity_id))
+    }
+}
+
+macro_rules! impl_query_data_tuple {
+    ($($name:ident),*) => {
+        #[allow(non_snake_case, unused_variables)]
+        impl<$($name: QueryData),*> QueryData for ($($name,)*) {
+            type Item<'a> = ($($name::Item<'a>,)*);
+            type Fetch<'a> = ($($name::Fetch<'a>,)*);
+
+            fn component_types(types: &mut Vec<(TypeId, &'static str)>) {
+                $($name::component_types(types);)*
+            }
+
+            fn accessed_component_types(types: &mut Vec<(TypeId, &'static str)>) {
+                $($name::accessed_component_types(types);)*
+            }
+
+            fn mutable_component_types(types: &mut Vec<(TypeId, &'static str)>) {
+                $($name::mutable_component_types(types);)*
+            }
+
+            fn init_fetch(manager: &EntityManager) -> Result<Self::Fetch<'_>, EcsError> {
+                Ok(($($name::init_fetch(manager)?,)*))
+            }
+
+            unsafe fn fetch<'a>(
+                fetch: &mut Self::Fetch<'a>,
+                entity_id: EntityId,
+            ) -> Option<Self::Item<'a>> {
+                let ($($name,)*) = fetch;
+                Some(($($name::fetch($name, entity_id)?,)*))
+            }
+        }
+    };
+}
+
+macro_rules! impl_query_data_tuples {
+    () => {
+        impl_query_data_tuple!();
+    };
+    ($first:ident $(, $rest:ident)*) => {
+        impl_query_data_tuple!($first $(, $rest)*);
+        impl_query_data_tuples!($($rest),*);
+    };
+}
+
+impl_query_data_tuples!(Q1, Q2, Q3, Q4, Q5, Q6, Q7, Q8, Q9, Q10, Q11, Q12, Q13, Q14, Q15, Q16);
+
+/// Narrows down the entities a query matches without fetching any data.
+/// This is implemented for `With<T>`, `Without<T>`, `Added<T>`, `Changed<T>`, `Or<(..)>` and
+/// tuples of filters, which all have to match.
+pub trait QueryFilter {
+    /// The component managers the filter reads from.
+    type Fetch<'a>;
+
+    /// Collects the component types an entity needs to match this filter.
+    /// Together with `excluded_component_types` this decides which entities are cached for a
+    /// query, before `matches` checks them one by one.
+    fn component_types(types: &mut Vec<(TypeId, &'static str)>);
+
+    /// Collects the component types an entity can't have to match this filter.
+    fn excluded_component_types(_types: &mut Vec<(TypeId, &'static str)>) {}
+
+    /// Collects every component type this filter reads.
+    fn accessed_component_types(types: &mut Vec<(TypeId, &'static str)>) {
+        Self::component_types(types);
+    }
+
+    /// Get what the filter needs to check entities.
+    /// `last_run` is the frame the querying system last ran in, if it has run before.
+    fn init_fetch(
+        manager: &EntityManager,
+        last_run: Option<u64>,
+    ) -> Result<Self::Fetch<'_>, EcsError>;
+
+    fn matches(fetch: &Self::Fetch<'_>, entity_id: EntityId) -> bool;
+}
+
+/// Only matches entities with a `T` component, without fetching it.
+pub struct With<T>(PhantomData<T>);
+
+/// Only matches entities without a `T` component.
+pub struct Without<T>(PhantomData<T>);
+
+/// Matches entities that match any of the filters in a tuple, e.g.
+/// `Or<(With<Sprite>, With<Shape>)>`.
+pub struct Or<F>(PhantomData<F>);
+
+/// Only matches entities whose `T` component was added since the system last ran.
+pub struct Added<T>(PhantomData<T>);
+
+/// Only matches entities whose `T` component was added or changed since the system last ran.
+pub struct Changed<T>(PhantomData<T>);
+
+macro_rules! impl_tick_filter {
+    ($filter:ident, $check:ident) => {
+        impl<T: 'static + Component> QueryFilter for $filter<T> {
+            type Fetch<'a> = (&'a ComponentManager<T>, Option<u64>);
+
+            fn component_types(types: &mut Vec<(TypeId, &'static str)>) {
+                types.push((TypeId::of::<T>(), type_name::<T>()));
+            }
+
+            fn init_fetch(
+                manager: &EntityManager,
+                last_run: Option<u64>,
+            ) -> Result<Self::Fetch<'_>, EcsError> {
+                // This only reads ticks, so it doesn't conflict with the query borrowing `T`.
+                Ok((manager.borrow_component_manager_untracked::<T>()?, last_run))
+            }
+
+            fn matches(fetch: &Self::Fetch<'_>, entity_id: EntityId) -> bool {
+                let (manager, last_run) = fetch;
+                manager
+                    .ticks(entity_id)
+                    .map_or(false, |ticks| ticks.$check(*last_run))
+            }
+        }
+    };
+}
+
+impl_tick_filter!(Added, is_added);
+impl_tick_filter!(Changed, is_changed);
+
+impl<T: 'static + Component> QueryFilter for With<T> {
+    type Fetch<'a> = &'a ComponentManager<T>;
+
+    fn component_types(types: &mut Vec<(TypeId, &'static str)>) {
+        types.push((TypeId::of::<T>(), type_name::<T>()));
+    }
+
+    fn init_fetch(
+        manager: &EntityManager,
+        _last_run: Option<u64>,
+    ) -> Result<Self::Fetch<'_>, EcsError> {
+        // This only checks which entities have a `T`, so it doesn't conflict with the query
+        // borrowing `T`.
+        manager.borrow_component_manager_untracked::<T>()
+    }
+
+    fn matches(fetch: &Self::Fetch<'_>, entity_id: EntityId) -> bool {
+        fetch.component_exists(entity_id)
+    }
+}
+
+impl<T: 'static + Component> QueryFilter for Without<T> {
+    type Fetch<'a> = &'a ComponentManager<T>;
+
+    fn component_types(_types: &mut Vec<(TypeId, &'static str)>) {}
+
+    fn excluded_component_types(types: &mut Vec<(TypeId, &'static str)>) {
+        types.push((TypeId::of::<T>(), type_name::<T>()));
+    }
+
+    fn accessed_component_types(types: &mut Vec<(TypeId, &'static str)>) {
+        types.push((TypeId::of::<T>(), type_name::<T>()));
+    }
+
+    fn init_fetch(
+        manager: &EntityManager,
+        _last_run: Option<u64>,
+    ) -> Result<Self::Fetch<'_>, EcsError> {
+        manager.borrow_component_manager_untracked::<T>()
+    }
+
+    fn matches(fetch: &Self::Fetch<'_>, entity_id: EntityId) -> bool {
+        !fetch.component_exists(entity_id)
+    }
+}
+
+macro_rules! impl_query_filter_tuple {
+    ($($name:ident),*) => {
+        #[allow(non_snake_case, unused_variables)]
+        impl<$($name: QueryFilter),*> QueryFilter for ($($name,)*) {
+            type Fetch<'a> = ($($name::Fetch<'a>,)*);
+
+            fn component_types(types: &mut Vec<(TypeId, &'static str)>) {
+                $($name::component_types(types);)*
+            }
+
+            fn excluded_component_types(types: &mut Vec<(TypeId, &'static str)>) {
+                $($name::excluded_component_types(types);)*
+            }
+
+            fn accessed_component_types(types: &mut Vec<(TypeId, &'static str)>) {
+                $($name::accessed_component_types(types);)*
+            }
+
+            fn init_fetch(
+                manager: &EntityManager,
+                last_run: Option<u64>,
+            ) -> Result<Self::Fetch<'_>, EcsError> {
+                Ok(($($name::init_fetch(manager, last_run)?,)*))
+            }
+
+            fn matches(fetch: &Self::Fetch<'_>, entity_id: EntityId) -> bool {
+                let ($($name,)*) = fetch;
+                true $(&& $name::matches($name, entity_id))*
+            }
+        }
+
+        // None of the filters have to match on their own, so `Or` doesn't narrow down the
+        // cached entities and checks every one of them instead.
+        #[allow(non_snake_case, unused_variables)]
+        impl<$($name: QueryFilter),*> QueryFilter for Or<($($name,)*)> {
+            type Fetch<'a> = ($($name::Fetch<'a>,)*);
+
+            fn component_types(_types: &mut Vec<(TypeId, &'static str)>) {}
+
+            fn accessed_component_types(types: &mut Vec<(TypeId, &'static str)>) {
+                $($name::accessed_component_types(types);)*
+            }
+
+            fn init_fetch(
+                manager: &EntityManager,
+                last_run: Option<u64>,
+            ) -> Result<Self::Fetch<'_>, EcsError> {
+                Ok(($($name::init_fetch(manager, last_run)?,)*))
+            }
+
+            fn matches(fetch: &Self::Fetch<'_>, entity_id: EntityId) -> bool {
+                let ($($name,)*) = fetch;
+                false $(|| $name::matches($name, entity_id))*
+            }
+        }
+    };
+}
+
+macro_rules! impl_query_filter_tuples {
+    () => {
+        impl_query_filter_tuple!();
+    };
+    ($first:ident $(, $rest:ident)*) => {
+        impl_query_filter_tuple!($first $(, $rest)*);
+        impl_query_filter_tuples!($($rest),*);
+    };
+}
+
+impl_query_filter_tuples!(F1, F2, F3, F4, F5, F6, F7, F8);
+
+/// An iterator over the entities matching a query, together with their components.
+pub struct QueryIter<'a, Q: QueryData, F: QueryFilter = ()> {
+    entity_ids: Cow<'a, [EntityId]>,
+    position: usize,
+    fetch: Q::Fetch<'a>,
+    filter: F::Fetch<'a>,
+}
+
+impl<'a, Q: QueryData, F: QueryFilter> QueryIter<'a, Q, F> {
+    pub(crate) fn new(
+        entity_ids: Cow<'a, [EntityId]>,
+        fetch: Q::Fetch<'a>,
+        filter: F::Fetch<'a>,
+    ) -> Self {
+        Self {
+            entity_ids,
+            position: 0,
+            fetch,
+            filter,
+        }
+    }
+}
+
+impl<'a, Q: QueryData, F: QueryFilter> Iterator for QueryIter<'a, Q, F> {
+    type Item = (EntityId, Q::Item<'a>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(entity_id) = self.entity_ids.get(self.position).copied() {
+            self.position += 1;
+
+            if !F::matches(&self.filter, entity_id) {
+                continue;
+            }
+
+            // SAFETY: Entity IDs are unique within the list, so every entity is fetched once.
+            if let Some(item) = unsafe { Q::fetch(&mut self.fetch, entity_id) } {
+                return Some((entity_id, item));
+            }
+        }
+
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.entity_ids.len() - self.position))
+    }
+}
+
+/// Get the sorted, deduplicated list of component types a query and its filter need.
+pub(crate) fn query_component_types<Q: QueryData, F: QueryFilter>() -> Vec<(TypeId, &'static str)> {
+    let mut types = Vec::new();
+    Q::component_types(&mut types);
+    F::component_types(&mut types);
+    sort_types(types)
+}
+
+/// Get the sorted, deduplicated list of component types a filter rules out.
+pub(crate) fn query_excluded_component_types<F: QueryFilter>() -> Vec<(TypeId, &'static str)> {
+    let mut types = Vec::new();
+    F::excluded_component_types(&mut types);
+    sort_types(types)
+}
+
+/// Get the sorted, deduplicated list of every component type a query and its filter read.
+pub(crate) fn query_accessed_component_types<Q: QueryData, F: QueryFilter>(
+) -> Vec<(TypeId, &'static str)> {
+    let mut types = Vec::new();
+    Q::accessed_component_types(&mut types);
+    F::accessed_component_types(&mut types);
+    sort_types(types)
+}
+
+fn sort_types(mut types: Vec<(TypeId, &'static str)>) -> Vec<(TypeId, &'static str)> {
+    types.sort_by_key(|(type_id, _)| *type_id);
+    types.dedup_by_key(|(type_id, _)| *type_id);
+    types
+}
@@ -0,0 +1,379 @@
+use serde_json::{Map, Value};
+use std::{
+    any::{type_name, Any, TypeId},
+    collections::BTreeMap,
+    fmt,
+};
+
+use super::{component::Component, entity::EntityId, entity_manager::EntityManager};
+use crate::errors::ReflectError;
+
+pub use taconite_derive::Reflect;
+
+/// Lets a value be inspected and edited without knowing its type, by listing its fields.
+/// Derive it with `#[derive(Reflect)]` on structs whose fields implement it, or on enums with
+/// only unit variants.
+///
+/// `dyn Reflect` implements `Debug`, and can be turned into JSON and back with `to_value` and
+/// `apply_value`.
+pub trait Reflect: Any + Send + Sync {
+    /// Describe the type and its fields.
+    fn type_info() -> TypeInfo
+    where
+        Self: Sized;
+
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+
+    fn reflect_type_name(&self) -> &'static str {
+        type_name::<Self>()
+    }
+
+    /// Get the fields of a struct by name, in the order they're declared in.
+    /// The fields of a tuple struct are named by their index, like `"0"`.
+    fn fields(&self) -> Vec<(&'static str, &dyn Reflect)> {
+        Vec::new()
+    }
+
+    fn field(&self, name: &str) -> Option<&dyn Reflect> {
+        self.fields()
+            .into_iter()
+            .find(|(field, _)| *field == name)
+            .map(|(_, value)| value)
+    }
+
+    fn field_mut(&mut self, _name: &str) -> Option<&mut dyn Reflect> {
+        None
+    }
+
+    /// Get the variant of an enum.
+    fn variant(&self) -> Option<&'static str> {
+        None
+    }
+
+    /// Switch an enum to the variant named `name`, and get whether it has one.
+    fn set_variant(&mut self, _name: &str) -> bool {
+        false
+    }
+
+    /// Turn the value into JSON. Structs become objects, and enums the name of their variant.
+    fn to_value(&self) -> Value {
+        match self.variant() {
+            Some(variant) => Value::String(variant.to_string()),
+            None => Value::Object(
+                self.fields()
+                    .into_iter()
+                    .map(|(name, value)| (name.to_string(), value.to_value()))
+                    .collect::<Map<_, _>>(),
+            ),
+        }
+    }
+
+    /// Set the value from JSON made by `to_value`.
+    /// Fields missing from an object are left as they are. If this fails, the fields before
+    /// the one that failed are already set.
+    fn apply_value(&mut self, value: &Value) -> Result<(), ReflectError> {
+        let type_name = self.reflect_type_name();
+        let invalid_value = || ReflectError::InvalidValue {
+            type_name,
+            value: value.clone(),
+        };
+
+        if self.variant().is_some() {
+            let variant = value.as_str().ok_or_else(invalid_value)?;
+            return match self.set_variant(variant) {
+                true => Ok(()),
+                false => Err(ReflectError::UnknownVariant {
+                    type_name,
+                    variant: variant.to_string(),
+                }),
+            };
+        }
+
+        let fields = value.as_object().ok_or_else(invalid_value)?;
+        for (name, value) in fields {
+            self.field_mut(name)
+                .ok_or_else(|| ReflectError::UnknownField {
+                    type_name,
+                    field: name.clone(),
+                })?
+                .apply_value(value)?;
+        }
+        Ok(())
+    }
+
+    /// Format the value like `Debug` would, with the type name without its module path.
+    fn debug(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(variant) = self.variant() {
+            return f.write_str(variant);
+        }
+
+        let name = short_type_name(self.reflect_type_name());
+        let fields = self.fields();
+        // The fields of tuple structs are named by their index.
+        if fields.first().is_some_and(|(field, _)| *field == "0") {
+            let mut debug = f.debug_tuple(name);
+            for (_, value) in fields {
+                debug.field(&value);
+            }
+            return debug.finish();
+        }
+
+        let mut debug = f.debug_struct(name);
+        for (field, value) in fields {
+            debug.field(field, &value);
+        }
+        debug.finish()
+    }
+}
+
+impl fmt::Debug for dyn Reflect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.debug(f)
+    }
+}
+
+/// Implements `Reflect` for types that are treated as a single value, using their `Debug` and
+/// serde implementations.
+macro_rules! impl_reflect_value {
+    ($($ty:ty),*) => {
+        $(
+            impl Reflect for $ty {
+                fn type_info() -> TypeInfo {
+                    TypeInfo::new::<Self>(TypeKind::Value)
+                }
+
+                fn as_any(&self) -> &dyn Any {
+                    self
+                }
+
+                fn as_any_mut(&mut self) -> &mut dyn Any {
+                    self
+                }
+
+                fn to_value(&self) -> Value {
+                    serde_json::to_value(self).unwrap_or_default()
+                }
+
+                fn apply_value(&mut self, value: &Value) -> Result<(), ReflectError> {
+                    *self = serde_json::from_value(value.clone()).map_err(|_| {
+                        ReflectError::InvalidValue {
+                            type_name: type_name::<Self>(),
+                            value: value.clone(),
+                        }
+                    })?;
+                    Ok(())
+                }
+
+                fn debug(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                    fmt::Debug::fmt(self, f)
+                }
+            }
+        )*
+    };
+}
+
+impl_reflect_value!(
+    bool, u8, u16, u32, u64, usize, i8, i16, i32, i64, isize, f32, f64, char, String
+);
+
+/// Describes a reflected type.
+#[derive(Clone, Debug)]
+pub struct TypeInfo {
+    type_name: &'static str,
+    type_id: TypeId,
+    kind: TypeKind,
+}
+
+#[derive(Clone, Debug)]
+pub enum TypeKind {
+    /// A struct with its fields, in the order they're declared in.
+    Struct(Vec<FieldInfo>),
+    /// An enum with the names of its variants.
+    Enum(Vec<&'static str>),
+    /// A value without fields, like a number or a string.
+    Value,
+}
+
+#[derive(Clone, Debug)]
+pub struct FieldInfo {
+    name: &'static str,
+    type_name: &'static str,
+    type_id: TypeId,
+}
+
+impl TypeInfo {
+    pub fn new<T: 'static>(kind: TypeKind) -> Self {
+        Self {
+            type_name: type_name::<T>(),
+            type_id: TypeId::of::<T>(),
+            kind,
+        }
+    }
+
+    /// Get the full type name, with the module path.
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+
+    /// Get the type name without the module path, like `Transform`.
+    pub fn short_name(&self) -> &'static str {
+        short_type_name(self.type_name)
+    }
+
+    pub fn type_id(&self) -> TypeId {
+        self.type_id
+    }
+
+    pub fn kind(&self) -> &TypeKind {
+        &self.kind
+    }
+
+    /// Get the fields of a struct, or nothing for other types.
+    pub fn fields(&self) -> &[FieldInfo] {
+        match &self.kind {
+            TypeKind::Struct(fields) => fields,
+            _ => &[],
+        }
+    }
+}
+
+impl FieldInfo {
+    pub fn new<T: 'static>(name: &'static str) -> Self {
+        Self {
+            name,
+            type_name: type_name::<T>(),
+            type_id: TypeId::of::<T>(),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+
+    pub fn type_id(&self) -> TypeId {
+        self.type_id
+    }
+}
+
+/// Get a type name without the module path, keeping the paths in its generic parameters.
+pub(crate) fn short_type_name(name: &'static str) -> &'static str {
+    let path_end = name.find('<').unwrap_or(name.len());
+    match name[..path_end].rfind("::") {
+        Some(index) => &name[index + 2..],
+        None => name,
+    }
+}
+
+type ReflectComponent = fn(&EntityManager, EntityId) -> Option<&dyn Reflect>;
+type ReflectComponentMut = fn(&mut EntityManager, EntityId) -> Option<&mut dyn Reflect>;
+
+struct TypeRegistration {
+    info: TypeInfo,
+    /// Borrows the component of an entity, for types registered as components.
+    component: Option<(ReflectComponent, ReflectComponentMut)>,
+}
+
+/// Keeps track of reflected types, and which of them are components that can be looked up on
+/// entities by name.
+#[derive(Default)]
+pub struct TypeRegistry {
+    types: BTreeMap<&'static str, TypeRegistration>,
+}
+
+impl TypeRegistry {
+    pub fn register<T: Reflect>(&mut self) -> &mut Self {
+        self.types
+            .entry(type_name::<T>())
+            .or_insert_with(|| TypeRegistration {
+                info: T::type_info(),
+                component: None,
+            });
+        self
+    }
+
+    pub(crate) fn register_component<T: Component + Reflect>(&mut self) {
+        self.register::<T>();
+        if let Some(registration) = self.types.get_mut(type_name::<T>()) {
+            registration.component = Some((reflect_component::<T>, reflect_component_mut::<T>));
+        }
+    }
+
+    pub fn get(&self, type_id: TypeId) -> Option<&TypeInfo> {
+        self.iter().find(|info| info.type_id == type_id)
+    }
+
+    /// Get a type by its full name, or its name without the module path.
+    pub fn get_by_name(&self, name: &str) -> Option<&TypeInfo> {
+        self.find(name).map(|registration| &registration.info)
+    }
+
+    /// Get every registered type, ordered by full name.
+    pub fn iter(&self) -> impl Iterator<Item = &TypeInfo> {
+        self.types.values().map(|registration| &registration.info)
+    }
+
+    /// Borrow every reflected component of an entity.
+    pub(crate) fn reflect_components<'a>(
+        &self,
+        manager: &'a EntityManager,
+        entity_id: EntityId,
+    ) -> Vec<&'a dyn Reflect> {
+        self.types
+            .values()
+            .filter_map(|registration| registration.component)
+            .filter_map(|(reflect, _)| reflect(manager, entity_id))
+            .collect()
+    }
+
+    pub(crate) fn reflect_component<'a>(
+        &self,
+        manager: &'a EntityManager,
+        entity_id: EntityId,
+        name: &str,
+    ) -> Option<&'a dyn Reflect> {
+        let (reflect, _) = self.find(name)?.component?;
+        reflect(manager, entity_id)
+    }
+
+    pub(crate) fn reflect_component_mut<'a>(
+        &self,
+        manager: &'a mut EntityManager,
+        entity_id: EntityId,
+        name: &str,
+    ) -> Option<&'a mut dyn Reflect> {
+        let (_, reflect_mut) = self.find(name)?.component?;
+        reflect_mut(manager, entity_id)
+    }
+
+    fn find(&self, name: &str) -> Option<&TypeRegistration> {
+        self.types.get(name).or_else(|| {
+            self.types
+                .values()
+                .find(|registration| registration.info.short_name() == name)
+        })
+    }
+}
+
+fn reflect_component<T: 'static + Component + Reflect>(
+    manager: &EntityManager,
+    entity_id: EntityId,
+) -> Option<&dyn Reflect> {
+    manager
+        .get_component_quietly::<T>(entity_id)
+        .map(|component| component as &dyn Reflect)
+}
+
+fn reflect_component_mut<T: 'static + Component + Reflect>(
+    manager: &mut EntityManager,
+    entity_id: EntityId,
+) -> Option<&mut dyn Reflect> {
+    manager.get_component_quietly::<T>(entity_id)?;
+    manager
+        .borrow_component_mut::<T>(entity_id)
+        .map(|component| component as &mut dyn Reflect)
+}
@@ -0,0 +1,136 @@
+use std::{fmt, marker::PhantomData};
+
+use super::{component::Component, entity::EntityId};
+
+/// A kind of relationship between entities, like "targets", "owned by" or "docked at".
+/// Relate entities with `World::relate`, and the relationship is dropped when either of them
+/// is removed, so it never points at a removed entity.
+pub trait Relationship: 'static {
+    /// Whether an entity can only be related to one entity at a time by this relationship.
+    /// Relating it to another entity then replaces the previous one.
+    const EXCLUSIVE: bool = false;
+}
+
+/// The entities an entity is related to by `R`, in the order they were related.
+/// This is maintained by `relate` and `unrelate` on the world, along with the other entities'
+/// `RelatedBy<R>`, so it can't be added by hand.
+pub struct Relation<R: Relationship> {
+    targets: Vec<EntityId>,
+    relationship: PhantomData<fn() -> R>,
+}
+
+/// The entities related to an entity by `R`, in the order they were related.
+pub struct RelatedBy<R: Relationship> {
+    sources: Vec<EntityId>,
+    relationship: PhantomData<fn() -> R>,
+}
+
+impl<R: Relationship> Relation<R> {
+    /// Get the first entity this one is related to, which is the only one for an exclusive
+    /// relationship.
+    pub fn target(&self) -> Option<EntityId> {
+        self.targets.first().copied()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &EntityId> {
+        self.targets.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.targets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.targets.is_empty()
+    }
+
+    pub fn contains(&self, entity_id: EntityId) -> bool {
+        self.targets.contains(&entity_id)
+    }
+
+    pub(crate) fn push(&mut self, entity_id: EntityId) {
+        self.targets.push(entity_id);
+    }
+
+    pub(crate) fn remove(&mut self, entity_id: EntityId) {
+        self.targets.retain(|target| *target != entity_id);
+    }
+}
+
+impl<R: Relationship> RelatedBy<R> {
+    pub fn iter(&self) -> impl Iterator<Item = &EntityId> {
+        self.sources.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.sources.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sources.is_empty()
+    }
+
+    pub fn contains(&self, entity_id: EntityId) -> bool {
+        self.sources.contains(&entity_id)
+    }
+
+    pub(crate) fn push(&mut self, entity_id: EntityId) {
+        self.sources.push(entity_id);
+    }
+
+    pub(crate) fn remove(&mut self, entity_id: EntityId) {
+        self.sources.retain(|source| *source != entity_id);
+    }
+}
+
+// These are implemented by hand so `R` doesn't need to implement them too.
+impl<R: Relationship> Default for Relation<R> {
+    fn default() -> Self {
+        Self {
+            targets: Vec::new(),
+            relationship: PhantomData,
+        }
+    }
+}
+
+impl<R: Relationship> Default for RelatedBy<R> {
+    fn default() -> Self {
+        Self {
+            sources: Vec::new(),
+            relationship: PhantomData,
+        }
+    }
+}
+
+impl<R: Relationship> Clone for Relation<R> {
+    fn clone(&self) -> Self {
+        Self {
+            targets: self.targets.clone(),
+            relationship: PhantomData,
+        }
+    }
+}
+
+impl<R: Relationship> Clone for RelatedBy<R> {
+    fn clone(&self) -> Self {
+        Self {
+            sources: self.sources.clone(),
+            relationship: PhantomData,
+        }
+    }
+}
+
+impl<R: Relationship> fmt::Debug for Relation<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Relation").field(&self.targets).finish()
+    }
+}
+
+impl<R: Relationship> fmt::Debug for RelatedBy<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("RelatedBy").field(&self.sources).finish()
+    }
+}
+
+impl<R: Relationship> Component for Relation<R> {}
+impl<R: Relationship> Component for RelatedBy<R> {}
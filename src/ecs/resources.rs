@@ -0,0 +1,87 @@
+use std::{
+    any::{type_name, Any, TypeId},
+    collections::HashMap,
+};
+use tracing::*;
+
+use super::change_detection::is_newer;
+
+/// A change to the resources sent through commands, waiting in the entity manager until the
+/// world makes it.
+pub(crate) type ResourceChange = Box<dyn FnOnce(&mut Resources) + Send + Sync>;
+
+/// Holds global data that isn't tied to an entity, like the score, delta time or the input.
+/// There's at most one resource of each type. Resources are shared with systems running in
+/// parallel, so they have to be `Send` and `Sync`.
+///
+/// Like components, resources remember the frame they last changed in. Inserting a resource or
+/// borrowing it mutably counts as a change.
+#[derive(Default)]
+pub struct Resources {
+    resource_map: HashMap<TypeId, Box<dyn Any + Send + Sync>>,
+    changed_ticks: HashMap<TypeId, u64>,
+    /// The current frame, which changes are marked in.
+    tick: u64,
+}
+
+impl Resources {
+    /// Insert a resource, replacing the previous resource of the same type.
+    pub fn insert<T: 'static + Send + Sync>(&mut self, resource: T) -> &mut Self {
+        self.resource_map
+            .insert(TypeId::of::<T>(), Box::new(resource));
+        self.changed_ticks.insert(TypeId::of::<T>(), self.tick);
+        self
+    }
+
+    /// Remove a resource and return it, if it exists.
+    pub fn remove<T: 'static + Send + Sync>(&mut self) -> Option<T> {
+        self.changed_ticks.remove(&TypeId::of::<T>());
+        self.resource_map
+            .remove(&TypeId::of::<T>())
+            .map(|resource| *resource.downcast::<T>().unwrap())
+    }
+
+    pub fn contains<T: 'static + Send + Sync>(&self) -> bool {
+        self.resource_map.contains_key(&TypeId::of::<T>())
+    }
+
+    pub fn get<T: 'static + Send + Sync>(&self) -> Option<&T> {
+        match self.resource_map.get(&TypeId::of::<T>()) {
+            Some(resource) => resource.downcast_ref::<T>(),
+            None => {
+                debug!("Resource {} does not exist.", type_name::<T>());
+                None
+            }
+        }
+    }
+
+    /// Mutably borrow a resource, marking it as changed.
+    pub fn get_mut<T: 'static + Send + Sync>(&mut self) -> Option<&mut T> {
+        match self.resource_map.get_mut(&TypeId::of::<T>()) {
+            Some(resource) => {
+                self.changed_ticks.insert(TypeId::of::<T>(), self.tick);
+                resource.downcast_mut::<T>()
+            }
+            None => {
+                debug!("Resource {} does not exist.", type_name::<T>());
+                None
+            }
+        }
+    }
+
+    /// Get the frame a resource was inserted or last borrowed mutably in.
+    pub fn changed_tick<T: 'static + Send + Sync>(&self) -> Option<u64> {
+        self.changed_ticks.get(&TypeId::of::<T>()).copied()
+    }
+
+    /// Check if a resource was inserted or changed after the frame `last_run`.
+    pub fn is_changed<T: 'static + Send + Sync>(&self, last_run: Option<u64>) -> bool {
+        self.changed_tick::<T>()
+            .is_some_and(|tick| is_newer(tick, last_run))
+    }
+
+    /// Set the current frame, which changes are marked in.
+    pub(crate) fn set_tick(&mut self, tick: u64) {
+        self.tick = tick;
+    }
+}
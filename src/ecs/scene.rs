@@ -0,0 +1,455 @@
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+    any::{type_name, Any, TypeId},
+    collections::{BTreeMap, HashMap},
+};
+use tracing::*;
+
+use super::{
+    component::Component,
+    entity::EntityId,
+    entity_manager::EntityManager,
+    hierarchy::Parent,
+    prefab::{Prefab, PrefabInsert, Prefabs},
+    reflect::short_type_name,
+};
+use crate::errors::SceneError;
+
+/// Maps the entity IDs saved in a scene to the entities they were loaded as.
+#[derive(Clone, Debug, Default)]
+pub struct EntityMap {
+    map: HashMap<EntityId, EntityId>,
+}
+
+impl EntityMap {
+    /// Get the entity a saved entity ID was loaded as.
+    pub fn get(&self, entity_id: EntityId) -> Option<EntityId> {
+        self.map.get(&entity_id).copied()
+    }
+
+    /// Get the entity a saved entity ID was loaded as, failing if it wasn't part of the scene.
+    pub fn map(&self, entity_id: EntityId) -> Result<EntityId, SceneError> {
+        self.get(entity_id)
+            .ok_or(SceneError::UnmappedEntity(entity_id))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&EntityId, &EntityId)> {
+        self.map.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    pub(crate) fn insert(&mut self, from: EntityId, to: EntityId) {
+        self.map.insert(from, to);
+    }
+}
+
+/// Implemented by components that refer to other entities, so those references can point at
+/// the right entities after a scene is loaded.
+pub trait MapEntities {
+    /// Replace every entity ID with the one `EntityMap::map` gives, failing if one of them
+    /// isn't part of the scene.
+    fn map_entities(&mut self, entity_map: &EntityMap) -> Result<(), SceneError>;
+}
+
+/// The components of an entity or prefab by name.
+pub(crate) type SceneComponents = BTreeMap<String, Value>;
+
+/// The format scenes are saved in.
+/// Both parts are optional, so a file can hold just prefabs.
+#[derive(Default, Serialize, Deserialize)]
+pub(crate) struct SceneData {
+    /// Prefabs by name, which are added to the world when the scene is loaded.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub(crate) prefabs: BTreeMap<String, SceneComponents>,
+    #[serde(default)]
+    pub(crate) entities: Vec<SceneEntity>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct SceneEntity {
+    pub(crate) id: EntityId,
+    /// The prefab the entity is spawned from. Its own components replace the prefab's.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) prefab: Option<String>,
+    #[serde(default)]
+    pub(crate) components: SceneComponents,
+}
+
+/// A component read from a scene, waiting to be added to its entity.
+pub(crate) trait LoadedComponent: Send {
+    /// Point the entity IDs the component refers to at the entities they were loaded as.
+    fn map_entities(&mut self, entity_map: &EntityMap) -> Result<(), SceneError>;
+
+    fn component_type_id(&self) -> TypeId;
+
+    fn as_any(&self) -> &dyn Any;
+
+    fn insert(self: Box<Self>, manager: &mut EntityManager, entity_id: EntityId);
+}
+
+struct Loaded<T> {
+    component: T,
+    map_entities: fn(&mut T, &EntityMap) -> Result<(), SceneError>,
+    insert: fn(&mut EntityManager, EntityId, T),
+}
+
+impl<T: 'static + Send> LoadedComponent for Loaded<T> {
+    fn map_entities(&mut self, entity_map: &EntityMap) -> Result<(), SceneError> {
+        (self.map_entities)(&mut self.component, entity_map)
+    }
+
+    fn component_type_id(&self) -> TypeId {
+        TypeId::of::<T>()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        &self.component
+    }
+
+    fn insert(self: Box<Self>, manager: &mut EntityManager, entity_id: EntityId) {
+        (self.insert)(manager, entity_id, self.component);
+    }
+}
+
+type DeserializeComponent = fn(Value) -> Result<Box<dyn LoadedComponent>, serde_json::Error>;
+
+struct SerializableComponent {
+    serialize: fn(&EntityManager, EntityId) -> Option<Result<Value, serde_json::Error>>,
+    deserialize: DeserializeComponent,
+}
+
+/// Keeps track of the components that can be saved in scenes, by name.
+#[derive(Default)]
+pub(crate) struct SceneRegistry {
+    components: BTreeMap<&'static str, SerializableComponent>,
+}
+
+impl SceneRegistry {
+    pub(crate) fn register<T>(&mut self)
+    where
+        T: 'static + Component + Serialize + DeserializeOwned,
+    {
+        self.insert::<T>(SerializableComponent {
+            serialize: serialize_component::<T>,
+            deserialize: deserialize_component::<T>,
+        });
+    }
+
+    pub(crate) fn register_with_entities<T>(&mut self)
+    where
+        T: 'static + Component + Serialize + DeserializeOwned + MapEntities,
+    {
+        self.insert::<T>(SerializableComponent {
+            serialize: serialize_component::<T>,
+            deserialize: deserialize_component_with_entities::<T>,
+        });
+    }
+
+    /// Register `Parent`, which is loaded through `set_parent` so the parent's `Children` are
+    /// filled in too.
+    pub(crate) fn register_parent(&mut self) {
+        self.insert::<Parent>(SerializableComponent {
+            serialize: serialize_component::<Parent>,
+            deserialize: deserialize_parent,
+        });
+    }
+
+    fn insert<T>(&mut self, component: SerializableComponent) {
+        let name = component_name::<T>();
+        if self.components.insert(name, component).is_some() {
+            warn!(
+                "{} replaced another serializable component named `{name}`.",
+                type_name::<T>()
+            );
+        }
+    }
+
+    /// Save every entity with at least one serializable component.
+    pub(crate) fn save(&self, manager: &EntityManager) -> Result<SceneData, SceneError> {
+        let mut scene = SceneData::default();
+
+        for entity_id in manager.entity_ids() {
+            let mut components = BTreeMap::new();
+            for (name, component) in self.components.iter() {
+                if let Some(value) = (component.serialize)(manager, entity_id) {
+                    let value = value.map_err(|source| SceneError::InvalidComponent {
+                        entity_id,
+                        name: name.to_string(),
+                        source,
+                    })?;
+                    components.insert(name.to_string(), value);
+                }
+            }
+
+            if !components.is_empty() {
+                scene.entities.push(SceneEntity {
+                    id: entity_id,
+                    prefab: None,
+                    components,
+                });
+            }
+        }
+
+        Ok(scene)
+    }
+
+    /// Read the components of a scene entity, checking all of them before anything is added to
+    /// the world.
+    pub(crate) fn deserialize_components(
+        &self,
+        entity: SceneEntity,
+    ) -> Result<Vec<Box<dyn LoadedComponent>>, SceneError> {
+        entity
+            .components
+            .into_iter()
+            .map(|(name, value)| {
+                let component = self.components.get(name.as_str()).ok_or_else(|| {
+                    SceneError::UnknownComponent {
+                        entity_id: entity.id,
+                        name: name.clone(),
+                    }
+                })?;
+                (component.deserialize)(value).map_err(|source| SceneError::InvalidComponent {
+                    entity_id: entity.id,
+                    name,
+                    source,
+                })
+            })
+            .collect()
+    }
+
+    /// Turn the components of a scene prefab into a prefab, checking that every one of them can
+    /// be loaded. Prefabs are spawned apart from the scene, so their components can't refer to
+    /// entities.
+    /// The components are kept as data and loaded again every time the prefab is spawned.
+    pub(crate) fn deserialize_prefab(
+        &self,
+        prefab: &str,
+        components: SceneComponents,
+    ) -> Result<Prefab, SceneError> {
+        let inserts = components
+            .into_iter()
+            .map(|(name, value)| {
+                let component = self.components.get(name.as_str()).ok_or_else(|| {
+                    SceneError::UnknownPrefabComponent {
+                        prefab: prefab.to_string(),
+                        name: name.clone(),
+                    }
+                })?;
+                let type_id = match (component.deserialize)(value.clone()) {
+                    Ok(mut loaded) => {
+                        loaded.map_entities(&EntityMap::default())?;
+                        loaded.component_type_id()
+                    }
+                    Err(source) => {
+                        return Err(SceneError::InvalidPrefabComponent {
+                            prefab: prefab.to_string(),
+                            name,
+                            source,
+                        })
+                    }
+                };
+
+                let deserialize = component.deserialize;
+                let insert: PrefabInsert = Box::new(move |manager, entity_id, overridden| {
+                    if overridden.contains(&type_id) {
+                        return;
+                    }
+                    match deserialize(value.clone()) {
+                        Ok(loaded) => loaded.insert(manager, entity_id),
+                        Err(e) => error!("Failed to add the component `{name}` of a prefab: {e}"),
+                    }
+                });
+                Ok(insert)
+            })
+            .collect::<Result<Vec<_>, SceneError>>()?;
+
+        Ok(Prefab::from_inserts(inserts))
+    }
+
+    /// Load the prefabs of a scene into `prefabs`, and its entities as new entities.
+    /// Entities with a prefab get their own components, and the prefab's of the other types.
+    /// Nothing is added if any of the prefabs or components can't be loaded, refer to an entity
+    /// that isn't in the scene, or make an entity its own ancestor.
+    pub(crate) fn load(
+        &self,
+        manager: &mut EntityManager,
+        prefabs: &mut Prefabs,
+        scene: SceneData,
+    ) -> Result<EntityMap, SceneError> {
+        let new_prefabs = scene
+            .prefabs
+            .into_iter()
+            .map(|(name, components)| {
+                let prefab = self.deserialize_prefab(&name, components)?;
+                Ok((name, prefab))
+            })
+            .collect::<Result<Vec<_>, SceneError>>()?;
+
+        let mut scene_ids = EntityMap::default();
+        for entity in scene.entities.iter() {
+            if scene_ids.get(entity.id).is_some() {
+                return Err(SceneError::DuplicateEntity(entity.id));
+            }
+            scene_ids.insert(entity.id, entity.id);
+        }
+
+        let mut entities = scene
+            .entities
+            .into_iter()
+            .map(|entity| {
+                if let Some(prefab) = &entity.prefab {
+                    if !prefabs.contains(prefab)
+                        && !new_prefabs.iter().any(|(name, _)| name == prefab)
+                    {
+                        return Err(SceneError::UnknownPrefab {
+                            entity_id: entity.id,
+                            name: prefab.clone(),
+                        });
+                    }
+                }
+                Ok((
+                    entity.id,
+                    entity.prefab.clone(),
+                    self.deserialize_components(entity)?,
+                ))
+            })
+            .collect::<Result<Vec<_>, SceneError>>()?;
+
+        // Mapping every ID to itself checks the references without changing them.
+        for (_, _, components) in entities.iter_mut() {
+            for component in components.iter_mut() {
+                component.map_entities(&scene_ids)?;
+            }
+        }
+        check_parent_cycles(&entities)?;
+
+        let mut entity_map = EntityMap::default();
+        for (id, _, _) in entities.iter() {
+            entity_map.insert(*id, manager.create_entity());
+        }
+
+        for (name, prefab) in new_prefabs {
+            prefabs.insert(name, prefab);
+        }
+
+        if let Err(error) = insert_entities(manager, prefabs, &entity_map, entities) {
+            // Don't leave the scene half loaded.
+            manager.despawn_batch(entity_map.iter().map(|(_, entity_id)| *entity_id));
+            return Err(error);
+        }
+        Ok(entity_map)
+    }
+}
+
+/// The entities of a scene with their prefab and components, by the ID they were saved with.
+type LoadedEntities = Vec<(EntityId, Option<String>, Vec<Box<dyn LoadedComponent>>)>;
+
+/// Fail if following the parents of the entities in a scene leads back to where it started.
+fn check_parent_cycles(entities: &LoadedEntities) -> Result<(), SceneError> {
+    let parents = entities
+        .iter()
+        .filter_map(|(id, _, components)| {
+            let parent = components
+                .iter()
+                .find_map(|component| component.as_any().downcast_ref::<Parent>())?;
+            Some((*id, parent.get()))
+        })
+        .collect::<HashMap<_, _>>();
+
+    for id in parents.keys() {
+        // A cycle that doesn't go through this entity is found from one of the entities in it.
+        let mut ancestor = parents.get(id);
+        for _ in 0..parents.len() {
+            match ancestor {
+                Some(ancestor) if ancestor == id => return Err(SceneError::ParentCycle(*id)),
+                Some(entity_id) => ancestor = parents.get(entity_id),
+                None => break,
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Add the components of a scene's entities to the entities they're loaded as.
+fn insert_entities(
+    manager: &mut EntityManager,
+    prefabs: &Prefabs,
+    entity_map: &EntityMap,
+    entities: LoadedEntities,
+) -> Result<(), SceneError> {
+    for (id, prefab, components) in entities {
+        let entity_id = entity_map.map(id)?;
+        if let Some(prefab) = prefab.and_then(|name| prefabs.get(&name)) {
+            let overridden = components
+                .iter()
+                .map(|component| component.component_type_id())
+                .collect::<Vec<_>>();
+            prefab.add_to_entity(manager, entity_id, &overridden);
+        }
+        for mut component in components {
+            component.map_entities(entity_map)?;
+            component.insert(manager, entity_id);
+        }
+    }
+    Ok(())
+}
+
+/// Get the name a component is saved under, which is its type name without the module path.
+pub(crate) fn component_name<T>() -> &'static str {
+    short_type_name(type_name::<T>())
+}
+
+fn serialize_component<T: 'static + Component + Serialize>(
+    manager: &EntityManager,
+    entity_id: EntityId,
+) -> Option<Result<Value, serde_json::Error>> {
+    manager
+        .get_component_quietly::<T>(entity_id)
+        .map(serde_json::to_value)
+}
+
+fn deserialize_component<T: 'static + Component + DeserializeOwned>(
+    value: Value,
+) -> Result<Box<dyn LoadedComponent>, serde_json::Error> {
+    Ok(Box::new(Loaded {
+        component: serde_json::from_value::<T>(value)?,
+        map_entities: |_, _| Ok(()),
+        insert: |manager, entity_id, component| {
+            manager.insert_component(entity_id, component);
+        },
+    }))
+}
+
+fn deserialize_component_with_entities<T>(
+    value: Value,
+) -> Result<Box<dyn LoadedComponent>, serde_json::Error>
+where
+    T: 'static + Component + DeserializeOwned + MapEntities,
+{
+    Ok(Box::new(Loaded {
+        component: serde_json::from_value::<T>(value)?,
+        map_entities: T::map_entities,
+        insert: |manager, entity_id, component| {
+            manager.insert_component(entity_id, component);
+        },
+    }))
+}
+
+fn deserialize_parent(value: Value) -> Result<Box<dyn LoadedComponent>, serde_json::Error> {
+    Ok(Box::new(Loaded {
+        component: serde_json::from_value::<Parent>(value)?,
+        map_entities: Parent::map_entities,
+        insert: |manager, entity_id, parent| {
+            manager.set_parent(entity_id, parent.get());
+        },
+    }))
+}
@@ -0,0 +1,301 @@
+use std::{collections::HashMap, ops::Range};
+use tracing::*;
+
+use super::{
+    condition::RunCondition,
+    entity_manager::EntityIdAccessor,
+    resources::Resources,
+    system::{ParallelSystem, RenderSystem, System, SystemAccess},
+};
+use crate::errors::EcsError;
+
+/// The stages systems run in. Every update runs the pre-update, fixed update, update and
+/// post-update stages in that order, and every render runs the render stage.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Stage {
+    PreUpdate,
+    /// Runs at a fixed rate however often the world updates, as many times as the time passed
+    /// calls for. This is where physics goes. See `FixedTimestep`.
+    FixedUpdate,
+    #[default]
+    Update,
+    PostUpdate,
+    /// Holds the render systems.
+    Render,
+}
+
+/// How the systems in a stage are run.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ExecutionMode {
+    /// Parallel systems that don't conflict run at the same time on the thread pool.
+    #[default]
+    Parallel,
+    /// Every system runs one after another on the calling thread, which is deterministic and
+    /// easier to debug. Systems that would share a batch still run in the same frame, and their
+    /// commands are applied once the whole batch is done, just like in parallel.
+    Serial,
+}
+
+/// Describes where a system is ordered within its stage.
+/// Systems without constraints run in the order they were added in.
+#[derive(Clone, Debug, Default)]
+pub struct SystemConfig {
+    /// A name other systems can refer to in `before` and `after`.
+    /// Several systems can share a label, so they can be ordered as a group.
+    pub label: Option<&'static str>,
+    /// The labels of the systems this system runs before.
+    pub before: Vec<&'static str>,
+    /// The labels of the systems this system runs after.
+    pub after: Vec<&'static str>,
+    /// The system only runs when all of these hold.
+    pub conditions: Vec<RunCondition>,
+}
+
+impl SystemConfig {
+    /// Only run the system when `condition` holds, like `run_if(in_state(GameState::Playing))`.
+    pub fn run_if(
+        mut self,
+        condition: impl Fn(&Resources, Option<u64>) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.conditions.push(RunCondition::new(condition));
+        self
+    }
+}
+
+/// A system in one of the update stages.
+pub(crate) enum UpdateSystem {
+    Exclusive(Box<dyn System>),
+    /// A parallel system, along with the access it declared.
+    Parallel(Box<dyn ParallelSystem>, SystemAccess),
+}
+
+/// Anything a stage can schedule.
+pub(crate) trait Schedulable {
+    /// Get the components the system declared it uses, or `None` if the system needs the whole
+    /// world to itself.
+    fn access(&self) -> Option<&SystemAccess>;
+}
+
+impl Schedulable for UpdateSystem {
+    fn access(&self) -> Option<&SystemAccess> {
+        match self {
+            UpdateSystem::Exclusive(_) => None,
+            UpdateSystem::Parallel(_, access) => Some(access),
+        }
+    }
+}
+
+impl Schedulable for Box<dyn RenderSystem> {
+    fn access(&self) -> Option<&SystemAccess> {
+        None
+    }
+}
+
+pub(crate) struct ScheduledSystem<S> {
+    pub(crate) system: S,
+    /// Every system gets its own accessor, so it remembers when that system last ran.
+    pub(crate) accessor: EntityIdAccessor,
+    name: &'static str,
+    config: SystemConfig,
+}
+
+impl<S> ScheduledSystem<S> {
+    /// Check the run conditions of the system.
+    pub(crate) fn should_run(&self, resources: &Resources) -> bool {
+        let last_run = self.accessor.last_run();
+        self.config
+            .conditions
+            .iter()
+            .all(|condition| condition.check(resources, last_run))
+    }
+
+    fn describe(&self) -> String {
+        match self.config.label {
+            Some(label) => format!("{} (`{label}`)", self.name),
+            None => self.name.to_string(),
+        }
+    }
+}
+
+/// The systems in a stage, kept sorted by their `before` and `after` constraints.
+pub(crate) struct SystemStage<S: Schedulable> {
+    stage: Stage,
+    systems: Vec<ScheduledSystem<S>>,
+    /// Runs of systems that can run at the same time, in the order they run in.
+    batches: Vec<Range<usize>>,
+    sorted: bool,
+}
+
+impl<S: Schedulable> SystemStage<S> {
+    pub(crate) fn new(stage: Stage) -> Self {
+        Self {
+            stage,
+            systems: Vec::new(),
+            batches: Vec::new(),
+            sorted: true,
+        }
+    }
+
+    pub(crate) fn add(&mut self, system: S, name: &'static str, config: SystemConfig) {
+        self.systems.push(ScheduledSystem {
+            system,
+            accessor: EntityIdAccessor::default(),
+            name,
+            config,
+        });
+        self.sorted = false;
+    }
+
+    /// Get the systems in the order they run in.
+    /// The stage has to be sorted first.
+    pub(crate) fn systems_mut(&mut self) -> &mut Vec<ScheduledSystem<S>> {
+        &mut self.systems
+    }
+
+    /// Get the batches of systems that can run at the same time.
+    /// The stage has to be sorted first.
+    pub(crate) fn batches(&self) -> Vec<Range<usize>> {
+        self.batches.clone()
+    }
+
+    /// Sort the systems so every `before` and `after` constraint holds, keeping the order they
+    /// were added in otherwise. This fails if the constraints form a cycle.
+    pub(crate) fn sort(&mut self) -> Result<(), EcsError> {
+        if self.sorted {
+            return Ok(());
+        }
+
+        let edges = self.edges();
+        let mut dependency_counts = vec![0; self.systems.len()];
+        for &(_, to) in edges.iter() {
+            dependency_counts[to] += 1;
+        }
+
+        // Always pick the earliest added system that is ready, so the order is deterministic.
+        let mut order = Vec::with_capacity(self.systems.len());
+        while let Some(index) = (0..self.systems.len())
+            .find(|&index| dependency_counts[index] == 0 && !order.contains(&index))
+        {
+            order.push(index);
+            for &(_, to) in edges.iter().filter(|(from, _)| *from == index) {
+                dependency_counts[to] -= 1;
+            }
+        }
+
+        if order.len() < self.systems.len() {
+            return Err(EcsError::SystemOrderCycle(
+                self.stage,
+                self.find_cycle(&edges, &order),
+            ));
+        }
+
+        let mut positions = vec![0; order.len()];
+        for (position, &index) in order.iter().enumerate() {
+            positions[index] = position;
+        }
+        let edges = edges
+            .into_iter()
+            .map(|(from, to)| (positions[from], positions[to]))
+            .collect::<Vec<_>>();
+
+        let mut systems = self.systems.drain(..).map(Some).collect::<Vec<_>>();
+        self.systems = order
+            .into_iter()
+            .map(|index| systems[index].take().unwrap())
+            .collect();
+        self.batch(&edges);
+        self.sorted = true;
+
+        Ok(())
+    }
+
+    /// Split the sorted systems into runs of parallel systems that don't conflict and aren't
+    /// ordered against each other. Every other system gets a batch to itself.
+    fn batch(&mut self, edges: &[(usize, usize)]) {
+        self.batches.clear();
+
+        let mut start = 0;
+        for index in 0..self.systems.len() {
+            let fits = self.systems[index].system.access().is_some_and(|access| {
+                (start..index).all(|other| {
+                    self.systems[other]
+                        .system
+                        .access()
+                        .is_some_and(|other_access| !access.conflicts_with(other_access))
+                        && !edges.contains(&(other, index))
+                })
+            });
+
+            if !fits && start < index {
+                self.batches.push(start..index);
+                start = index;
+            }
+        }
+        if start < self.systems.len() {
+            self.batches.push(start..self.systems.len());
+        }
+    }
+
+    /// Get every `(before, after)` pair of system indexes the constraints ask for.
+    fn edges(&self) -> Vec<(usize, usize)> {
+        let mut labels: HashMap<&'static str, Vec<usize>> = HashMap::new();
+        for (index, system) in self.systems.iter().enumerate() {
+            if let Some(label) = system.config.label {
+                labels.entry(label).or_default().push(index);
+            }
+        }
+
+        let mut edges = Vec::new();
+        for (index, system) in self.systems.iter().enumerate() {
+            for label in system.config.before.iter() {
+                match labels.get(label) {
+                    Some(others) => edges.extend(others.iter().map(|&other| (index, other))),
+                    None => warn!(
+                        "{} runs before `{label}`, but no system in the {:?} stage has that label.",
+                        system.name, self.stage
+                    ),
+                }
+            }
+            for label in system.config.after.iter() {
+                match labels.get(label) {
+                    Some(others) => edges.extend(others.iter().map(|&other| (other, index))),
+                    None => warn!(
+                        "{} runs after `{label}`, but no system in the {:?} stage has that label.",
+                        system.name, self.stage
+                    ),
+                }
+            }
+        }
+
+        edges
+    }
+
+    /// Describe a cycle among the systems that couldn't be sorted, e.g. `A -> B -> A`.
+    fn find_cycle(&self, edges: &[(usize, usize)], sorted: &[usize]) -> String {
+        // Every unsorted system waits on another unsorted system, so walking backwards along
+        // those dependencies has to come back around to a system it already visited.
+        let mut path = vec![(0..self.systems.len())
+            .find(|index| !sorted.contains(index))
+            .unwrap()];
+        loop {
+            let current = *path.last().unwrap();
+            let previous = edges
+                .iter()
+                .find(|&&(from, to)| to == current && !sorted.contains(&from))
+                .map(|&(from, _)| from)
+                .unwrap();
+
+            if let Some(start) = path.iter().position(|&index| index == previous) {
+                let mut cycle = path[start..]
+                    .iter()
+                    .rev()
+                    .map(|&index| self.systems[index].describe())
+                    .collect::<Vec<_>>();
+                cycle.push(cycle[0].clone());
+                return cycle.join(" -> ");
+            }
+
+            path.push(previous);
+        }
+    }
+}
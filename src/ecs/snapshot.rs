@@ -0,0 +1,33 @@
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    sync::Arc,
+};
+
+use super::entity_manager::Entities;
+
+/// A copy of the entities of a world and their cloneable components, taken by
+/// `World::snapshot` and brought back with `World::restore`.
+/// The copy is shared, so cloning a snapshot is cheap.
+#[derive(Clone)]
+pub struct Snapshot {
+    data: Arc<SnapshotData>,
+}
+
+pub(crate) struct SnapshotData {
+    pub(crate) entities: Entities,
+    /// The storage of every cloneable component type.
+    pub(crate) storages: HashMap<TypeId, Box<dyn Any + Send + Sync>>,
+}
+
+impl Snapshot {
+    pub(crate) fn new(data: SnapshotData) -> Self {
+        Self {
+            data: Arc::new(data),
+        }
+    }
+
+    pub(crate) fn data(&self) -> &SnapshotData {
+        &self.data
+    }
+}
@@ -0,0 +1,488 @@
+use super::{change_detection::ComponentTicks, entity::EntityId};
+use std::{
+    collections::HashMap,
+    marker::PhantomData,
+    mem,
+    ptr::{self, NonNull},
+    slice,
+};
+
+/// How the components of one type are stored.
+/// `Component::STORAGE` picks the default, which `register_component_with_storage` overrides.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum StorageKind {
+    /// Components are packed together and found through a hash map.
+    /// Iterating is fast, and entities without the component don't take up any memory.
+    #[default]
+    Table,
+    /// Components are packed together and found through a list indexed by entity.
+    /// Adding and removing components is faster than a table, but the list grows with the
+    /// highest entity index.
+    SparseSet,
+    /// Only a bitset of the entities with the component is kept, which makes adding and removing
+    /// it very cheap. This is only for zero-sized marker components.
+    Tag,
+}
+
+/// A growable set of indexes.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct BitSet {
+    blocks: Vec<u64>,
+}
+
+impl BitSet {
+    pub(crate) fn contains(&self, index: usize) -> bool {
+        self.blocks
+            .get(index / 64)
+            .is_some_and(|block| block & (1 << (index % 64)) != 0)
+    }
+
+    pub(crate) fn insert(&mut self, index: usize) {
+        if index / 64 >= self.blocks.len() {
+            self.blocks.resize(index / 64 + 1, 0);
+        }
+        self.blocks[index / 64] |= 1 << (index % 64);
+    }
+
+    pub(crate) fn remove(&mut self, index: usize) {
+        if let Some(block) = self.blocks.get_mut(index / 64) {
+            *block &= !(1 << (index % 64));
+        }
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.blocks
+            .iter()
+            .enumerate()
+            .flat_map(|(block_index, block)| {
+                let mut block = *block;
+                std::iter::from_fn(move || {
+                    if block == 0 {
+                        return None;
+                    }
+                    let bit = block.trailing_zeros() as usize;
+                    block &= block - 1;
+                    Some(block_index * 64 + bit)
+                })
+            })
+    }
+}
+
+/// Marks an unused slot in a sparse set.
+const EMPTY_SLOT: usize = usize::MAX;
+
+/// Finds the slot of an entity's component in packed storage.
+#[derive(Clone)]
+enum SlotLookup {
+    Map(HashMap<EntityId, usize>),
+    /// Slots by entity index. Entity IDs are checked against the packed IDs, so a stale ID with
+    /// the same index doesn't match.
+    Sparse(Vec<usize>),
+}
+
+/// Components packed together, for the table and sparse set storage kinds.
+/// The components of disabled entities are kept after the enabled ones, so the enabled ones can
+/// be handed out as one slice.
+#[derive(Clone)]
+pub(crate) struct PackedStorage<T> {
+    components: Vec<T>,
+    ticks: Vec<ComponentTicks>,
+    entity_ids: Vec<EntityId>,
+    lookup: SlotLookup,
+    /// The number of components at the start of the lists that belong to enabled entities.
+    enabled_count: usize,
+}
+
+impl<T> PackedStorage<T> {
+    fn new(lookup: SlotLookup) -> Self {
+        Self {
+            components: Vec::new(),
+            ticks: Vec::new(),
+            entity_ids: Vec::new(),
+            lookup,
+            enabled_count: 0,
+        }
+    }
+
+    fn slot(&self, entity_id: EntityId) -> Option<usize> {
+        match &self.lookup {
+            SlotLookup::Map(map) => map.get(&entity_id).copied(),
+            SlotLookup::Sparse(slots) => slots
+                .get(entity_id.index())
+                .copied()
+                .filter(|slot| *slot != EMPTY_SLOT && self.entity_ids[*slot] == entity_id),
+        }
+    }
+
+    fn set_slot(&mut self, entity_id: EntityId, slot: usize) {
+        match &mut self.lookup {
+            SlotLookup::Map(map) => {
+                map.insert(entity_id, slot);
+            }
+            SlotLookup::Sparse(slots) => {
+                if entity_id.index() >= slots.len() {
+                    slots.resize(entity_id.index() + 1, EMPTY_SLOT);
+                }
+                slots[entity_id.index()] = slot;
+            }
+        }
+    }
+
+    fn clear_slot(&mut self, entity_id: EntityId) {
+        match &mut self.lookup {
+            SlotLookup::Map(map) => {
+                map.remove(&entity_id);
+            }
+            SlotLookup::Sparse(slots) => slots[entity_id.index()] = EMPTY_SLOT,
+        }
+    }
+
+    fn push(&mut self, entity_id: EntityId, component: T, ticks: ComponentTicks) -> usize {
+        self.components.push(component);
+        self.ticks.push(ticks);
+        self.entity_ids.push(entity_id);
+        self.set_slot(entity_id, self.components.len() - 1);
+
+        // New components belong to enabled entities until told otherwise.
+        let slot = self.enabled_count;
+        self.swap(slot, self.components.len() - 1);
+        self.enabled_count += 1;
+        slot
+    }
+
+    fn remove(&mut self, entity_id: EntityId) -> Option<T> {
+        let mut slot = self.slot(entity_id)?;
+
+        // Move the component to the end of the enabled ones first, so swapping in the last
+        // component doesn't move a disabled one among them.
+        if slot < self.enabled_count {
+            self.enabled_count -= 1;
+            self.swap(slot, self.enabled_count);
+            slot = self.enabled_count;
+        }
+
+        let last = self.components.len() - 1;
+        self.swap(slot, last);
+        self.clear_slot(entity_id);
+        self.ticks.pop();
+        self.entity_ids.pop();
+        self.components.pop()
+    }
+
+    fn set_enabled(&mut self, slot: usize, enabled: bool) {
+        if enabled && slot >= self.enabled_count {
+            self.swap(slot, self.enabled_count);
+            self.enabled_count += 1;
+        } else if !enabled && slot < self.enabled_count {
+            self.enabled_count -= 1;
+            self.swap(slot, self.enabled_count);
+        }
+    }
+
+    fn swap(&mut self, a: usize, b: usize) {
+        if a == b {
+            return;
+        }
+
+        self.components.swap(a, b);
+        self.ticks.swap(a, b);
+        self.entity_ids.swap(a, b);
+        self.set_slot(self.entity_ids[a], a);
+        self.set_slot(self.entity_ids[b], b);
+    }
+}
+
+/// Zero-sized components, kept as bits by entity index.
+/// As the components have no data, any well-aligned pointer is a valid one to hand out.
+#[derive(Clone)]
+pub(crate) struct TagStorage {
+    tagged: BitSet,
+    disabled: BitSet,
+    /// The generation of the entity each bit was set for, by entity index.
+    generations: Vec<u32>,
+    /// The change ticks of each tag, by entity index.
+    ticks: Vec<ComponentTicks>,
+    enabled_count: usize,
+}
+
+impl TagStorage {
+    fn new() -> Self {
+        Self {
+            tagged: BitSet::default(),
+            disabled: BitSet::default(),
+            generations: Vec::new(),
+            ticks: Vec::new(),
+            enabled_count: 0,
+        }
+    }
+
+    fn slot(&self, entity_id: EntityId) -> Option<usize> {
+        let index = entity_id.index();
+        (self.tagged.contains(index) && self.generations[index] == entity_id.generation())
+            .then_some(index)
+    }
+
+    fn push(&mut self, entity_id: EntityId, ticks: ComponentTicks) -> usize {
+        let index = entity_id.index();
+        if index >= self.generations.len() {
+            self.generations.resize(index + 1, 0);
+            self.ticks.resize(index + 1, ComponentTicks::new(0));
+        }
+
+        self.tagged.insert(index);
+        self.disabled.remove(index);
+        self.generations[index] = entity_id.generation();
+        self.ticks[index] = ticks;
+        self.enabled_count += 1;
+        index
+    }
+
+    fn remove(&mut self, entity_id: EntityId) -> Option<()> {
+        let index = self.slot(entity_id)?;
+        if !self.disabled.contains(index) {
+            self.enabled_count -= 1;
+        }
+        self.tagged.remove(index);
+        self.disabled.remove(index);
+        Some(())
+    }
+
+    fn set_enabled(&mut self, slot: usize, enabled: bool) {
+        match (enabled, self.disabled.contains(slot)) {
+            (true, true) => {
+                self.disabled.remove(slot);
+                self.enabled_count += 1;
+            }
+            (false, false) => {
+                self.disabled.insert(slot);
+                self.enabled_count -= 1;
+            }
+            _ => {}
+        }
+    }
+
+    fn entity_ids(&self) -> impl Iterator<Item = EntityId> + '_ {
+        self.tagged
+            .iter()
+            .filter(|index| !self.disabled.contains(*index))
+            .map(|index| EntityId::new(index, self.generations[index]))
+    }
+}
+
+/// The storage of one component type, laid out according to its `StorageKind`.
+#[derive(Clone)]
+pub(crate) enum Storage<T> {
+    Packed(PackedStorage<T>),
+    /// Only used when `T` is zero-sized.
+    Tags(TagStorage, PhantomData<T>),
+}
+
+impl<T> Storage<T> {
+    /// Create the storage for `kind`.
+    /// `kind` can only be `StorageKind::Tag` if `T` is zero-sized.
+    pub(crate) fn new(kind: StorageKind) -> Self {
+        match kind {
+            StorageKind::Table => Self::Packed(PackedStorage::new(SlotLookup::Map(HashMap::new()))),
+            StorageKind::SparseSet => {
+                Self::Packed(PackedStorage::new(SlotLookup::Sparse(Vec::new())))
+            }
+            StorageKind::Tag => {
+                assert_eq!(
+                    mem::size_of::<T>(),
+                    0,
+                    "Only zero-sized components can be tags."
+                );
+                Self::Tags(TagStorage::new(), PhantomData)
+            }
+        }
+    }
+
+    pub(crate) fn kind(&self) -> StorageKind {
+        match self {
+            Self::Packed(PackedStorage {
+                lookup: SlotLookup::Map(_),
+                ..
+            }) => StorageKind::Table,
+            Self::Packed(_) => StorageKind::SparseSet,
+            Self::Tags(..) => StorageKind::Tag,
+        }
+    }
+
+    /// Get the slot an entity's component and ticks are in.
+    pub(crate) fn slot(&self, entity_id: EntityId) -> Option<usize> {
+        match self {
+            Self::Packed(packed) => packed.slot(entity_id),
+            Self::Tags(tags, _) => tags.slot(entity_id),
+        }
+    }
+
+    /// Get the number of enabled entities with the component.
+    pub(crate) fn enabled_count(&self) -> usize {
+        match self {
+            Self::Packed(packed) => packed.enabled_count,
+            Self::Tags(tags, _) => tags.enabled_count,
+        }
+    }
+
+    /// Make room for at least `additional` more components.
+    pub(crate) fn reserve(&mut self, additional: usize) {
+        // Tags grow their bitsets as they're set, and there's nothing else to make room for.
+        if let Self::Packed(packed) = self {
+            packed.components.reserve(additional);
+            packed.ticks.reserve(additional);
+            packed.entity_ids.reserve(additional);
+            if let SlotLookup::Map(map) = &mut packed.lookup {
+                map.reserve(additional);
+            }
+        }
+    }
+
+    /// Add the component of an entity that doesn't have one yet, and get its slot.
+    pub(crate) fn push(
+        &mut self,
+        entity_id: EntityId,
+        component: T,
+        ticks: ComponentTicks,
+    ) -> usize {
+        match self {
+            Self::Packed(packed) => packed.push(entity_id, component, ticks),
+            Self::Tags(tags, _) => {
+                // The component has no data, so there's nothing to keep. `remove` hands it back.
+                mem::forget(component);
+                tags.push(entity_id, ticks)
+            }
+        }
+    }
+
+    /// Remove the component of an entity and hand it back.
+    pub(crate) fn remove(&mut self, entity_id: EntityId) -> Option<T> {
+        match self {
+            Self::Packed(packed) => packed.remove(entity_id),
+            Self::Tags(tags, _) => {
+                tags.remove(entity_id)?;
+                // SAFETY: `T` is zero-sized, so reading it from a dangling pointer is valid, and
+                // this gives back the component that `push` forgot.
+                Some(unsafe { ptr::read(NonNull::<T>::dangling().as_ptr()) })
+            }
+        }
+    }
+
+    pub(crate) fn set_enabled(&mut self, slot: usize, enabled: bool) {
+        match self {
+            Self::Packed(packed) => packed.set_enabled(slot, enabled),
+            Self::Tags(tags, _) => tags.set_enabled(slot, enabled),
+        }
+    }
+
+    pub(crate) fn get(&self, slot: usize) -> &T {
+        match self {
+            Self::Packed(packed) => &packed.components[slot],
+            // SAFETY: `T` is zero-sized, so any well-aligned pointer points at a valid `T`.
+            Self::Tags(..) => unsafe { NonNull::<T>::dangling().as_ref() },
+        }
+    }
+
+    pub(crate) fn get_mut(&mut self, slot: usize) -> &mut T {
+        match self {
+            Self::Packed(packed) => &mut packed.components[slot],
+            // SAFETY: See `get`.
+            Self::Tags(..) => unsafe { NonNull::<T>::dangling().as_mut() },
+        }
+    }
+
+    pub(crate) fn ticks_mut(&mut self, slot: usize) -> &mut ComponentTicks {
+        match self {
+            Self::Packed(packed) => &mut packed.ticks[slot],
+            Self::Tags(tags, _) => &mut tags.ticks[slot],
+        }
+    }
+
+    /// Get pointers to the start of the components and ticks, which slots index into.
+    pub(crate) fn as_mut_ptrs(&mut self) -> (*mut T, *mut ComponentTicks) {
+        match self {
+            Self::Packed(packed) => (packed.components.as_mut_ptr(), packed.ticks.as_mut_ptr()),
+            Self::Tags(tags, _) => (NonNull::dangling().as_ptr(), tags.ticks.as_mut_ptr()),
+        }
+    }
+
+    /// Get a pointer to the start of the ticks, which slots index into.
+    pub(crate) fn ticks_ptr(&self) -> *const ComponentTicks {
+        match self {
+            Self::Packed(packed) => packed.ticks.as_ptr(),
+            Self::Tags(tags, _) => tags.ticks.as_ptr(),
+        }
+    }
+
+    /// Get the enabled entities with the component.
+    pub(crate) fn entity_ids(&self) -> Box<dyn Iterator<Item = EntityId> + '_> {
+        match self {
+            Self::Packed(packed) => {
+                Box::new(packed.entity_ids[..packed.enabled_count].iter().copied())
+            }
+            Self::Tags(tags, _) => Box::new(tags.entity_ids()),
+        }
+    }
+
+    /// Get every entity with the component, including disabled ones.
+    pub(crate) fn all_entity_ids(&self) -> Vec<EntityId> {
+        match self {
+            Self::Packed(packed) => packed.entity_ids.clone(),
+            Self::Tags(tags, _) => tags
+                .tagged
+                .iter()
+                .map(|index| EntityId::new(index, tags.generations[index]))
+                .collect(),
+        }
+    }
+
+    /// Mark every component as changed in `tick`, including those of disabled entities.
+    pub(crate) fn set_all_changed(&mut self, tick: u64) {
+        match self {
+            Self::Packed(packed) => {
+                for ticks in packed.ticks.iter_mut() {
+                    ticks.set_changed(tick);
+                }
+            }
+            Self::Tags(tags, _) => {
+                for index in tags.tagged.iter() {
+                    tags.ticks[index].set_changed(tick);
+                }
+            }
+        }
+    }
+
+    /// Get the components of the enabled entities.
+    pub(crate) fn components(&self) -> &[T] {
+        match self {
+            Self::Packed(packed) => &packed.components[..packed.enabled_count],
+            // SAFETY: `T` is zero-sized, so a slice of any length starting at a well-aligned
+            // pointer is valid.
+            Self::Tags(tags, _) => unsafe {
+                slice::from_raw_parts(NonNull::dangling().as_ptr(), tags.enabled_count)
+            },
+        }
+    }
+
+    /// Mutably get the components of the enabled entities, marking them as changed in `tick`.
+    pub(crate) fn components_mut(&mut self, tick: u64) -> &mut [T] {
+        match self {
+            Self::Packed(packed) => {
+                for ticks in packed.ticks[..packed.enabled_count].iter_mut() {
+                    ticks.set_changed(tick);
+                }
+                &mut packed.components[..packed.enabled_count]
+            }
+            Self::Tags(tags, _) => {
+                for index in tags.tagged.iter() {
+                    if !tags.disabled.contains(index) {
+                        tags.ticks[index].set_changed(tick);
+                    }
+                }
+                // SAFETY: See `components`.
+                unsafe {
+                    slice::from_raw_parts_mut(NonNull::dangling().as_ptr(), tags.enabled_count)
+                }
+            }
+        }
+    }
+}
@@ -1,22 +1,242 @@
-use crate::InputHandler;
-
-use super::entity_manager::{EntityIdAccessor, EntityManager};
+use super::{
+    commands::Commands,
+    component::Component,
+    entity::EntityId,
+    entity_manager::{EntityIdAccessor, EntityManager},
+    query::{query_accessed_component_types, QueryData, QueryFilter, QueryIter},
+    resources::Resources,
+};
+use crate::errors::EcsError;
+use std::any::{type_name, TypeId};
 // use sdl2::{render::Canvas, video::Window};
 
-pub trait System {
+pub trait System: Send {
     fn update(
         &mut self,
         _manager: &mut EntityManager,
         _accessor: &mut EntityIdAccessor,
-        _input_handler: &InputHandler,
+        _commands: &mut Commands,
+        _resources: &mut Resources,
     ) -> Option<()>;
 }
 
-pub trait RenderSystem {
+/// A system that declares which components it reads and writes, so it can run at the same time
+/// as other parallel systems that don't write to the same components.
+/// Unlike `System` it can only reach components through queries, and resources are read-only.
+/// Resources are changed and events sent through `Commands::insert_resource` and
+/// `Commands::send_event` instead, once the system's batch is done.
+pub trait ParallelSystem: Send {
+    /// Declare the components the system reads and writes.
+    /// Filters like `Changed<T>` count as reading `T`.
+    fn access(&self, access: &mut SystemAccess);
+
+    fn update(
+        &mut self,
+        _view: &mut SystemView<'_>,
+        _commands: &mut Commands,
+        _resources: &Resources,
+    ) -> Option<()>;
+}
+
+pub trait RenderSystem: Send {
     fn update(
         &mut self,
         _manager: &mut EntityManager,
         _accessor: &mut EntityIdAccessor,
+        _resources: &Resources,
         // canvas: &mut Canvas<Window>,
     ) -> Option<()>;
 }
+
+/// Reacts to `E` events as soon as they're triggered on an entity, instead of polling every
+/// frame. Register one with `World::observe` to watch a single entity, or `World::add_observer`
+/// to watch every entity.
+///
+/// Events bubble up the `Parent` chain, so an observer on an entity also sees the events
+/// triggered on its descendants, unless one of their observers stops the propagation.
+pub trait Observer<E>: Send {
+    fn trigger(
+        &mut self,
+        trigger: &mut Trigger<'_, E>,
+        manager: &mut EntityManager,
+        commands: &mut Commands,
+        resources: &mut Resources,
+    );
+}
+
+impl<E, F> Observer<E> for F
+where
+    F: FnMut(&mut Trigger<'_, E>, &mut EntityManager, &mut Commands, &mut Resources) + Send,
+{
+    fn trigger(
+        &mut self,
+        trigger: &mut Trigger<'_, E>,
+        manager: &mut EntityManager,
+        commands: &mut Commands,
+        resources: &mut Resources,
+    ) {
+        self(trigger, manager, commands, resources);
+    }
+}
+
+/// An event triggered on an entity, as seen by an observer.
+pub struct Trigger<'a, E> {
+    event: &'a E,
+    target: EntityId,
+    entity: EntityId,
+    propagate: bool,
+}
+
+impl<'a, E> Trigger<'a, E> {
+    pub(crate) fn new(event: &'a E, target: EntityId) -> Self {
+        Self {
+            event,
+            target,
+            entity: target,
+            propagate: true,
+        }
+    }
+
+    pub fn event(&self) -> &E {
+        self.event
+    }
+
+    /// Get the entity the event was triggered on.
+    pub fn target(&self) -> EntityId {
+        self.target
+    }
+
+    /// Get the entity the observer is watching, which is an ancestor of the target while the
+    /// event bubbles up.
+    pub fn entity(&self) -> EntityId {
+        self.entity
+    }
+
+    /// Stop the event from bubbling up to the parent of the current entity.
+    pub fn stop_propagation(&mut self) {
+        self.propagate = false;
+    }
+
+    pub(crate) fn set_entity(&mut self, entity_id: EntityId) {
+        self.entity = entity_id;
+    }
+
+    pub(crate) fn propagates(&self) -> bool {
+        self.propagate
+    }
+}
+
+/// The component types a parallel system reads and writes.
+#[derive(Clone, Debug, Default)]
+pub struct SystemAccess {
+    reads: Vec<(TypeId, &'static str)>,
+    writes: Vec<(TypeId, &'static str)>,
+}
+
+impl SystemAccess {
+    pub fn read<T: 'static + Component>(&mut self) -> &mut Self {
+        self.reads.push((TypeId::of::<T>(), type_name::<T>()));
+        self
+    }
+
+    pub fn write<T: 'static + Component>(&mut self) -> &mut Self {
+        self.writes.push((TypeId::of::<T>(), type_name::<T>()));
+        self
+    }
+
+    /// Check if two systems can't run at the same time, as one writes to a component the other
+    /// reads or writes.
+    pub fn conflicts_with(&self, other: &SystemAccess) -> bool {
+        self.reads
+            .iter()
+            .chain(self.writes.iter())
+            .any(|(type_id, _)| contains(&other.writes, type_id))
+            || other
+                .reads
+                .iter()
+                .any(|(type_id, _)| contains(&self.writes, type_id))
+    }
+
+    /// Check that a query only reaches the components this access declared.
+    fn allows(
+        &self,
+        types: &[(TypeId, &'static str)],
+        mutable_types: &[(TypeId, &'static str)],
+    ) -> Result<(), EcsError> {
+        for (type_id, name) in mutable_types {
+            if !contains(&self.writes, type_id) {
+                return Err(EcsError::UndeclaredAccess(name));
+            }
+        }
+        for (type_id, name) in types {
+            if !contains(&self.reads, type_id) && !contains(&self.writes, type_id) {
+                return Err(EcsError::UndeclaredAccess(name));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn contains(types: &[(TypeId, &'static str)], type_id: &TypeId) -> bool {
+    types.iter().any(|(other, _)| other == type_id)
+}
+
+/// What a parallel system can see of the entities.
+/// Queries are checked against the access the system declared.
+pub struct SystemView<'a> {
+    manager: &'a EntityManager,
+    accessor: &'a mut EntityIdAccessor,
+    access: &'a SystemAccess,
+}
+
+impl<'a> SystemView<'a> {
+    pub(crate) fn new(
+        manager: &'a EntityManager,
+        accessor: &'a mut EntityIdAccessor,
+        access: &'a SystemAccess,
+    ) -> Self {
+        Self {
+            manager,
+            accessor,
+            access,
+        }
+    }
+
+    /// Query the entities that have every component in `Q`, like `EntityIdAccessor::query`.
+    pub fn query<Q: QueryData>(&mut self) -> Result<QueryIter<'_, Q>, EcsError> {
+        self.query_filtered::<Q, ()>()
+    }
+
+    /// Query the entities that have every component in `Q` and match the filter `F`, like
+    /// `EntityIdAccessor::query_filtered`.
+    pub fn query_filtered<Q: QueryData, F: QueryFilter>(
+        &mut self,
+    ) -> Result<QueryIter<'_, Q, F>, EcsError> {
+        let mut mutable_types = Vec::new();
+        Q::mutable_component_types(&mut mutable_types);
+        self.access
+            .allows(&query_accessed_component_types::<Q, F>(), &mutable_types)?;
+
+        self.accessor.query_shared::<Q, F>(self.manager)
+    }
+
+    /// Get the entities that had a `T` component removed since the system last ran.
+    /// The system has to declare that it reads or writes `T`.
+    pub fn removed<T: 'static + Component>(
+        &self,
+    ) -> Result<impl Iterator<Item = EntityId> + '_, EcsError> {
+        self.access
+            .allows(&[(TypeId::of::<T>(), type_name::<T>())], &[])?;
+        Ok(self.accessor.removed::<T>(self.manager))
+    }
+
+    pub fn entity_exists(&self, entity_id: EntityId) -> bool {
+        self.manager.entity_exists(entity_id)
+    }
+
+    /// Get the frame the system last ran in.
+    pub fn last_run(&self) -> Option<u64> {
+        self.accessor.last_run()
+    }
+}
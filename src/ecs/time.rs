@@ -0,0 +1,149 @@
+use std::time::Duration;
+use tracing::*;
+
+/// How often the fixed update stage runs.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FixedTimestep {
+    /// The time every fixed update steps the world forward by. This is never zero.
+    timestep: Duration,
+    /// The most fixed updates run in one update. Time beyond that is dropped, so a slow frame
+    /// doesn't make the next one even slower trying to catch up. This is never zero.
+    max_steps: u32,
+}
+
+impl Default for FixedTimestep {
+    /// Run the fixed update stage 60 times a second, catching up on at most 5 steps.
+    fn default() -> Self {
+        Self {
+            timestep: Duration::from_secs(1) / 60,
+            max_steps: 5,
+        }
+    }
+}
+
+impl FixedTimestep {
+    /// Run the fixed update stage `hz` times a second.
+    /// A rate that isn't positive and finite falls back to the default of 60.
+    pub fn from_hz(hz: f64) -> Self {
+        match Duration::try_from_secs_f64(1.0 / hz) {
+            Ok(timestep) if !timestep.is_zero() => Self::from_timestep(timestep),
+            _ => {
+                error!(
+                    "Failed to run the fixed update {hz} times a second. It runs 60 times instead."
+                );
+                Self::default()
+            }
+        }
+    }
+
+    /// Step the world forward by `timestep` every fixed update.
+    /// A zero timestep falls back to the default of a 60th of a second.
+    pub fn from_timestep(timestep: Duration) -> Self {
+        if timestep.is_zero() {
+            error!("Failed to use a zero fixed timestep. It steps by a 60th of a second instead.");
+            return Self::default();
+        }
+
+        Self {
+            timestep,
+            ..Self::default()
+        }
+    }
+
+    /// Set the most fixed updates run in one update. At least one has to run, so 0 keeps the
+    /// previous limit.
+    pub fn with_max_steps(mut self, max_steps: u32) -> Self {
+        match max_steps {
+            0 => error!(
+                "Failed to set the most fixed updates per update to 0. It stays at {}.",
+                self.max_steps
+            ),
+            _ => self.max_steps = max_steps,
+        }
+        self
+    }
+
+    /// Get the time every fixed update steps the world forward by.
+    pub fn timestep(&self) -> Duration {
+        self.timestep
+    }
+
+    /// Get the most fixed updates run in one update.
+    pub fn max_steps(&self) -> u32 {
+        self.max_steps
+    }
+}
+
+/// A resource with the time passed between updates, which the world keeps up to date.
+/// While the fixed update stage runs, `delta` is the fixed timestep.
+#[derive(Clone, Debug, Default)]
+pub struct Time {
+    delta: Duration,
+    elapsed: Duration,
+    fixed_timestep: FixedTimestep,
+    /// The time that has passed but hasn't been stepped through by a fixed update yet.
+    accumulator: Duration,
+}
+
+impl Time {
+    /// Get the time passed since the last update.
+    pub fn delta(&self) -> Duration {
+        self.delta
+    }
+
+    pub fn delta_seconds(&self) -> f32 {
+        self.delta.as_secs_f32()
+    }
+
+    /// Get the time passed since the first update.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    pub fn fixed_timestep(&self) -> FixedTimestep {
+        self.fixed_timestep
+    }
+
+    /// Get how far the world is between the last fixed update and the next one, from 0 to 1.
+    /// Render systems can use this to interpolate between the last two fixed updates.
+    pub fn alpha(&self) -> f32 {
+        (self.accumulator.as_secs_f64() / self.fixed_timestep.timestep.as_secs_f64()) as f32
+    }
+
+    pub(crate) fn set_fixed_timestep(&mut self, fixed_timestep: FixedTimestep) {
+        self.fixed_timestep = fixed_timestep;
+    }
+
+    /// Move time forward by `delta`, and get the number of fixed updates to run.
+    pub(crate) fn advance(&mut self, delta: Duration) -> u32 {
+        self.delta = delta;
+        self.elapsed += delta;
+        self.accumulator += delta;
+
+        let timestep = self.fixed_timestep.timestep;
+
+        let mut steps = 0;
+        while self.accumulator >= timestep {
+            if steps == self.fixed_timestep.max_steps {
+                // Drop whole steps that can't be caught up on, but keep the partial one for
+                // the interpolation.
+                self.accumulator = Duration::from_nanos(
+                    (self.accumulator.as_nanos() % timestep.as_nanos()) as u64,
+                );
+                break;
+            }
+            self.accumulator -= timestep;
+            steps += 1;
+        }
+        steps
+    }
+
+    /// Make `delta` the fixed timestep while the fixed update stage runs, or give back the time
+    /// passed since the last update afterwards.
+    pub(crate) fn set_in_fixed_update(&mut self, in_fixed_update: bool, delta: Duration) {
+        self.delta = match in_fixed_update {
+            true => self.fixed_timestep.timestep,
+            false => delta,
+        };
+    }
+}
@@ -1,48 +1,596 @@
 // use sdl2::{render::Canvas, video::Window};
 
-use crate::InputHandler;
-
 use super::{
+    bundle::Bundle,
+    commands::Commands,
     component::Component,
-    entity_manager::{EntityIdAccessor, EntityManager},
-    system::{RenderSystem, System},
+    entity::EntityId,
+    entity_manager::EntityManager,
+    events::{send_event, Events},
+    hooks::ComponentHooks,
+    name::{Name, Tags},
+    observer::{pending_trigger, run_pending_triggers, Observers},
+    prefab::{Prefab, Prefabs},
+    reflect::{Reflect, TypeRegistry},
+    relation::Relationship,
+    resources::Resources,
+    scene::{EntityMap, MapEntities, SceneData, SceneRegistry},
+    schedule::{ExecutionMode, ScheduledSystem, Stage, SystemConfig, SystemStage, UpdateSystem},
+    snapshot::Snapshot,
+    storage::StorageKind,
+    system::{Observer, ParallelSystem, RenderSystem, System, SystemAccess, SystemView},
+    time::{FixedTimestep, Time},
+};
+use crate::{
+    errors::{EcsError, SceneError},
+    thread_pool::build_thread_pool,
 };
+use rayon::ThreadPool;
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    any::type_name,
+    fs,
+    path::Path,
+    time::{Duration, Instant},
+};
+use tracing::*;
 
-#[derive(Default)]
 pub struct World {
     entity_manager: EntityManager,
-    entity_id_accessor: EntityIdAccessor,
-    systems: Vec<Box<dyn System>>,
-    render_systems: Vec<Box<dyn RenderSystem>>,
+    pre_update_systems: SystemStage<UpdateSystem>,
+    fixed_update_systems: SystemStage<UpdateSystem>,
+    update_systems: SystemStage<UpdateSystem>,
+    post_update_systems: SystemStage<UpdateSystem>,
+    render_systems: SystemStage<Box<dyn RenderSystem>>,
+    execution_mode: ExecutionMode,
+    /// The pool parallel systems run on, built the first time it's needed.
+    thread_pool: Option<ThreadPool>,
+    /// The frame the last update started in, so removed components are kept around until every
+    /// system had a chance to see them.
+    last_update_frame: u64,
+    /// When the last update started, to measure the time passed since.
+    last_update_instant: Option<Instant>,
+    commands: Commands,
+    resources: Resources,
+    /// Swaps the buffers of every `Events<T>` resource added through `add_event`.
+    event_updaters: Vec<fn(&mut Resources)>,
+    scene_registry: SceneRegistry,
+    type_registry: TypeRegistry,
+    prefabs: Prefabs,
+    observers: Observers,
+}
+
+impl Default for World {
+    fn default() -> Self {
+        let mut scene_registry = SceneRegistry::default();
+        // `Children` is filled in as the parents are set, so only `Parent` is saved.
+        scene_registry.register_parent();
+        scene_registry.register::<Name>();
+        scene_registry.register::<Tags>();
+
+        let mut resources = Resources::default();
+        resources.insert(Time::default());
+
+        let entity_manager = EntityManager::default();
+        let commands = entity_manager.commands();
+
+        Self {
+            entity_manager,
+            pre_update_systems: SystemStage::new(Stage::PreUpdate),
+            fixed_update_systems: SystemStage::new(Stage::FixedUpdate),
+            update_systems: SystemStage::new(Stage::Update),
+            post_update_systems: SystemStage::new(Stage::PostUpdate),
+            render_systems: SystemStage::new(Stage::Render),
+            execution_mode: ExecutionMode::default(),
+            thread_pool: None,
+            last_update_frame: 0,
+            last_update_instant: None,
+            commands,
+            resources,
+            event_updaters: Vec::new(),
+            scene_registry,
+            type_registry: TypeRegistry::default(),
+            prefabs: Prefabs::default(),
+            observers: Observers::default(),
+        }
+    }
 }
 
 impl World {
-    pub fn create_entity(&mut self) -> usize {
+    pub fn create_entity(&mut self) -> EntityId {
         self.entity_manager.create_entity()
     }
 
-    pub fn remove_entity(&mut self, entity_id: usize) {
+    /// Spawn an entity with every component in a bundle, like
+    /// `world.spawn((Transform::default(), Shape::default()))`.
+    pub fn spawn<B: Bundle>(&mut self, bundle: B) -> EntityId {
+        self.entity_manager.spawn(bundle)
+    }
+
+    /// Spawn an entity for every bundle, like particles or tiles, and get their IDs.
+    /// Room for all of them is made up front, so this is faster than spawning them one by one.
+    pub fn spawn_batch<B: Bundle>(
+        &mut self,
+        bundles: impl IntoIterator<Item = B>,
+    ) -> Vec<EntityId> {
+        self.entity_manager.spawn_batch(bundles)
+    }
+
+    /// Add a named prefab, which spawns entities with a copy of every component in `bundle`.
+    /// This replaces the previous prefab with the same name.
+    pub fn register_prefab<B: Bundle + Clone>(
+        &mut self,
+        name: impl Into<String>,
+        bundle: B,
+    ) -> &mut Self {
+        self.prefabs
+            .insert(name.into(), Prefab::from_bundle(bundle));
+        self
+    }
+
+    /// Spawn an entity from a prefab, with the components in `overrides` replacing the
+    /// prefab's. Pass `()` to spawn the prefab as it is.
+    pub fn spawn_prefab<B: Bundle>(&mut self, name: &str, overrides: B) -> Option<EntityId> {
+        self.prefabs
+            .spawn(&mut self.entity_manager, name, overrides)
+    }
+
+    /// Remove an entity, along with its children and their children.
+    pub fn remove_entity(&mut self, entity_id: EntityId) {
         self.entity_manager.remove_entity(entity_id);
     }
 
+    /// Remove every entity in `entity_ids`, along with their children.
+    /// Entities that are already gone are skipped.
+    pub fn despawn_batch(&mut self, entity_ids: impl IntoIterator<Item = EntityId>) -> &mut Self {
+        self.entity_manager.despawn_batch(entity_ids);
+        self
+    }
+
+    /// Move an entity to another world, along with all of its components and children, and
+    /// get its ID there.
+    /// Its observers, relationships and parent stay behind, and resources and systems aren't
+    /// shared between worlds.
+    pub fn move_entity(&mut self, entity_id: EntityId, target: &mut World) -> Option<EntityId> {
+        self.entity_manager
+            .move_entity(entity_id, &mut target.entity_manager)
+    }
+
+    /// Enable or disable an entity.
+    /// Disabled entities keep their components, but queries skip them until they're enabled
+    /// again.
+    pub fn set_entity_enabled(&mut self, entity_id: EntityId, enabled: bool) -> &mut Self {
+        self.entity_manager.set_entity_enabled(entity_id, enabled);
+        self
+    }
+
+    pub fn is_entity_enabled(&self, entity_id: EntityId) -> bool {
+        self.entity_manager.is_entity_enabled(entity_id)
+    }
+
+    /// Find an entity by its `Name`, like `world.find_by_name("player")`.
+    /// If several entities have the name, the one that got it first is returned.
+    pub fn find_by_name(&self, name: &str) -> Option<EntityId> {
+        self.entity_manager.find_by_name(name)
+    }
+
+    /// Get the entities tagged with `tag`, ordered by ID.
+    pub fn entities_with_tag<'a>(&'a self, tag: &'a str) -> impl Iterator<Item = EntityId> + 'a {
+        self.entity_manager.entities_with_tag(tag)
+    }
+
+    /// Tag an entity, giving it a `Tags` component if it doesn't have one.
+    pub fn add_tag(&mut self, entity_id: EntityId, tag: impl Into<String>) -> &mut Self {
+        self.entity_manager.add_tag(entity_id, tag);
+        self
+    }
+
+    pub fn remove_tag(&mut self, entity_id: EntityId, tag: &str) -> &mut Self {
+        self.entity_manager.remove_tag(entity_id, tag);
+        self
+    }
+
+    pub fn has_tag(&self, entity_id: EntityId, tag: &str) -> bool {
+        self.entity_manager.has_tag(entity_id, tag)
+    }
+
+    /// Make `parent` the parent of `child`, replacing its previous parent.
+    pub fn set_parent(&mut self, child: EntityId, parent: EntityId) -> &mut Self {
+        self.entity_manager.set_parent(child, parent);
+        self
+    }
+
+    /// Detach an entity from its parent, if it has one.
+    pub fn remove_parent(&mut self, child: EntityId) -> &mut Self {
+        self.entity_manager.remove_parent(child);
+        self
+    }
+
+    /// Relate `source` to `target` by `R`, e.g. `world.relate::<DockedAt>(ship, station)`.
+    /// The relationship is dropped when either entity is removed.
+    pub fn relate<R: Relationship>(&mut self, source: EntityId, target: EntityId) -> &mut Self {
+        self.entity_manager.relate::<R>(source, target);
+        self
+    }
+
+    pub fn unrelate<R: Relationship>(&mut self, source: EntityId, target: EntityId) -> &mut Self {
+        self.entity_manager.unrelate::<R>(source, target);
+        self
+    }
+
+    pub fn is_related<R: Relationship>(&self, source: EntityId, target: EntityId) -> bool {
+        self.entity_manager.is_related::<R>(source, target)
+    }
+
+    /// Get the entities `source` is related to by `R`.
+    pub fn related<R: Relationship>(
+        &self,
+        source: EntityId,
+    ) -> impl Iterator<Item = EntityId> + '_ {
+        self.entity_manager.related::<R>(source)
+    }
+
+    /// Get the entities related to `target` by `R`, like every ship docked at a station.
+    pub fn related_to<R: Relationship>(
+        &self,
+        target: EntityId,
+    ) -> impl Iterator<Item = EntityId> + '_ {
+        self.entity_manager.related_to::<R>(target)
+    }
+
     pub fn register_component<T: 'static + Component>(&mut self) -> &mut Self {
         self.entity_manager.register::<T>();
         self
     }
 
+    /// Register a component that is included in snapshots.
+    /// Components registered any other way are left out of them, so components that aren't
+    /// `Clone` can still be used alongside snapshots.
+    pub fn register_cloneable_component<T: 'static + Component + Clone>(&mut self) -> &mut Self {
+        self.entity_manager.register_cloneable::<T>();
+        self
+    }
+
+    /// Register a component with a certain storage kind instead of the one it asks for.
+    pub fn register_component_with_storage<T: 'static + Component>(
+        &mut self,
+        kind: StorageKind,
+    ) -> &mut Self {
+        self.entity_manager.register_with_storage::<T>(kind);
+        self
+    }
+
+    /// Register a component along with the hooks that run when it's added, replaced or removed.
+    pub fn register_component_with_hooks<T: 'static + Component>(
+        &mut self,
+        hooks: ComponentHooks<T>,
+    ) -> &mut Self {
+        self.entity_manager
+            .register::<T>()
+            .set_component_hooks(hooks);
+        self
+    }
+
+    /// Set the hooks of a registered component, replacing its previous ones.
+    pub fn set_component_hooks<T: 'static + Component>(
+        &mut self,
+        hooks: ComponentHooks<T>,
+    ) -> &mut Self {
+        self.entity_manager.set_component_hooks(hooks);
+        self
+    }
+
+    /// Register a component and let it be saved in scenes, under its type name without the
+    /// module path.
+    pub fn register_serializable_component<T>(&mut self) -> &mut Self
+    where
+        T: 'static + Component + Serialize + DeserializeOwned,
+    {
+        self.entity_manager.register::<T>();
+        self.scene_registry.register::<T>();
+        self
+    }
+
+    /// Like `register_serializable_component`, for components that refer to other entities.
+    /// Their references are remapped to the loaded entities when a scene is loaded.
+    pub fn register_serializable_component_with_entities<T>(&mut self) -> &mut Self
+    where
+        T: 'static + Component + Serialize + DeserializeOwned + MapEntities,
+    {
+        self.entity_manager.register::<T>();
+        self.scene_registry.register_with_entities::<T>();
+        self
+    }
+
+    /// Register a component and let it be reflected, so it can be listed, printed and edited
+    /// on entities without knowing its type.
+    pub fn register_reflect_component<T: 'static + Component + Reflect>(&mut self) -> &mut Self {
+        self.entity_manager.register::<T>();
+        self.type_registry.register_component::<T>();
+        self
+    }
+
+    /// Get the reflected types, to look up their fields.
+    pub fn type_registry(&self) -> &TypeRegistry {
+        &self.type_registry
+    }
+
+    /// Get the reflected types, to register types that aren't components.
+    pub fn type_registry_mut(&mut self) -> &mut TypeRegistry {
+        &mut self.type_registry
+    }
+
+    /// Borrow every reflected component of an entity, ordered by type name.
+    pub fn reflect_components(&self, entity_id: EntityId) -> Vec<&dyn Reflect> {
+        self.type_registry
+            .reflect_components(&self.entity_manager, entity_id)
+    }
+
+    /// Borrow a reflected component of an entity by its type name, like `"Transform"`.
+    pub fn reflect_component(&self, entity_id: EntityId, name: &str) -> Option<&dyn Reflect> {
+        self.type_registry
+            .reflect_component(&self.entity_manager, entity_id, name)
+    }
+
+    /// Mutably borrow a reflected component of an entity by its type name, marking it as
+    /// changed.
+    pub fn reflect_component_mut(
+        &mut self,
+        entity_id: EntityId,
+        name: &str,
+    ) -> Option<&mut dyn Reflect> {
+        self.type_registry
+            .reflect_component_mut(&mut self.entity_manager, entity_id, name)
+    }
+
+    /// Print an entity with its reflected components, like
+    /// `3v0 "player" [Transform { position: Vector3 { .. }, .. }]`.
+    pub fn debug_entity(&self, entity_id: EntityId) -> Option<String> {
+        if !self.entity_manager.entity_exists(entity_id) {
+            error!("Failed to print entity {entity_id}. It does not exist.");
+            return None;
+        }
+
+        Some(format!(
+            "{} {:?}",
+            self.entity_manager.entity_label(entity_id),
+            self.reflect_components(entity_id)
+        ))
+    }
+
+    /// Copy every entity along with its cloneable components, for rollback or undo.
+    /// Resources, events and components that weren't registered as cloneable aren't copied.
+    pub fn snapshot(&self) -> Snapshot {
+        self.entity_manager.snapshot()
+    }
+
+    /// Bring the entities and cloneable components back to how they were in a snapshot.
+    /// Entities created since are removed, while the ones that are restored keep their
+    /// components that aren't in snapshots, and relationships made since are dropped.
+    /// IDs handed out since the snapshot stay stale, and aren't reused for new entities.
+    ///
+    /// Component hooks run during the restore for every component that's dropped or brought
+    /// back. Their commands are applied before this returns, so entities spawned by a hook, like
+    /// an `on_remove` hook that leaves something behind, exist afterwards even though they
+    /// weren't in the snapshot.
+    pub fn restore(&mut self, snapshot: &Snapshot) -> &mut Self {
+        self.entity_manager.restore(snapshot);
+        self
+    }
+
+    /// Save every entity with a serializable component as JSON.
+    /// Components that weren't registered as serializable are left out.
+    pub fn save_scene(&self) -> Result<String, SceneError> {
+        let scene = self.scene_registry.save(&self.entity_manager)?;
+        serde_json::to_string_pretty(&scene).map_err(SceneError::Malformed)
+    }
+
+    pub fn save_scene_to_file(&self, path: impl AsRef<Path>) -> Result<(), SceneError> {
+        fs::write(path, self.save_scene()?)?;
+        Ok(())
+    }
+
+    /// Load the entities of a JSON scene as new entities, and add the prefabs it defines.
+    /// The returned map gives the entity each saved entity ID was loaded as. Nothing is added if
+    /// the scene can't be loaded.
+    pub fn load_scene(&mut self, scene: &str) -> Result<EntityMap, SceneError> {
+        let scene = serde_json::from_str::<SceneData>(scene).map_err(SceneError::Malformed)?;
+        self.scene_registry
+            .load(&mut self.entity_manager, &mut self.prefabs, scene)
+    }
+
+    pub fn load_scene_from_file(
+        &mut self,
+        path: impl AsRef<Path>,
+    ) -> Result<EntityMap, SceneError> {
+        let scene = fs::read_to_string(path)?;
+        self.load_scene(&scene)
+    }
+
+    /// Add an `Events<T>` resource, which is updated at the start of every update.
+    pub fn add_event<T: 'static + Send + Sync>(&mut self) -> &mut Self {
+        if self.resources.contains::<Events<T>>() {
+            return self;
+        }
+
+        self.resources.insert(Events::<T>::default());
+        self.event_updaters.push(|resources| {
+            if let Some(events) = resources.get_mut::<Events<T>>() {
+                events.update();
+            }
+        });
+        self
+    }
+
+    /// Send an event, which systems can read with an `EventReader<T>`.
+    pub fn send_event<T: 'static + Send + Sync>(&mut self, event: T) -> &mut Self {
+        send_event(&mut self.resources, event);
+        self
+    }
+
+    /// Add an observer that runs as soon as an `E` event is triggered on any entity.
+    pub fn add_observer<E: 'static, O: 'static + Observer<E>>(&mut self, observer: O) -> &mut Self {
+        self.observers.add(Box::new(observer));
+        self
+    }
+
+    /// Add an observer that runs as soon as an `E` event is triggered on an entity or one of its
+    /// descendants. It's dropped once the entity is removed.
+    pub fn observe<E: 'static, O: 'static + Observer<E>>(
+        &mut self,
+        entity_id: EntityId,
+        observer: O,
+    ) -> &mut Self {
+        if !self.entity_manager.entity_exists(entity_id) {
+            error!(
+                "Failed to observe entity {}. It does not exist.",
+                self.entity_manager.entity_label(entity_id)
+            );
+            return self;
+        }
+
+        self.observers.add_to_entity(entity_id, Box::new(observer));
+        self
+    }
+
+    /// Trigger an event on an entity, running its observers right away. The event then bubbles
+    /// up to the observers of the entity's ancestors, and finally reaches the observers added
+    /// with `add_observer`.
+    pub fn trigger<E: 'static + Send + Sync>(&mut self, event: E, target: EntityId) -> &mut Self {
+        if !self.entity_manager.entity_exists(target) {
+            error!(
+                "Failed to trigger {} on entity {target}. It does not exist.",
+                type_name::<E>()
+            );
+            return self;
+        }
+
+        self.entity_manager
+            .queue_trigger(pending_trigger(event, target));
+        run_pending_triggers(
+            &mut self.observers,
+            &mut self.entity_manager,
+            &mut self.resources,
+        );
+        self
+    }
+
+    /// Add a system to the update stage.
     pub fn add_system<T: 'static + System>(&mut self, system: T) -> &mut Self {
-        self.systems.push(Box::new(system));
+        self.add_system_to_stage(Stage::Update, system, SystemConfig::default())
+    }
+
+    /// Add a system to a stage, ordered within the stage by `config`.
+    /// The render stage only holds render systems, see `add_render_system_with_config`.
+    pub fn add_system_to_stage<T: 'static + System>(
+        &mut self,
+        stage: Stage,
+        system: T,
+        config: SystemConfig,
+    ) -> &mut Self {
+        if let Some(systems) = self.update_stage_mut::<T>(stage) {
+            systems.add(
+                UpdateSystem::Exclusive(Box::new(system)),
+                type_name::<T>(),
+                config,
+            );
+        }
         self
     }
 
+    /// Add a parallel system to the update stage.
+    pub fn add_parallel_system<T: 'static + ParallelSystem>(&mut self, system: T) -> &mut Self {
+        self.add_parallel_system_to_stage(Stage::Update, system, SystemConfig::default())
+    }
+
+    /// Add a parallel system to a stage, ordered within the stage by `config`.
+    /// It runs at the same time as its neighbours in the stage, as long as their access
+    /// doesn't conflict and they aren't ordered against each other.
+    pub fn add_parallel_system_to_stage<T: 'static + ParallelSystem>(
+        &mut self,
+        stage: Stage,
+        system: T,
+        config: SystemConfig,
+    ) -> &mut Self {
+        let mut access = SystemAccess::default();
+        system.access(&mut access);
+
+        if let Some(systems) = self.update_stage_mut::<T>(stage) {
+            systems.add(
+                UpdateSystem::Parallel(Box::new(system), access),
+                type_name::<T>(),
+                config,
+            );
+        }
+        self
+    }
+
+    fn update_stage_mut<T>(&mut self, stage: Stage) -> Option<&mut SystemStage<UpdateSystem>> {
+        match stage {
+            Stage::PreUpdate => Some(&mut self.pre_update_systems),
+            Stage::FixedUpdate => Some(&mut self.fixed_update_systems),
+            Stage::Update => Some(&mut self.update_systems),
+            Stage::PostUpdate => Some(&mut self.post_update_systems),
+            Stage::Render => {
+                error!(
+                    "Failed to add {}. Only render systems can go in the render stage.",
+                    type_name::<T>()
+                );
+                None
+            }
+        }
+    }
+
+    /// Add a render system to the render stage.
     pub fn add_render_system<T: 'static + RenderSystem>(&mut self, system: T) -> &mut Self {
-        self.render_systems.push(Box::new(system));
+        self.add_render_system_with_config(system, SystemConfig::default())
+    }
+
+    /// Add a render system to the render stage, ordered within the stage by `config`.
+    pub fn add_render_system_with_config<T: 'static + RenderSystem>(
+        &mut self,
+        system: T,
+        config: SystemConfig,
+    ) -> &mut Self {
+        self.render_systems
+            .add(Box::new(system), type_name::<T>(), config);
+        self
+    }
+
+    /// Set how often the fixed update stage runs, which is 60 times a second by default.
+    pub fn set_fixed_timestep(&mut self, fixed_timestep: FixedTimestep) -> &mut Self {
+        match self.resources.get_mut::<Time>() {
+            Some(time) => time.set_fixed_timestep(fixed_timestep),
+            None => {
+                let mut time = Time::default();
+                time.set_fixed_timestep(fixed_timestep);
+                self.resources.insert(time);
+            }
+        }
+        self
+    }
+
+    /// Choose whether parallel systems run at the same time, or one after another.
+    pub fn set_execution_mode(&mut self, execution_mode: ExecutionMode) -> &mut Self {
+        self.execution_mode = execution_mode;
         self
     }
 
+    /// Sort the systems in every stage by their `before` and `after` constraints.
+    /// This happens before the first update anyway, but calling it at startup reports cycles
+    /// before anything runs.
+    pub fn build_schedule(&mut self) -> Result<(), EcsError> {
+        self.pre_update_systems.sort()?;
+        self.fixed_update_systems.sort()?;
+        self.update_systems.sort()?;
+        self.post_update_systems.sort()?;
+        self.render_systems.sort()?;
+        Ok(())
+    }
+
     pub fn add_component_to_entity<T: 'static + Component>(
         &mut self,
-        entity_id: usize,
+        entity_id: EntityId,
         component: T,
     ) -> &mut Self {
         self.entity_manager
@@ -50,26 +598,255 @@ impl World {
         self
     }
 
-    pub fn update(&mut self, input_handler: &InputHandler) {
-        for system in self.systems.iter_mut() {
-            system.update(
+    /// Insert a resource, replacing the previous resource of the same type.
+    pub fn insert_resource<T: 'static + Send + Sync>(&mut self, resource: T) -> &mut Self {
+        self.resources.set_tick(self.entity_manager.get_frame());
+        self.resources.insert(resource);
+        self
+    }
+
+    /// Remove a resource and return it, if it exists.
+    pub fn remove_resource<T: 'static + Send + Sync>(&mut self) -> Option<T> {
+        self.resources.remove::<T>()
+    }
+
+    pub fn get_resource<T: 'static + Send + Sync>(&self) -> Option<&T> {
+        self.resources.get::<T>()
+    }
+
+    /// Mutably borrow a resource, marking it as changed.
+    pub fn get_resource_mut<T: 'static + Send + Sync>(&mut self) -> Option<&mut T> {
+        self.resources.set_tick(self.entity_manager.get_frame());
+        self.resources.get_mut::<T>()
+    }
+
+    /// Run the pre-update, fixed update, update and post-update stages, with the time passed
+    /// since the last update.
+    pub fn update(&mut self) {
+        let now = Instant::now();
+        let delta = self
+            .last_update_instant
+            .map(|last_update| now - last_update)
+            .unwrap_or_default();
+        self.last_update_instant = Some(now);
+
+        self.update_with_delta(delta);
+    }
+
+    /// Run the update stages as if `delta` passed since the last update, for games that keep
+    /// their own time.
+    pub fn update_with_delta(&mut self, delta: Duration) {
+        if let Err(e) = self.build_schedule() {
+            error!("Failed to update the world: {e}");
+            return;
+        }
+
+        self.entity_manager
+            .clear_removed_components(self.last_update_frame);
+        self.observers.retain_existing(&self.entity_manager);
+        run_pending_triggers(
+            &mut self.observers,
+            &mut self.entity_manager,
+            &mut self.resources,
+        );
+        self.last_update_frame = self.entity_manager.get_frame();
+
+        for update_events in self.event_updaters.iter() {
+            update_events(&mut self.resources);
+        }
+
+        if self.execution_mode == ExecutionMode::Parallel && self.thread_pool.is_none() {
+            match build_thread_pool() {
+                Ok(thread_pool) => self.thread_pool = Some(thread_pool),
+                Err(e) => {
+                    error!("Failed to build the thread pool, running systems serially: {e}");
+                    self.execution_mode = ExecutionMode::Serial;
+                }
+            }
+        }
+        let thread_pool = match self.execution_mode {
+            ExecutionMode::Parallel => self.thread_pool.as_ref(),
+            ExecutionMode::Serial => None,
+        };
+
+        self.resources.set_tick(self.entity_manager.get_frame());
+        let fixed_steps = self
+            .resources
+            .get_mut::<Time>()
+            .map(|time| time.advance(delta))
+            .unwrap_or_default();
+
+        run_stage(
+            &mut self.pre_update_systems,
+            &mut self.entity_manager,
+            &mut self.commands,
+            &mut self.resources,
+            &mut self.observers,
+            thread_pool,
+        );
+        for _ in 0..fixed_steps {
+            if let Some(time) = self.resources.get_mut::<Time>() {
+                time.set_in_fixed_update(true, delta);
+            }
+            run_stage(
+                &mut self.fixed_update_systems,
                 &mut self.entity_manager,
-                &mut self.entity_id_accessor,
-                input_handler,
+                &mut self.commands,
+                &mut self.resources,
+                &mut self.observers,
+                thread_pool,
+            );
+        }
+        if let Some(time) = self.resources.get_mut::<Time>() {
+            time.set_in_fixed_update(false, delta);
+        }
+        for systems in [&mut self.update_systems, &mut self.post_update_systems] {
+            run_stage(
+                systems,
+                &mut self.entity_manager,
+                &mut self.commands,
+                &mut self.resources,
+                &mut self.observers,
+                thread_pool,
             );
-            self.entity_manager.increment_frame();
         }
     }
 
+    /// Run the render stage.
     pub fn update_render(&mut self) {
-        for render_system in self.render_systems.iter_mut() {
-            render_system.update(
+        if let Err(e) = self.build_schedule() {
+            error!("Failed to render the world: {e}");
+            return;
+        }
+
+        for scheduled in self.render_systems.systems_mut().iter_mut() {
+            if !scheduled.should_run(&self.resources) {
+                continue;
+            }
+
+            scheduled.system.update(
                 &mut self.entity_manager,
-                &mut self.entity_id_accessor,
+                &mut scheduled.accessor,
+                &self.resources,
                 // canvas,
             );
 
+            scheduled
+                .accessor
+                .set_last_run(self.entity_manager.get_frame());
             self.entity_manager.increment_frame();
         }
     }
 }
+
+/// Run every system in an update stage whose run conditions hold, batch by batch.
+fn run_stage(
+    systems: &mut SystemStage<UpdateSystem>,
+    manager: &mut EntityManager,
+    commands: &mut Commands,
+    resources: &mut Resources,
+    observers: &mut Observers,
+    thread_pool: Option<&ThreadPool>,
+) {
+    for batch in systems.batches() {
+        match &mut systems.systems_mut()[batch] {
+            [scheduled] => {
+                if scheduled.should_run(resources) {
+                    run_system(scheduled, manager, commands, resources, observers);
+                }
+            }
+            batch => run_parallel_batch(batch, manager, resources, observers, thread_pool),
+        }
+    }
+}
+
+fn run_system(
+    scheduled: &mut ScheduledSystem<UpdateSystem>,
+    manager: &mut EntityManager,
+    commands: &mut Commands,
+    resources: &mut Resources,
+    observers: &mut Observers,
+) {
+    resources.set_tick(manager.get_frame());
+    match &mut scheduled.system {
+        UpdateSystem::Exclusive(system) => {
+            system.update(manager, &mut scheduled.accessor, commands, resources);
+        }
+        UpdateSystem::Parallel(system, access) => {
+            let mut view = SystemView::new(manager, &mut scheduled.accessor, access);
+            system.update(&mut view, commands, resources);
+        }
+    }
+    scheduled.accessor.set_last_run(manager.get_frame());
+    manager.increment_frame();
+
+    // Apply the commands after moving on to the next frame, so the system sees the entities it
+    // spawned as added the next time it runs.
+    commands.apply(manager);
+    run_pending_triggers(observers, manager, resources);
+}
+
+/// Run a batch of parallel systems, at the same time on the thread pool or one after another
+/// without one. Either way they all run in the same frame, and each system records into its own
+/// commands, which are applied in order once they all finish, so a serial run matches a
+/// parallel one.
+fn run_parallel_batch(
+    batch: &mut [ScheduledSystem<UpdateSystem>],
+    manager: &mut EntityManager,
+    resources: &mut Resources,
+    observers: &mut Observers,
+    thread_pool: Option<&ThreadPool>,
+) {
+    resources.set_tick(manager.get_frame());
+    let runs = batch
+        .iter()
+        .map(|scheduled| scheduled.should_run(resources))
+        .collect::<Vec<_>>();
+    let mut batch_commands = batch.iter().map(|_| manager.commands()).collect::<Vec<_>>();
+
+    let shared_manager = &*manager;
+    let shared_resources = &*resources;
+    let systems = batch
+        .iter_mut()
+        .zip(batch_commands.iter_mut())
+        .zip(runs.iter())
+        .filter(|(_, runs)| **runs)
+        .map(|(system, _)| system);
+    match thread_pool {
+        Some(thread_pool) => thread_pool.scope(|scope| {
+            for (scheduled, commands) in systems {
+                scope.spawn(move |_| {
+                    run_parallel_system(scheduled, shared_manager, commands, shared_resources)
+                });
+            }
+        }),
+        None => {
+            for (scheduled, commands) in systems {
+                run_parallel_system(scheduled, shared_manager, commands, shared_resources);
+            }
+        }
+    }
+
+    let frame = manager.get_frame();
+    for (scheduled, _) in batch.iter_mut().zip(runs).filter(|(_, runs)| *runs) {
+        scheduled.accessor.set_last_run(frame);
+    }
+    manager.increment_frame();
+
+    for commands in batch_commands.iter_mut() {
+        commands.apply(manager);
+    }
+    run_pending_triggers(observers, manager, resources);
+}
+
+fn run_parallel_system(
+    scheduled: &mut ScheduledSystem<UpdateSystem>,
+    manager: &EntityManager,
+    commands: &mut Commands,
+    resources: &Resources,
+) {
+    if let UpdateSystem::Parallel(system, access) = &mut scheduled.system {
+        let mut view = SystemView::new(manager, &mut scheduled.accessor, access);
+        system.update(&mut view, commands, resources);
+    }
+}
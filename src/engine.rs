@@ -1,89 +1,468 @@
-use std::sync::{Arc, Mutex, MutexGuard};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    path::Path,
+    sync::{Arc, Mutex, MutexGuard},
+};
 use tracing::{error, info};
 
-use crate::input_handler::Key;
+use crate::input_handler::{InputHandler, Key};
 use crate::window_starter::*;
-use crate::{ecs::*, WindowConfig};
-use crate::{Shape, Sprite, Transform};
+use crate::{ecs::*, EventHandler, SceneError, WindowConfig, Worlds};
+use crate::{
+    GlobalTransform, Shape, Shapes, Sprite, Transform, TransformPropagationSystem, Vector2, Vector3,
+};
 
 /// The main struct that holds the engine.
 pub struct Taconite {
     world: Arc<Mutex<World>>,
+    worlds: Worlds,
     window_starter: WindowStarter,
 }
 
 impl Default for Taconite {
     fn default() -> Self {
-        let mut taconite = Self {
-            world: Arc::new(Mutex::new(World::default())),
-            window_starter: WindowStarter::default(),
-        };
-
-        taconite.register_component::<Transform>();
-        taconite.register_component::<Sprite>();
-        taconite.register_component::<Shape>();
+        let world = Arc::new(Mutex::new(engine_world()));
+        let worlds = Worlds::new(world.clone());
 
         info!("Created a Taconite instance.");
 
-        taconite
+        Self {
+            world,
+            worlds,
+            window_starter: WindowStarter::default(),
+        }
     }
 }
 
+/// Create a world with the components, events, resources and systems the engine needs.
+fn engine_world() -> World {
+    let mut world = World::default();
+    world
+        .register_serializable_component::<Transform>()
+        .register_serializable_component::<GlobalTransform>()
+        .register_cloneable_component::<Transform>()
+        .register_cloneable_component::<GlobalTransform>()
+        .register_reflect_component::<Transform>()
+        .register_reflect_component::<GlobalTransform>()
+        .register_reflect_component::<Shape>()
+        .register_component::<Sprite>()
+        .insert_resource(InputHandler::default())
+        .add_system_to_stage(
+            Stage::PostUpdate,
+            TransformPropagationSystem {},
+            SystemConfig {
+                label: Some("transform_propagation"),
+                ..Default::default()
+            },
+        );
+    world
+        .type_registry_mut()
+        .register::<Vector2>()
+        .register::<Vector3>()
+        .register::<Shapes>();
+    EventHandler::add_events(&mut world);
+
+    world
+}
+
 impl Taconite {
     /// Create a new entity.
-    /// This will create a new entity with the ECS system and return it's ID (a usize).
-    pub fn create_entity(&mut self) -> usize {
+    /// This will create a new entity with the ECS system and return it's ID (an `EntityId`).
+    pub fn create_entity(&mut self) -> EntityId {
         Mutex::lock(&self.world).unwrap().create_entity()
     }
 
-    /// Remove an entity.
-    /// This takes in the ID of the entity (a usize)
-    pub fn remove_entity(&mut self, entity_id: usize) {
+    /// Create a new entity with every component in a bundle.
+    /// This takes in a tuple of components, like `(Transform::default(), Shape::default())`, and
+    /// returns the ID of the entity (an `EntityId`).
+    pub fn spawn<B: Bundle>(&mut self, bundle: B) -> EntityId {
+        self.get_world_guard().spawn(bundle)
+    }
+
+    /// Create a new entity for every bundle, locking the world only once.
+    /// This takes in anything that iterates over bundles, like
+    /// `(0..1000).map(|_| (Transform::default(), Shape::default()))`, and returns the IDs of the
+    /// new entities in the same order.
+    pub fn spawn_batch<B: Bundle>(
+        &mut self,
+        bundles: impl IntoIterator<Item = B>,
+    ) -> Vec<EntityId> {
+        self.get_world_guard().spawn_batch(bundles)
+    }
+
+    /// Register a named prefab that entities can be spawned from.
+    /// This takes in the name and a tuple of components, which every spawned entity gets a copy
+    /// of.
+    pub fn register_prefab<B: Bundle + Clone>(&mut self, name: impl Into<String>, bundle: B) {
+        self.get_world_guard().register_prefab(name, bundle);
+    }
+
+    /// Create a new entity from a prefab.
+    /// This takes in the name of the prefab and a tuple of components that replace the prefab's,
+    /// or `()` to keep them all. Prefabs can also be loaded from scene files.
+    pub fn spawn_prefab<B: Bundle>(&mut self, name: &str, overrides: B) -> Option<EntityId> {
+        self.get_world_guard().spawn_prefab(name, overrides)
+    }
+
+    /// Remove an entity, along with its children.
+    /// This takes in the ID of the entity (an `EntityId`). Stale IDs are ignored.
+    pub fn remove_entity(&mut self, entity_id: EntityId) {
         Mutex::lock(&self.world).unwrap().remove_entity(entity_id);
     }
 
+    /// Remove many entities at once, along with their children, locking the world only once.
+    /// This takes in anything that iterates over `EntityId`s. Stale IDs are skipped.
+    pub fn despawn_batch(&mut self, entity_ids: impl IntoIterator<Item = EntityId>) {
+        self.get_world_guard().despawn_batch(entity_ids);
+    }
+
+    /// Enable or disable an entity.
+    /// This takes in the ID of the entity (an `EntityId`). Disabled entities keep their
+    /// components, but systems don't see them until they're enabled again.
+    pub fn set_entity_enabled(&mut self, entity_id: EntityId, enabled: bool) {
+        self.get_world_guard()
+            .set_entity_enabled(entity_id, enabled);
+    }
+
+    /// Find an entity by its `Name`.
+    /// This takes in the name, e.g. `"player"`, and returns the ID of the entity (an `EntityId`)
+    /// if there is one.
+    pub fn find_by_name(&self, name: &str) -> Option<EntityId> {
+        self.get_world_guard().find_by_name(name)
+    }
+
+    /// Get the IDs of the entities tagged with `tag`, ordered by ID.
+    pub fn entities_with_tag(&self, tag: &str) -> Vec<EntityId> {
+        self.get_world_guard().entities_with_tag(tag).collect()
+    }
+
+    /// Tag an entity.
+    /// This takes in the ID of the entity (an `EntityId`) and the tag, e.g. `"enemy"`.
+    pub fn add_tag(&mut self, entity_id: EntityId, tag: impl Into<String>) {
+        self.get_world_guard().add_tag(entity_id, tag);
+    }
+
+    /// Remove a tag from an entity.
+    pub fn remove_tag(&mut self, entity_id: EntityId, tag: &str) {
+        self.get_world_guard().remove_tag(entity_id, tag);
+    }
+
+    /// Make `parent` the parent of `child`, so `child` moves along with it.
+    /// This takes in the IDs of both entities (`EntityId`s).
+    pub fn set_parent(&mut self, child: EntityId, parent: EntityId) {
+        self.get_world_guard().set_parent(child, parent);
+    }
+
+    /// Detach an entity from its parent, if it has one.
+    pub fn remove_parent(&mut self, child: EntityId) {
+        self.get_world_guard().remove_parent(child);
+    }
+
+    /// Relate an entity to another one by the relationship `R`, e.g.
+    /// `taconite.relate::<DockedAt>(ship, station)`.
+    /// This takes in the IDs of both entities (`EntityId`s).
+    pub fn relate<R: Relationship>(&mut self, source: EntityId, target: EntityId) {
+        self.get_world_guard().relate::<R>(source, target);
+    }
+
+    /// Stop relating an entity to another one by the relationship `R`.
+    pub fn unrelate<R: Relationship>(&mut self, source: EntityId, target: EntityId) {
+        self.get_world_guard().unrelate::<R>(source, target);
+    }
+
+    /// Get the IDs of the entities related to `target` by the relationship `R`.
+    pub fn related_to<R: Relationship>(&self, target: EntityId) -> Vec<EntityId> {
+        self.get_world_guard().related_to::<R>(target).collect()
+    }
+
     /// Register a new component.
     /// This takes in a struct with `Component` implemeneted.
     pub fn register_component<T: 'static + Component>(&mut self) {
         Mutex::lock(&self.world).unwrap().register_component::<T>();
     }
 
+    /// Register a new component with a certain storage kind.
+    /// This takes in a struct with `Component` implemented and the `StorageKind` to store it
+    /// with, e.g. `StorageKind::SparseSet` for components that are added and removed often.
+    pub fn register_component_with_storage<T: 'static + Component>(&mut self, kind: StorageKind) {
+        self.get_world_guard()
+            .register_component_with_storage::<T>(kind);
+    }
+
+    /// Register a new component with hooks that run when it's added, replaced or removed.
+    /// This takes in a struct with `Component` implemented and its `ComponentHooks`, e.g.
+    /// `ComponentHooks::default().on_remove(|body, entity_id, commands| ..)`.
+    pub fn register_component_with_hooks<T: 'static + Component>(
+        &mut self,
+        hooks: ComponentHooks<T>,
+    ) {
+        self.get_world_guard().register_component_with_hooks(hooks);
+    }
+
+    /// Register a new component that is included in snapshots.
+    /// This takes in a struct with `Component` and `Clone` implemented. Components registered
+    /// any other way are left out of snapshots.
+    pub fn register_cloneable_component<T: 'static + Component + Clone>(&mut self) {
+        self.get_world_guard().register_cloneable_component::<T>();
+    }
+
+    /// Register a new component that can be saved in scenes.
+    /// This takes in a struct with `Component`, `Serialize` and `Deserialize` implemented.
+    pub fn register_serializable_component<T>(&mut self)
+    where
+        T: 'static + Component + Serialize + DeserializeOwned,
+    {
+        self.get_world_guard()
+            .register_serializable_component::<T>();
+    }
+
+    /// Register a new component that can be saved in scenes and refers to other entities.
+    /// This takes in a struct with `Component`, `Serialize`, `Deserialize` and `MapEntities`
+    /// implemented.
+    pub fn register_serializable_component_with_entities<T>(&mut self)
+    where
+        T: 'static + Component + Serialize + DeserializeOwned + MapEntities,
+    {
+        self.get_world_guard()
+            .register_serializable_component_with_entities::<T>();
+    }
+
+    /// Register a new component that can be listed, printed and edited without knowing its
+    /// type.
+    /// This takes in a struct with `Reflect` implemented, usually with `#[derive(Reflect)]`.
+    pub fn register_reflect_component<T: 'static + Component + Reflect>(&mut self) {
+        self.get_world_guard().register_reflect_component::<T>();
+    }
+
+    /// Print an entity with its reflected components, for debugging.
+    /// This takes in the ID of the entity (an `EntityId`).
+    pub fn debug_entity(&self, entity_id: EntityId) -> Option<String> {
+        self.get_world_guard().debug_entity(entity_id)
+    }
+
+    /// Save the entities with serializable components to a JSON scene file.
+    pub fn save_scene(&self, path: impl AsRef<Path>) -> Result<(), SceneError> {
+        self.get_world_guard().save_scene_to_file(path)
+    }
+
+    /// Load the entities in a JSON scene file as new entities.
+    /// This returns the map from the saved entity IDs to the new ones.
+    pub fn load_scene(&mut self, path: impl AsRef<Path>) -> Result<EntityMap, SceneError> {
+        self.get_world_guard().load_scene_from_file(path)
+    }
+
+    /// Copy every entity along with its cloneable components, e.g. for a checkpoint.
+    /// The returned `Snapshot` is cheap to clone, and can be brought back with `restore`.
+    pub fn snapshot(&self) -> Snapshot {
+        self.get_world_guard().snapshot()
+    }
+
+    /// Bring the entities and cloneable components back to how they were in a snapshot.
+    pub fn restore(&mut self, snapshot: &Snapshot) {
+        self.get_world_guard().restore(snapshot);
+    }
+
+    /// Add a queue of events of type `T`, which systems read with an `EventReader<T>`.
+    pub fn add_event<T: 'static + Send + Sync>(&mut self) {
+        self.get_world_guard().add_event::<T>();
+    }
+
+    /// Send an event to the systems reading events of type `T`.
+    pub fn send_event<T: 'static + Send + Sync>(&mut self, event: T) {
+        self.get_world_guard().send_event(event);
+    }
+
+    /// Add an observer that runs as soon as an `E` event is triggered on any entity.
+    /// This takes in a struct with `Observer<E>` implemented.
+    pub fn add_observer<E: 'static, O: 'static + Observer<E>>(&mut self, observer: O) {
+        self.get_world_guard().add_observer(observer);
+    }
+
+    /// Add an observer to an entity, which runs as soon as an `E` event is triggered on the
+    /// entity or one of its children.
+    /// This takes in the ID of the entity (an `EntityId`) and a struct with `Observer<E>`
+    /// implemented.
+    pub fn observe<E: 'static, O: 'static + Observer<E>>(
+        &mut self,
+        entity_id: EntityId,
+        observer: O,
+    ) {
+        self.get_world_guard().observe(entity_id, observer);
+    }
+
+    /// Trigger an event on an entity, running its observers right away.
+    /// This takes in the event, e.g. `Clicked`, and the ID of the entity (an `EntityId`).
+    pub fn trigger<E: 'static + Send + Sync>(&mut self, event: E, target: EntityId) {
+        self.get_world_guard().trigger(event, target);
+    }
+
     /// Add a system to run every frame.
     /// This takes in a sruct with `System` implemented.
     pub fn add_system<T: 'static + System>(&mut self, system: T) {
         Mutex::lock(&self.world).unwrap().add_system(system);
     }
 
+    /// Add a system to run every frame in a certain stage.
+    /// This takes in the `Stage`, a struct with `System` implemented and a `SystemConfig` to order
+    /// it with.
+    pub fn add_system_to_stage<T: 'static + System>(
+        &mut self,
+        stage: Stage,
+        system: T,
+        config: SystemConfig,
+    ) {
+        self.get_world_guard()
+            .add_system_to_stage(stage, system, config);
+    }
+
+    /// Add a parallel system to run every frame.
+    /// This takes in a struct with `ParallelSystem` implemented, which runs at the same time as
+    /// other parallel systems it doesn't conflict with.
+    pub fn add_parallel_system<T: 'static + ParallelSystem>(&mut self, system: T) {
+        self.get_world_guard().add_parallel_system(system);
+    }
+
+    /// Add a parallel system to run every frame in a certain stage.
+    /// This takes in the `Stage`, a struct with `ParallelSystem` implemented and a
+    /// `SystemConfig` to order it with.
+    pub fn add_parallel_system_to_stage<T: 'static + ParallelSystem>(
+        &mut self,
+        stage: Stage,
+        system: T,
+        config: SystemConfig,
+    ) {
+        self.get_world_guard()
+            .add_parallel_system_to_stage(stage, system, config);
+    }
+
+    /// Set how often systems in `Stage::FixedUpdate` run.
+    /// This takes in a `FixedTimestep`, e.g. `FixedTimestep::from_hz(120.0)`. The default is 60
+    /// times a second. Render systems can smooth between fixed updates with `Time::alpha`.
+    pub fn set_fixed_timestep(&mut self, fixed_timestep: FixedTimestep) {
+        self.get_world_guard().set_fixed_timestep(fixed_timestep);
+    }
+
+    /// Choose whether parallel systems run at the same time (the default), or one after another
+    /// for debugging.
+    pub fn set_execution_mode(&mut self, execution_mode: ExecutionMode) {
+        self.get_world_guard().set_execution_mode(execution_mode);
+    }
+
     /// Add a render system to run every frame (after normal systems are updated).
     /// This takes in a struct with `RenderSystem` implemented.
     pub fn add_render_system<T: 'static + RenderSystem>(&mut self, system: T) {
         Mutex::lock(&self.world).unwrap().add_render_system(system);
     }
 
+    /// Add a render system to run every frame, ordered by a `SystemConfig`.
+    pub fn add_render_system_with_config<T: 'static + RenderSystem>(
+        &mut self,
+        system: T,
+        config: SystemConfig,
+    ) {
+        self.get_world_guard()
+            .add_render_system_with_config(system, config);
+    }
+
     /// Add a component to an entity.
-    /// This takes in an ID (a usize) and a struct that implements `Component`.
+    /// This takes in an ID (an `EntityId`) and a struct that implements `Component`.
     pub fn add_component_to_entity<T: 'static + Component>(
         &mut self,
-        entity_id: usize,
+        entity_id: EntityId,
         component: T,
     ) {
         self.get_world_guard()
             .add_component_to_entity(entity_id, component);
     }
 
+    /// Insert a resource, which systems can get through `Resources`.
+    /// This replaces the previous resource of the same type.
+    pub fn insert_resource<T: 'static + Send + Sync>(&mut self, resource: T) {
+        self.get_world_guard().insert_resource(resource);
+    }
+
+    /// Remove a resource and return it, if it exists.
+    pub fn remove_resource<T: 'static + Send + Sync>(&mut self) -> Option<T> {
+        self.get_world_guard().remove_resource::<T>()
+    }
+
+    /// Get a resource.
+    /// The world is behind a lock, so this hands the resource to `f` instead of returning it.
+    pub fn get_resource<T: 'static + Send + Sync, R>(&self, f: impl FnOnce(&T) -> R) -> Option<R> {
+        self.get_world_guard().get_resource::<T>().map(f)
+    }
+
+    /// Mutably get a resource.
+    /// The world is behind a lock, so this hands the resource to `f` instead of returning it.
+    pub fn get_resource_mut<T: 'static + Send + Sync, R>(
+        &mut self,
+        f: impl FnOnce(&mut T) -> R,
+    ) -> Option<R> {
+        self.get_world_guard().get_resource_mut::<T>().map(f)
+    }
+
     pub fn is_key_down(&self, key_variant: Key) -> bool {
-        todo!("Check if key is down");
+        self.get_resource(|input_handler: &InputHandler| input_handler.is_key_down(key_variant))
+            .unwrap_or_default()
+    }
+
+    /// Add another world, like a separate world for the UI, and get it back behind a lock.
+    /// It's set up like the main world, with its own systems and resources, and is updated and
+    /// rendered after the worlds added before it.
+    /// This takes in the name of the world, e.g. `"ui"`. A world with the same name is replaced,
+    /// but the main world can't be.
+    pub fn add_world(&mut self, name: impl Into<String>) -> Option<Arc<Mutex<World>>> {
+        self.worlds.insert(name, engine_world())
+    }
+
+    /// Get a world by name. The main world is named `MAIN_WORLD`.
+    pub fn get_world(&self, name: &str) -> Option<Arc<Mutex<World>>> {
+        self.worlds.get(name)
+    }
+
+    /// Get a handle to every world, which can be moved into systems or other threads to add
+    /// worlds or switch them on and off while the engine runs.
+    pub fn worlds(&self) -> Worlds {
+        self.worlds.clone()
     }
 
-    /// Start the window and begin rendering and updating.
+    /// Choose whether a world is updated every frame.
+    /// This takes in the name of the world, e.g. `"loading"`.
+    pub fn set_world_updating(&mut self, name: &str, update: bool) {
+        self.worlds.set_updating(name, update);
+    }
+
+    /// Choose whether the render systems of a world run every frame.
+    /// This takes in the name of the world, e.g. `"loading"`.
+    pub fn set_world_rendering(&mut self, name: &str, render: bool) {
+        self.worlds.set_rendering(name, render);
+    }
+
+    /// Move an entity from one world to another, along with all of its components and
+    /// children.
+    /// This takes in the ID of the entity (an `EntityId`) and the names of both worlds, and
+    /// returns the ID of the entity in the other world.
+    pub fn move_entity(&mut self, entity_id: EntityId, from: &str, to: &str) -> Option<EntityId> {
+        self.worlds.move_entity(entity_id, from, to)
+    }
+
+    /// Start the window and begin rendering and updating every world that is switched on.
     /// This takes in a `WindowConfig` and opens the window.
+    /// The systems are ordered first, so this doesn't start if their order has a cycle.
     pub fn start(&mut self, window_config: WindowConfig) {
-        if let Err(e) = self.window_starter.run(window_config) {
+        for world in self.worlds.all() {
+            if let Err(e) = world.lock().unwrap().build_schedule() {
+                error!("Error starting: {e}");
+                return;
+            }
+        }
+
+        if let Err(e) = self.window_starter.run(window_config, self.worlds.clone()) {
             error!("Error starting window: {e}");
         };
     }
 
-    fn get_world_guard(&self) -> MutexGuard<World> {
+    fn get_world_guard(&self) -> MutexGuard<'_, World> {
         self.world.lock().unwrap()
     }
 }
@@ -1,5 +1,7 @@
 use thiserror::*;
 
+use crate::ecs::{EntityId, Stage};
+
 #[derive(Debug, Error)]
 pub enum WindowError {
     #[error("There was no GPU detected.")]
@@ -11,3 +13,78 @@ pub enum WindowError {
     #[error("Can't resize to this size. (Is it greater than 0 on both axis?)")]
     ResizeError,
 }
+
+#[derive(Debug, Error)]
+pub enum EcsError {
+    #[error("The component `{0}` isn't registered.")]
+    UnregisteredComponent(&'static str),
+    #[error("The component `{0}` is already borrowed in a way that conflicts with this access.")]
+    BorrowConflict(&'static str),
+    #[error("The entity {0} does not exist.")]
+    NoSuchEntity(EntityId),
+    #[error("The entity {0} is disabled.")]
+    DisabledEntity(EntityId),
+    #[error("The entity {0} doesn't have the components this query needs.")]
+    QueryMismatch(EntityId),
+    #[error("The systems in the {0:?} stage can't be ordered, as they form a cycle: {1}")]
+    SystemOrderCycle(Stage, String),
+    #[error("The system didn't declare access to the component `{0}`.")]
+    UndeclaredAccess(&'static str),
+    #[error("The component `{0}` can only be changed through `set_parent` and `remove_parent`.")]
+    HierarchyComponent(&'static str),
+}
+
+#[derive(Debug, Error)]
+pub enum SceneError {
+    #[error("The scene isn't valid: {0}")]
+    Malformed(#[source] serde_json::Error),
+    #[error("The entity {entity_id} has the component `{name}`, which isn't registered as serializable.")]
+    UnknownComponent { entity_id: EntityId, name: String },
+    #[error("The component `{name}` of the entity {entity_id} is invalid: {source}")]
+    InvalidComponent {
+        entity_id: EntityId,
+        name: String,
+        #[source]
+        source: serde_json::Error,
+    },
+    #[error("The entity {entity_id} is spawned from the prefab `{name}`, which doesn't exist.")]
+    UnknownPrefab { entity_id: EntityId, name: String },
+    #[error(
+        "The prefab `{prefab}` has the component `{name}`, which isn't registered as serializable."
+    )]
+    UnknownPrefabComponent { prefab: String, name: String },
+    #[error("The component `{name}` of the prefab `{prefab}` is invalid: {source}")]
+    InvalidPrefabComponent {
+        prefab: String,
+        name: String,
+        #[source]
+        source: serde_json::Error,
+    },
+    #[error("The entity {0} is in the scene more than once.")]
+    DuplicateEntity(EntityId),
+    #[error("The scene refers to the entity {0}, which isn't in it.")]
+    UnmappedEntity(EntityId),
+    #[error("The entity {0} is its own ancestor in the scene.")]
+    ParentCycle(EntityId),
+    #[error("Failed to read or write the scene file: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+#[derive(Debug, Error)]
+pub enum ReflectError {
+    #[error("`{type_name}` can't be set from {value}.")]
+    InvalidValue {
+        type_name: &'static str,
+        value: serde_json::Value,
+    },
+    #[error("`{type_name}` has no field named `{field}`.")]
+    UnknownField {
+        type_name: &'static str,
+        field: String,
+    },
+    #[error("`{type_name}` has no variant named `{variant}`.")]
+    UnknownVariant {
+        type_name: &'static str,
+        variant: String,
+    },
+}
@@ -1,37 +1,196 @@
-use crate::{
-    input_handler::{InputHandler, Key},
-    World,
-};
+use crate::{Events, InputHandler, Key, World, Worlds};
 // use sdl2::{event::Event, keyboard::Keycode, render::Canvas, video::Window, EventPump};
-use std::sync::{Arc, Mutex};
+use winit::event::{ElementState, VirtualKeyCode, WindowEvent};
+
+/// Sent when the window is resized, with the new size in physical pixels.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WindowResized {
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Sent when the window is asked to close.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WindowCloseRequested;
+
+/// Sent when the window gains (`true`) or loses (`false`) focus.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WindowFocused(pub bool);
+
+/// Sent when a key is pressed or released.
+/// `key` is `None` for keys that don't have a `Key` variant.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KeyInput {
+    pub key: Option<Key>,
+    pub scancode: u32,
+    pub pressed: bool,
+}
+
+/// Sent when the cursor moves, with its position in physical pixels from the top left of the
+/// window.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CursorMoved {
+    pub x: f64,
+    pub y: f64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MouseButton {
+    Left,
+    Right,
+    Middle,
+    Other(u16),
+}
+
+/// Sent when a mouse button is pressed or released.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MouseInput {
+    pub button: MouseButton,
+    pub pressed: bool,
+}
 
-#[allow(dead_code)]
 pub struct EventHandler {
-    world: Arc<Mutex<World>>,
+    worlds: Worlds,
     // pub(crate) event_pump: Option<EventPump>,
-    pub(crate) input_handler: InputHandler,
 }
 
 impl EventHandler {
-    pub fn new(world: Arc<Mutex<World>>) -> EventHandler {
+    pub fn new(worlds: Worlds) -> EventHandler {
         EventHandler {
-            world,
+            worlds,
             // event_pump,
-            input_handler: InputHandler::default(),
         }
     }
 
+    /// Add the events window events are published through.
+    pub(crate) fn add_events(world: &mut World) {
+        world
+            .add_event::<WindowResized>()
+            .add_event::<WindowCloseRequested>()
+            .add_event::<WindowFocused>()
+            .add_event::<KeyInput>()
+            .add_event::<CursorMoved>()
+            .add_event::<MouseInput>();
+    }
+
+    /// Update every world that is switched on, in order.
     pub fn update(&mut self) {
-        self.world.lock().unwrap().update(&self.input_handler);
+        for world in self.worlds.updating() {
+            world.lock().unwrap().update();
+        }
     }
 
-    pub fn draw(&mut self, auto_clear: bool) {
+    pub fn draw(&mut self, _auto_clear: bool) {
         // if auto_clear {
         //     canvas.clear();
         // }
 
-        self.world.lock().unwrap().update_render();
+        for world in self.worlds.rendering() {
+            world.lock().unwrap().update_render();
+        }
+    }
+
+    /// Publish a window event to every world that is updated, and keep their `InputHandler`
+    /// resources up to date.
+    pub(crate) fn handle_window_event(&mut self, event: &WindowEvent) {
+        for world in self.worlds.updating() {
+            publish_window_event(&mut world.lock().unwrap(), event);
+        }
     }
 
     // NOTE: `handle_events` moved to `State`
 }
+
+/// Send a window event to a world, skipping the events the world didn't add.
+fn send_event<T: 'static + Send + Sync>(world: &mut World, event: T) {
+    if world.get_resource::<Events<T>>().is_some() {
+        world.send_event(event);
+    }
+}
+
+fn publish_window_event(world: &mut World, event: &WindowEvent) {
+    match event {
+        WindowEvent::Resized(size) => {
+            send_event(
+                world,
+                WindowResized {
+                    width: size.width,
+                    height: size.height,
+                },
+            );
+        }
+        WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
+            send_event(
+                world,
+                WindowResized {
+                    width: new_inner_size.width,
+                    height: new_inner_size.height,
+                },
+            );
+        }
+        WindowEvent::CloseRequested => {
+            send_event(world, WindowCloseRequested);
+        }
+        WindowEvent::Focused(focused) => {
+            send_event(world, WindowFocused(*focused));
+        }
+        WindowEvent::KeyboardInput { input, .. } => {
+            let key = input.virtual_keycode.and_then(key_from_keycode);
+            let pressed = input.state == ElementState::Pressed;
+
+            if let (Some(key), Some(input_handler)) =
+                (key, world.get_resource_mut::<InputHandler>())
+            {
+                match pressed {
+                    true => input_handler.add_key(key),
+                    false => input_handler.remove_key(key),
+                }
+            }
+
+            send_event(
+                world,
+                KeyInput {
+                    key,
+                    scancode: input.scancode,
+                    pressed,
+                },
+            );
+        }
+        WindowEvent::CursorMoved { position, .. } => {
+            send_event(
+                world,
+                CursorMoved {
+                    x: position.x,
+                    y: position.y,
+                },
+            );
+        }
+        WindowEvent::MouseInput { state, button, .. } => {
+            let button = match button {
+                winit::event::MouseButton::Left => MouseButton::Left,
+                winit::event::MouseButton::Right => MouseButton::Right,
+                winit::event::MouseButton::Middle => MouseButton::Middle,
+                winit::event::MouseButton::Other(other) => MouseButton::Other(*other),
+            };
+
+            send_event(
+                world,
+                MouseInput {
+                    button,
+                    pressed: *state == ElementState::Pressed,
+                },
+            );
+        }
+        _ => {}
+    }
+}
+
+fn key_from_keycode(keycode: VirtualKeyCode) -> Option<Key> {
+    match keycode {
+        VirtualKeyCode::W => Some(Key::W),
+        VirtualKeyCode::A => Some(Key::A),
+        VirtualKeyCode::S => Some(Key::S),
+        VirtualKeyCode::D => Some(Key::D),
+        _ => None,
+    }
+}
@@ -5,8 +5,7 @@ pub struct InputHandler {
     keys: Vec<Key>,
 }
 
-#[allow(dead_code)] // TODO: Will be gone soon
-#[derive(PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Key {
     W,
     A,
@@ -14,7 +13,6 @@ pub enum Key {
     D,
 }
 
-#[allow(dead_code)] // TODO: Will be gone soon
 impl InputHandler {
     pub(crate) fn add_key(&mut self, key_variant: Key) {
         if !self.keys.contains(&key_variant) {
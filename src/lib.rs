@@ -1,3 +1,6 @@
+// Lets `#[derive(Reflect)]` refer to `::taconite` from inside the engine too.
+extern crate self as taconite;
+
 mod components;
 pub mod ecs;
 mod engine;
@@ -11,12 +14,15 @@ mod render_systems;
 mod state;
 mod texture_manager;
 mod thread_pool;
+mod transform_systems;
 mod window_config;
 mod window_starter;
+mod worlds;
 
 pub use components::*;
 pub use ecs::*;
 pub use engine::*;
+pub use errors::{EcsError, ReflectError, SceneError};
 pub use event_handler::*;
 pub use input_handler::*;
 pub use logging::*;
@@ -24,4 +30,6 @@ pub use math::*;
 pub use new_year::*;
 pub use render_systems::*;
 pub use texture_manager::*;
+pub use transform_systems::*;
 pub use window_config::*;
+pub use worlds::*;
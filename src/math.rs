@@ -1,6 +1,12 @@
+use serde::{Deserialize, Serialize};
+use std::ops::{Add, Mul};
+
+use crate::ecs::Reflect;
+
 /// A struct that holds a:
 ///     * x coordinate
 ///     * y coordinate
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize, Reflect)]
 pub struct Vector2 {
     pub x: f32,
     pub y: f32,
@@ -10,12 +16,23 @@ pub struct Vector2 {
 ///     * x coordinate
 ///     * y coordinate
 ///     * z coordinate
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize, Reflect)]
 pub struct Vector3 {
     pub x: f32,
     pub y: f32,
     pub z: f32,
 }
 
+/// A rotation stored as a unit quaternion, which can be composed without the problems of euler
+/// angles.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize, Reflect)]
+pub struct Quaternion {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub w: f32,
+}
+
 impl Vector2 {
     pub fn new(x: f32, y: f32) -> Self {
         Self { x, y }
@@ -38,4 +55,110 @@ impl Vector3 {
             z: val,
         }
     }
+
+    /// Rotate the vector by euler angles in radians, around the x, then y, then z axis.
+    pub fn rotate(&self, rotation: Vector3) -> Self {
+        let (sin_x, cos_x) = rotation.x.sin_cos();
+        let (sin_y, cos_y) = rotation.y.sin_cos();
+        let (sin_z, cos_z) = rotation.z.sin_cos();
+
+        let y = self.y * cos_x - self.z * sin_x;
+        let z = self.y * sin_x + self.z * cos_x;
+
+        let x = self.x * cos_y + z * sin_y;
+        let z = -self.x * sin_y + z * cos_y;
+
+        Self {
+            x: x * cos_z - y * sin_z,
+            y: x * sin_z + y * cos_z,
+            z,
+        }
+    }
+}
+
+impl Quaternion {
+    /// The rotation that leaves everything as it is.
+    pub const IDENTITY: Self = Self {
+        x: 0.0,
+        y: 0.0,
+        z: 0.0,
+        w: 1.0,
+    };
+
+    /// Create a rotation from euler angles in radians, applied in the same order as
+    /// `Vector3::rotate`.
+    pub fn from_euler(rotation: Vector3) -> Self {
+        let (sin_x, cos_x) = (rotation.x * 0.5).sin_cos();
+        let (sin_y, cos_y) = (rotation.y * 0.5).sin_cos();
+        let (sin_z, cos_z) = (rotation.z * 0.5).sin_cos();
+
+        Self {
+            x: sin_x * cos_y * cos_z - cos_x * sin_y * sin_z,
+            y: cos_x * sin_y * cos_z + sin_x * cos_y * sin_z,
+            z: cos_x * cos_y * sin_z - sin_x * sin_y * cos_z,
+            w: cos_x * cos_y * cos_z + sin_x * sin_y * sin_z,
+        }
+    }
+
+    /// Get the euler angles in radians that `Vector3::rotate` would rotate by.
+    pub fn to_euler(&self) -> Vector3 {
+        let Self { x, y, z, w } = *self;
+        Vector3 {
+            x: (2.0 * (w * x + y * z)).atan2(1.0 - 2.0 * (x * x + y * y)),
+            y: (2.0 * (w * y - z * x)).clamp(-1.0, 1.0).asin(),
+            z: (2.0 * (w * z + x * y)).atan2(1.0 - 2.0 * (y * y + z * z)),
+        }
+    }
+
+    /// Rotate a vector by this rotation.
+    pub fn rotate(&self, vector: Vector3) -> Vector3 {
+        // v + 2w(u x v) + 2u x (u x v), where u is the vector part of the quaternion.
+        let cross = |a: Vector3, b: Vector3| Vector3 {
+            x: a.y * b.z - a.z * b.y,
+            y: a.z * b.x - a.x * b.z,
+            z: a.x * b.y - a.y * b.x,
+        };
+        let axis = Vector3::new(self.x, self.y, self.z);
+        let t = cross(axis, vector);
+        let t = Vector3::new(2.0 * t.x, 2.0 * t.y, 2.0 * t.z);
+        let u = cross(axis, t);
+
+        Vector3 {
+            x: vector.x + self.w * t.x + u.x,
+            y: vector.y + self.w * t.y + u.y,
+            z: vector.z + self.w * t.z + u.z,
+        }
+    }
+}
+
+impl Default for Quaternion {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+/// Combine two rotations, the result rotates by `other` first and then by `self`.
+impl Mul for Quaternion {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self {
+        Self {
+            x: self.w * other.x + self.x * other.w + self.y * other.z - self.z * other.y,
+            y: self.w * other.y - self.x * other.z + self.y * other.w + self.z * other.x,
+            z: self.w * other.z + self.x * other.y - self.y * other.x + self.z * other.w,
+            w: self.w * other.w - self.x * other.x - self.y * other.y - self.z * other.z,
+        }
+    }
+}
+
+impl Add for Vector3 {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            x: self.x + other.x,
+            y: self.y + other.y,
+            z: self.z + other.z,
+        }
+    }
 }
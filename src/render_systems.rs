@@ -6,8 +6,9 @@ pub struct ShapeRenderSystem {}
 impl RenderSystem for ShapeRenderSystem {
     fn update(
         &mut self,
-        manager: &mut crate::EntityManager,
+        _manager: &mut crate::EntityManager,
         _accessor: &mut crate::EntityIdAccessor,
+        _resources: &crate::Resources,
     ) -> Option<()> {
         todo!("Setup the Shape Render System");
     }
@@ -1,6 +1,14 @@
 use num_cpus::get;
+use rayon::{ThreadPool, ThreadPoolBuilder};
 
-#[allow(dead_code)]
 pub fn thread_pool_size() -> usize {
     (get() as f64 * 1.5) as usize
 }
+
+/// Build the pool parallel systems run on, sized by `thread_pool_size`.
+pub(crate) fn build_thread_pool() -> Result<ThreadPool, rayon::ThreadPoolBuildError> {
+    ThreadPoolBuilder::new()
+        .num_threads(thread_pool_size())
+        .thread_name(|index| format!("taconite-worker-{index}"))
+        .build()
+}
@@ -0,0 +1,127 @@
+use std::collections::HashSet;
+
+use crate::ecs::*;
+use crate::{GlobalTransform, Transform};
+
+/// Computes the `GlobalTransform` of every entity from its `Transform` and the transforms of
+/// its ancestors. `Taconite` runs this in the post-update stage with the label
+/// `transform_propagation`.
+/// Entities with a `Transform` get a `GlobalTransform` the first time this runs after it's
+/// added. Entities without a `Transform` are skipped, and their children are treated as roots.
+///
+/// Only the entities whose `Transform` or `Parent` changed since the last run are updated,
+/// along with their descendants, so still parts of the hierarchy cost next to nothing.
+pub struct TransformPropagationSystem {}
+
+impl System for TransformPropagationSystem {
+    fn update(
+        &mut self,
+        manager: &mut EntityManager,
+        accessor: &mut EntityIdAccessor,
+        _commands: &mut Commands,
+        _resources: &mut Resources,
+    ) -> Option<()> {
+        let missing = accessor
+            .query_filtered::<&Transform, Without<GlobalTransform>>(manager)
+            .ok()?
+            .map(|(entity_id, transform)| (entity_id, GlobalTransform::from(*transform)))
+            .collect::<Vec<_>>();
+        for (entity_id, global_transform) in missing {
+            manager.add_component_to_entity(entity_id, global_transform);
+        }
+
+        let mut dirty = accessor
+            .query_filtered::<&Transform, (
+                With<GlobalTransform>,
+                Or<(Changed<Transform>, Changed<Parent>, Added<GlobalTransform>)>,
+            )>(manager)
+            .ok()?
+            .map(|(entity_id, _)| entity_id)
+            .collect::<HashSet<_>>();
+        // Entities that lost their parent become roots, and so do the children of entities that
+        // lost their transform.
+        dirty.extend(accessor.removed::<Parent>(manager));
+        for entity_id in accessor.removed::<Transform>(manager).collect::<Vec<_>>() {
+            if let Some(children) = manager.get_component_quietly::<Children>(entity_id) {
+                dirty.extend(children.iter().copied());
+            }
+        }
+        dirty.retain(|entity_id| {
+            manager
+                .get_component_quietly::<Transform>(*entity_id)
+                .is_some()
+        });
+
+        // Updating an entity updates its descendants too, so only the topmost dirty entities
+        // need to be walked down from.
+        let tops = dirty
+            .iter()
+            .filter(|entity_id| !has_dirty_ancestor(manager, **entity_id, &dirty))
+            .copied()
+            .collect::<Vec<_>>();
+        for entity_id in tops {
+            let parent_global_transform = transform_parent(manager, entity_id)
+                .and_then(|parent| manager.get_component_quietly::<GlobalTransform>(parent))
+                .copied();
+            propagate(manager, entity_id, parent_global_transform);
+        }
+
+        Some(())
+    }
+}
+
+/// Get the parent of an entity, if it has a `Transform` the entity's is relative to.
+fn transform_parent(manager: &EntityManager, entity_id: EntityId) -> Option<EntityId> {
+    let parent = manager.get_component_quietly::<Parent>(entity_id)?.get();
+    manager
+        .get_component_quietly::<Transform>(parent)
+        .map(|_| parent)
+}
+
+fn has_dirty_ancestor(
+    manager: &EntityManager,
+    entity_id: EntityId,
+    dirty: &HashSet<EntityId>,
+) -> bool {
+    let mut entity_id = entity_id;
+    while let Some(parent) = transform_parent(manager, entity_id) {
+        if dirty.contains(&parent) {
+            return true;
+        }
+        entity_id = parent;
+    }
+    false
+}
+
+/// Compute the `GlobalTransform` of an entity and its descendants.
+fn propagate(
+    manager: &mut EntityManager,
+    entity_id: EntityId,
+    parent_global_transform: Option<GlobalTransform>,
+) {
+    let Some(transform) = manager.get_component_quietly::<Transform>(entity_id) else {
+        return;
+    };
+    let global_transform = match parent_global_transform {
+        Some(parent_global_transform) => parent_global_transform.mul_transform(transform),
+        None => GlobalTransform::from(*transform),
+    };
+
+    // Only write when it moved, so `Changed<GlobalTransform>` stays useful.
+    if manager
+        .get_component_quietly::<GlobalTransform>(entity_id)
+        .is_some_and(|current| *current != global_transform)
+    {
+        if let Some(current) = manager.borrow_component_mut::<GlobalTransform>(entity_id) {
+            *current = global_transform;
+        }
+    }
+
+    let children = manager
+        .get_component_quietly::<Children>(entity_id)
+        .map(|children| children.iter().copied().collect::<Vec<_>>())
+        .unwrap_or_default();
+    for child in children {
+        propagate(manager, child, Some(global_transform));
+    }
+}
@@ -1,5 +1,5 @@
 use crate::state::State;
-use crate::{errors::*, WindowConfig};
+use crate::{errors::*, EventHandler, WindowConfig, Worlds};
 
 use winit::dpi::PhysicalSize;
 use winit::event::*;
@@ -14,14 +14,21 @@ pub struct WindowStarter();
 // TODO: Remove allowing dead code
 
 impl WindowStarter {
-    pub fn run(&mut self, window_config: WindowConfig) -> Result<(), WindowError> {
-        pollster::block_on(self.create_window(window_config))?;
+    pub fn run(&mut self, window_config: WindowConfig, worlds: Worlds) -> Result<(), WindowError> {
+        pollster::block_on(self.create_window(window_config, worlds))?;
 
         Ok(())
     }
 
     // TODO: Add a way to get window config back in.
-    pub async fn create_window(&mut self, window_config: WindowConfig) -> Result<(), WindowError> {
+    pub async fn create_window(
+        &mut self,
+        window_config: WindowConfig,
+        worlds: Worlds,
+    ) -> Result<(), WindowError> {
+        let auto_clear = window_config.auto_clear;
+        let mut event_handler = EventHandler::new(worlds);
+
         let event_loop = EventLoop::new();
         let window = WindowBuilder::new()
             .with_inner_size(PhysicalSize::new(window_config.width, window_config.height))
@@ -37,6 +44,8 @@ impl WindowStarter {
                     ref event,
                     window_id,
                 } if window_id == state.window().id() => {
+                    event_handler.handle_window_event(event);
+
                     if !state.input(event) {
                         // UPDATED!
                         match event {
@@ -51,17 +60,23 @@ impl WindowStarter {
                                 ..
                             } => *control_flow = ControlFlow::Exit,
                             WindowEvent::Resized(physical_size) => {
-                                state.resize(*physical_size);
+                                if let Err(e) = state.resize(*physical_size) {
+                                    warn!("{e}");
+                                }
                             }
                             WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
                                 // new_inner_size is &&mut so w have to dereference it twice
-                                state.resize(**new_inner_size);
+                                if let Err(e) = state.resize(**new_inner_size) {
+                                    warn!("{e}");
+                                }
                             }
                             _ => {}
                         }
                     }
                 }
                 Event::RedrawRequested(window_id) if window_id == state.window().id() => {
+                    event_handler.update();
+                    event_handler.draw(auto_clear);
                     state.update();
                     match state.render() {
                         Ok(_) => {}
@@ -0,0 +1,189 @@
+use std::sync::{Arc, Mutex};
+use tracing::error;
+
+use crate::{EntityId, World};
+
+/// The name of the world every `Taconite` method works on.
+pub const MAIN_WORLD: &str = "main";
+
+struct WorldEntry {
+    name: String,
+    world: Arc<Mutex<World>>,
+    update: bool,
+    render: bool,
+}
+
+/// The worlds the engine runs by name, in the order they're updated and rendered.
+/// This is a handle, so it can be cloned into systems or other threads, e.g. to fill a world
+/// in the background and switch it on once it's loaded.
+#[derive(Clone, Default)]
+pub struct Worlds {
+    entries: Arc<Mutex<Vec<WorldEntry>>>,
+}
+
+impl Worlds {
+    /// Create the list of worlds with the main world in it.
+    pub(crate) fn new(main_world: Arc<Mutex<World>>) -> Self {
+        let worlds = Self::default();
+        worlds.set_entry(MAIN_WORLD.to_string(), main_world);
+        worlds
+    }
+
+    /// Add a world that is updated and rendered after the ones already added, and get it back
+    /// behind a lock. A world with the same name is replaced, but the main world can't be.
+    pub fn insert(&self, name: impl Into<String>, world: World) -> Option<Arc<Mutex<World>>> {
+        let name = name.into();
+        if name == MAIN_WORLD {
+            error!("Failed to add the world `{name}`. The main world can't be replaced.");
+            return None;
+        }
+
+        let world = Arc::new(Mutex::new(world));
+        self.set_entry(name, world.clone());
+        Some(world)
+    }
+
+    fn set_entry(&self, name: String, world: Arc<Mutex<World>>) {
+        let mut entries = self.entries.lock().unwrap();
+        match entries.iter_mut().find(|entry| entry.name == name) {
+            Some(entry) => entry.world = world,
+            None => entries.push(WorldEntry {
+                name,
+                world,
+                update: true,
+                render: true,
+            }),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<Arc<Mutex<World>>> {
+        self.entries
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|entry| entry.name == name)
+            .map(|entry| entry.world.clone())
+    }
+
+    /// Stop running a world and get it back. The main world can't be removed.
+    pub fn remove(&self, name: &str) -> Option<Arc<Mutex<World>>> {
+        if name == MAIN_WORLD {
+            error!("Failed to remove the main world. Switch it off instead.");
+            return None;
+        }
+
+        let mut entries = self.entries.lock().unwrap();
+        let index = entries.iter().position(|entry| entry.name == name)?;
+        Some(entries.remove(index).world)
+    }
+
+    /// Get the names of the worlds, in the order they're updated and rendered.
+    pub fn names(&self) -> Vec<String> {
+        self.entries
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|entry| entry.name.clone())
+            .collect()
+    }
+
+    /// Choose whether the engine updates a world every frame.
+    /// A world that isn't updated doesn't get window events either.
+    pub fn set_updating(&self, name: &str, update: bool) {
+        self.with_entry(name, |entry| entry.update = update);
+    }
+
+    /// Choose whether the engine runs the render systems of a world every frame.
+    pub fn set_rendering(&self, name: &str, render: bool) {
+        self.with_entry(name, |entry| entry.render = render);
+    }
+
+    pub fn is_updating(&self, name: &str) -> bool {
+        self.with_entry(name, |entry| entry.update)
+            .unwrap_or_default()
+    }
+
+    pub fn is_rendering(&self, name: &str) -> bool {
+        self.with_entry(name, |entry| entry.render)
+            .unwrap_or_default()
+    }
+
+    /// Move an entity from one world to another, along with all of its components and
+    /// children, and get its ID in the other world.
+    /// This locks both worlds, so it can't be called from a system of either of them.
+    pub fn move_entity(&self, entity_id: EntityId, from: &str, to: &str) -> Option<EntityId> {
+        if from == to {
+            error!("Failed to move entity {entity_id}. It is already in the world `{to}`.");
+            return None;
+        }
+
+        let (from_world, to_world, from_first) = {
+            let entries = self.entries.lock().unwrap();
+            let find = |name: &str| {
+                let found = entries
+                    .iter()
+                    .enumerate()
+                    .find(|(_, entry)| entry.name == name)
+                    .map(|(index, entry)| (index, entry.world.clone()));
+                if found.is_none() {
+                    error!("Failed to move entity {entity_id}. There is no world named `{name}`.");
+                }
+                found
+            };
+            let (from_index, from_world) = find(from)?;
+            let (to_index, to_world) = find(to)?;
+            (from_world, to_world, from_index < to_index)
+        };
+
+        // Worlds are always locked in the order they were added, so two moves in opposite
+        // directions can't deadlock.
+        let (mut from_world, mut to_world) = match from_first {
+            true => {
+                let from_world = from_world.lock().unwrap();
+                (from_world, to_world.lock().unwrap())
+            }
+            false => {
+                let to_world = to_world.lock().unwrap();
+                (from_world.lock().unwrap(), to_world)
+            }
+        };
+        from_world.move_entity(entity_id, &mut to_world)
+    }
+
+    /// Get the worlds to update, in order.
+    /// The list is copied, so the worlds can be locked one at a time without holding it.
+    pub(crate) fn updating(&self) -> Vec<Arc<Mutex<World>>> {
+        self.filtered(|entry| entry.update)
+    }
+
+    /// Get the worlds to render, in order.
+    pub(crate) fn rendering(&self) -> Vec<Arc<Mutex<World>>> {
+        self.filtered(|entry| entry.render)
+    }
+
+    /// Get every world, in order.
+    pub(crate) fn all(&self) -> Vec<Arc<Mutex<World>>> {
+        self.filtered(|_| true)
+    }
+
+    fn filtered(&self, filter: impl Fn(&WorldEntry) -> bool) -> Vec<Arc<Mutex<World>>> {
+        self.entries
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|entry| filter(entry))
+            .map(|entry| entry.world.clone())
+            .collect()
+    }
+
+    fn with_entry<R>(&self, name: &str, f: impl FnOnce(&mut WorldEntry) -> R) -> Option<R> {
+        let mut entries = self.entries.lock().unwrap();
+        match entries.iter_mut().find(|entry| entry.name == name) {
+            Some(entry) => Some(f(entry)),
+            None => {
+                error!("There is no world named `{name}`.");
+                None
+            }
+        }
+    }
+}
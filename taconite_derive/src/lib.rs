@@ -0,0 +1,132 @@
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{
+    parse_macro_input, parse_quote, spanned::Spanned, Data, DataEnum, DataStruct, DeriveInput,
+    Error, Fields, Index,
+};
+
+/// Implement `Reflect` for a struct, whose fields must all implement `Reflect`, or for an enum
+/// with only unit variants.
+#[proc_macro_derive(Reflect)]
+pub fn derive_reflect(input: TokenStream) -> TokenStream {
+    let mut input = parse_macro_input!(input as DeriveInput);
+
+    for param in input.generics.type_params_mut() {
+        param.bounds.push(parse_quote!(::taconite::Reflect));
+    }
+
+    let body = match &input.data {
+        Data::Struct(data) => Ok(reflect_struct(data)),
+        Data::Enum(data) => reflect_enum(data),
+        Data::Union(data) => Err(Error::new(
+            data.union_token.span(),
+            "Reflect can't be derived for unions.",
+        )),
+    };
+    let body = match body {
+        Ok(body) => body,
+        Err(e) => return e.to_compile_error().into(),
+    };
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    quote! {
+        impl #impl_generics ::taconite::Reflect for #name #ty_generics #where_clause {
+            fn as_any(&self) -> &dyn ::std::any::Any {
+                self
+            }
+
+            fn as_any_mut(&mut self) -> &mut dyn ::std::any::Any {
+                self
+            }
+
+            #body
+        }
+    }
+    .into()
+}
+
+fn reflect_struct(data: &DataStruct) -> TokenStream2 {
+    let (names, members): (Vec<_>, Vec<_>) = match &data.fields {
+        Fields::Named(fields) => fields
+            .named
+            .iter()
+            .map(|field| {
+                let ident = field.ident.as_ref().unwrap();
+                (ident.to_string(), quote!(#ident))
+            })
+            .unzip(),
+        Fields::Unnamed(fields) => (0..fields.unnamed.len())
+            .map(|index| {
+                let index = Index::from(index);
+                (index.index.to_string(), quote!(#index))
+            })
+            .unzip(),
+        Fields::Unit => (Vec::new(), Vec::new()),
+    };
+    let types = data.fields.iter().map(|field| &field.ty);
+
+    quote! {
+        fn type_info() -> ::taconite::TypeInfo
+        where
+            Self: Sized,
+        {
+            ::taconite::TypeInfo::new::<Self>(::taconite::TypeKind::Struct(vec![
+                #(::taconite::FieldInfo::new::<#types>(#names),)*
+            ]))
+        }
+
+        fn fields(&self) -> Vec<(&'static str, &dyn ::taconite::Reflect)> {
+            vec![#((#names, &self.#members as &dyn ::taconite::Reflect),)*]
+        }
+
+        fn field_mut(&mut self, name: &str) -> Option<&mut dyn ::taconite::Reflect> {
+            match name {
+                #(#names => Some(&mut self.#members),)*
+                _ => None,
+            }
+        }
+    }
+}
+
+fn reflect_enum(data: &DataEnum) -> Result<TokenStream2, Error> {
+    if let Some(variant) = data
+        .variants
+        .iter()
+        .find(|variant| !matches!(variant.fields, Fields::Unit))
+    {
+        return Err(Error::new(
+            variant.span(),
+            "Reflect can only be derived for enums with unit variants.",
+        ));
+    }
+
+    let idents: Vec<_> = data.variants.iter().map(|variant| &variant.ident).collect();
+    let names: Vec<_> = idents.iter().map(|ident| ident.to_string()).collect();
+
+    Ok(quote! {
+        fn type_info() -> ::taconite::TypeInfo
+        where
+            Self: Sized,
+        {
+            ::taconite::TypeInfo::new::<Self>(::taconite::TypeKind::Enum(vec![#(#names,)*]))
+        }
+
+        fn variant(&self) -> Option<&'static str> {
+            match self {
+                #(Self::#idents => Some(#names),)*
+            }
+        }
+
+        fn set_variant(&mut self, name: &str) -> bool {
+            match name {
+                #(#names => {
+                    *self = Self::#idents;
+                    true
+                })*
+                _ => false,
+            }
+        }
+    })
+}
@@ -0,0 +1,113 @@
+use std::sync::{Arc, Mutex};
+use taconite::*;
+
+struct Health(u32);
+/// Spawned by the `Health` hooks.
+struct Spark;
+
+impl Component for Health {}
+impl Component for Spark {}
+
+/// The hooks that ran, with the health they saw, in order.
+type HookLog = Arc<Mutex<Vec<(&'static str, u32)>>>;
+
+/// A manager whose `Health` hooks each spawn a spark.
+fn manager(log: &HookLog) -> EntityManager {
+    let hook = |name: &'static str, log: &HookLog| {
+        let log = log.clone();
+        move |health: &Health, _: EntityId, commands: &mut Commands| {
+            log.lock().unwrap().push((name, health.0));
+            commands.spawn_bundle((Spark,));
+        }
+    };
+
+    let spark_log = log.clone();
+    let mut manager = EntityManager::default();
+    manager
+        .register::<Spark>()
+        .set_component_hooks(ComponentHooks::default().on_add(
+            move |_: &Spark, _: EntityId, _: &mut Commands| {
+                spark_log.lock().unwrap().push(("spark", 0));
+            },
+        ))
+        .register::<Health>()
+        .set_component_hooks(
+            ComponentHooks::default()
+                .on_add(hook("add", log))
+                .on_remove(hook("remove", log)),
+        );
+    manager
+}
+
+fn sparks(manager: &mut EntityManager) -> Vec<EntityId> {
+    let mut sparks = manager
+        .query::<&Spark>()
+        .unwrap()
+        .map(|(entity_id, _)| entity_id)
+        .collect::<Vec<_>>();
+    sparks.sort();
+    sparks
+}
+
+#[test]
+fn hook_commands_wait_for_the_whole_batch() {
+    let log = HookLog::default();
+    let mut manager = manager(&log);
+
+    manager.spawn_batch((0..3).map(|health| (Health(health),)));
+    // The sparks are only spawned once the whole batch is.
+    let log = log.lock().unwrap().drain(..).collect::<Vec<_>>();
+    assert_eq!(log[..3], [("add", 0), ("add", 1), ("add", 2)]);
+    assert_eq!(log[3..], [("spark", 0); 3]);
+    assert_eq!(sparks(&mut manager).len(), 3);
+}
+
+#[test]
+fn batches_reuse_the_ids_they_free() {
+    let mut manager = EntityManager::default();
+    let first = manager.spawn_batch((0..100).map(|_| ()));
+    manager.despawn_batch(first.iter().copied());
+    assert!(first
+        .iter()
+        .all(|entity_id| !manager.entity_exists(*entity_id)));
+
+    let second = manager.spawn_batch((0..100).map(|_| ()));
+    let indexes = |entity_ids: &[EntityId]| {
+        let mut indexes = entity_ids.iter().map(EntityId::index).collect::<Vec<_>>();
+        indexes.sort();
+        indexes
+    };
+    assert_eq!(indexes(&first), indexes(&second));
+    assert!(first.iter().all(|entity_id| !second.contains(entity_id)));
+}
+
+#[test]
+fn despawned_batches_take_their_children_along() {
+    let log = HookLog::default();
+    let mut manager = manager(&log);
+    let entity_ids = manager.spawn_batch((0..4).map(|health| (Health(health),)));
+    let [parent, child, grandchild, other] = entity_ids[..] else {
+        unreachable!()
+    };
+    manager
+        .set_parent(child, parent)
+        .set_parent(grandchild, child);
+    let spark_count = sparks(&mut manager).len();
+    log.lock().unwrap().clear();
+
+    // The child is already gone by the time the batch gets to it.
+    manager.despawn_batch([parent, child]);
+    for entity_id in [parent, child, grandchild] {
+        assert!(!manager.entity_exists(entity_id));
+    }
+    assert!(manager.entity_exists(other));
+
+    // The sparks are only spawned once the whole batch is removed.
+    let log = log.lock().unwrap().drain(..).collect::<Vec<_>>();
+    assert_eq!(log[3..], [("spark", 0); 3]);
+    let mut removed = log[..3].to_vec();
+    removed.sort();
+    assert_eq!(removed, [("remove", 0), ("remove", 1), ("remove", 2)]);
+    // Entities spawned by hooks during the batch aren't removed with it.
+    assert_eq!(sparks(&mut manager).len(), spark_count + 3);
+}
@@ -0,0 +1,166 @@
+use std::sync::{Arc, Mutex};
+use taconite::*;
+
+struct Position(i32);
+
+impl Component for Position {}
+
+/// The entities whose position a `Mover` changes the next time it runs.
+type Moves = Arc<Mutex<Vec<EntityId>>>;
+
+struct Mover(Moves);
+
+impl System for Mover {
+    fn update(
+        &mut self,
+        manager: &mut EntityManager,
+        _accessor: &mut EntityIdAccessor,
+        _commands: &mut Commands,
+        _resources: &mut Resources,
+    ) -> Option<()> {
+        for entity_id in self.0.lock().unwrap().drain(..) {
+            manager.borrow_component_mut::<Position>(entity_id)?.0 += 1;
+        }
+        Some(())
+    }
+}
+
+/// The entities a `Watcher` saw with an added, changed or removed position, every run.
+#[derive(Debug, PartialEq)]
+struct Seen {
+    added: Vec<EntityId>,
+    changed: Vec<EntityId>,
+    removed: Vec<EntityId>,
+}
+
+fn sorted(mut entity_ids: Vec<EntityId>) -> Vec<EntityId> {
+    entity_ids.sort();
+    entity_ids
+}
+
+struct Watcher(Arc<Mutex<Vec<Seen>>>);
+
+impl System for Watcher {
+    fn update(
+        &mut self,
+        manager: &mut EntityManager,
+        accessor: &mut EntityIdAccessor,
+        _commands: &mut Commands,
+        _resources: &mut Resources,
+    ) -> Option<()> {
+        let added = accessor
+            .query_filtered::<(), Added<Position>>(manager)
+            .ok()?
+            .map(|(entity_id, _)| entity_id)
+            .collect();
+        let changed = accessor
+            .query_filtered::<(), Changed<Position>>(manager)
+            .ok()?
+            .map(|(entity_id, _)| entity_id)
+            .collect();
+        let removed = accessor.removed::<Position>(manager).collect();
+        self.0.lock().unwrap().push(Seen {
+            added: sorted(added),
+            changed: sorted(changed),
+            removed: sorted(removed),
+        });
+        Some(())
+    }
+}
+
+struct Watched {
+    world: World,
+    early: Moves,
+    late: Moves,
+    seen: Arc<Mutex<Vec<Seen>>>,
+}
+
+/// A world where the watcher runs between a mover in the pre-update stage and one in the
+/// post-update stage.
+fn watched() -> Watched {
+    let early = Moves::default();
+    let late = Moves::default();
+    let seen = Arc::default();
+    let mut world = World::default();
+    world
+        .register_component::<Position>()
+        .add_system_to_stage(
+            Stage::PreUpdate,
+            Mover(early.clone()),
+            SystemConfig::default(),
+        )
+        .add_system(Watcher(Arc::clone(&seen)))
+        .add_system_to_stage(
+            Stage::PostUpdate,
+            Mover(late.clone()),
+            SystemConfig::default(),
+        );
+    Watched {
+        world,
+        early,
+        late,
+        seen,
+    }
+}
+
+impl Watched {
+    /// Run an update and get what the watcher saw.
+    fn update(&mut self) -> Seen {
+        self.world.update();
+        self.seen.lock().unwrap().pop().unwrap()
+    }
+}
+
+fn seen(added: &[EntityId], changed: &[EntityId], removed: &[EntityId]) -> Seen {
+    Seen {
+        added: added.to_vec(),
+        changed: changed.to_vec(),
+        removed: removed.to_vec(),
+    }
+}
+
+#[test]
+fn changes_are_seen_once_per_system_run() {
+    let mut watched = watched();
+    let first = watched.world.spawn((Position(0),));
+    let second = watched.world.spawn((Position(0),));
+
+    // Added components count as changed too.
+    assert_eq!(
+        watched.update(),
+        seen(&[first, second], &[first, second], &[])
+    );
+    assert_eq!(watched.update(), seen(&[], &[], &[]));
+
+    watched.early.lock().unwrap().push(first);
+    assert_eq!(watched.update(), seen(&[], &[first], &[]));
+    assert_eq!(watched.update(), seen(&[], &[], &[]));
+
+    // A change made after the watcher ran is seen on its next run.
+    watched.late.lock().unwrap().push(second);
+    assert_eq!(watched.update(), seen(&[], &[], &[]));
+    assert_eq!(watched.update(), seen(&[], &[second], &[]));
+    assert_eq!(watched.update(), seen(&[], &[], &[]));
+
+    let third = watched.world.spawn(());
+    watched.world.add_component_to_entity(third, Position(0));
+    assert_eq!(watched.update(), seen(&[third], &[third], &[]));
+}
+
+#[test]
+fn removals_are_seen_once() {
+    let mut watched = watched();
+    let removed = watched.world.spawn((Position(0),));
+    let kept = watched.world.spawn((Position(0),));
+    watched.update();
+
+    watched.world.remove_entity(removed);
+    assert_eq!(watched.update(), seen(&[], &[], &[removed]));
+    assert_eq!(watched.update(), seen(&[], &[], &[]));
+
+    // A component added and removed between runs is only seen as removed.
+    let brief = watched.world.spawn((Position(0),));
+    watched.world.remove_entity(brief);
+    watched.early.lock().unwrap().push(kept);
+    assert_eq!(watched.update(), seen(&[], &[kept], &[brief]));
+}
@@ -0,0 +1,88 @@
+use taconite::*;
+
+struct Health(u32);
+
+impl Component for Health {}
+
+#[test]
+fn spawned_id_is_known_up_front() {
+    let mut manager = EntityManager::default();
+    manager.register::<Health>();
+    let parent = manager.create_entity();
+
+    let mut commands = manager.commands();
+    let child = commands.spawn().insert(Health(3)).id();
+    commands.set_parent(child, parent);
+    assert!(!manager.entity_exists(child));
+
+    // Entities created before the commands are applied don't take the reserved ID.
+    let other = manager.create_entity();
+    assert_ne!(other, child);
+
+    commands.apply(&mut manager);
+    assert!(manager.entity_exists(child));
+    assert_eq!(manager.borrow_component::<Health>(child).unwrap().0, 3);
+    assert_eq!(
+        manager.borrow_component::<Parent>(child).unwrap().get(),
+        parent
+    );
+    assert!(manager
+        .borrow_component::<Children>(parent)
+        .unwrap()
+        .iter()
+        .any(|entity_id| *entity_id == child));
+}
+
+#[test]
+fn spawned_id_reuses_removed_index() {
+    let mut manager = EntityManager::default();
+    let removed = manager.create_entity();
+    manager.remove_entity(removed);
+
+    let mut commands = manager.commands();
+    let spawned = commands.spawn().id();
+    assert_eq!(spawned.index(), removed.index());
+    assert_ne!(spawned, removed);
+
+    commands.apply(&mut manager);
+    assert!(manager.entity_exists(spawned));
+    assert!(!manager.entity_exists(removed));
+}
+
+#[test]
+fn inserting_replaces_the_component() {
+    let mut manager = EntityManager::default();
+    manager.register::<Health>();
+    let entity_id = manager.spawn((Health(1),));
+
+    let mut commands = manager.commands();
+    commands.insert(entity_id, Health(2));
+    commands.spawn().insert(Health(3)).insert(Health(4));
+    commands.apply(&mut manager);
+
+    assert_eq!(manager.borrow_component::<Health>(entity_id).unwrap().0, 2);
+    let mut healths = manager
+        .query::<&Health>()
+        .unwrap()
+        .map(|(_, health)| health.0)
+        .collect::<Vec<_>>();
+    healths.sort();
+    assert_eq!(healths, [2, 4]);
+}
+
+#[test]
+fn dropped_commands_give_back_reserved_ids() {
+    let mut manager = EntityManager::default();
+    let mut commands = manager.commands();
+    let reserved = commands.spawn().id();
+    drop(commands);
+
+    assert_eq!(manager.create_entity(), reserved);
+
+    // Applied commands keep the ID.
+    let mut commands = manager.commands();
+    let spawned = commands.spawn().id();
+    commands.apply(&mut manager);
+    drop(commands);
+    assert_ne!(manager.create_entity(), spawned);
+}
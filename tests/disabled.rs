@@ -0,0 +1,78 @@
+use taconite::*;
+
+struct Health(u32);
+struct Poisoned;
+
+impl Component for Health {}
+impl Component for Poisoned {}
+
+fn manager() -> EntityManager {
+    let mut manager = EntityManager::default();
+    manager.register::<Health>().register::<Poisoned>();
+    manager
+}
+
+fn healths(manager: &mut EntityManager) -> Vec<u32> {
+    let mut healths = manager
+        .query::<&Health>()
+        .unwrap()
+        .map(|(_, health)| health.0)
+        .collect::<Vec<_>>();
+    healths.sort();
+    healths
+}
+
+#[test]
+fn disabled_entities_are_skipped() {
+    let mut manager = manager();
+    let mut accessor = EntityIdAccessor::default();
+    let enabled = manager.spawn((Health(1),));
+    let disabled = manager.spawn((Health(2),));
+    // The cached query has to notice the entity leaving.
+    assert_eq!(accessor.query::<&Health>(&mut manager).unwrap().count(), 2);
+
+    manager.set_entity_enabled(disabled, false);
+    assert!(manager.entity_exists(disabled));
+    assert!(!manager.is_entity_enabled(disabled));
+    assert_eq!(healths(&mut manager), [1]);
+    let cached = accessor
+        .query::<&Health>(&mut manager)
+        .unwrap()
+        .map(|(entity_id, _)| entity_id)
+        .collect::<Vec<_>>();
+    assert_eq!(cached, [enabled]);
+    assert!(matches!(
+        manager.query_entity::<&Health>(disabled).err(),
+        Some(EcsError::DisabledEntity(_))
+    ));
+    assert_eq!(manager.borrow_components::<Health>().unwrap().len(), 1);
+
+    // The components can still be reached by ID.
+    assert_eq!(manager.borrow_component::<Health>(disabled).unwrap().0, 2);
+}
+
+#[test]
+fn entities_keep_their_components_while_disabled() {
+    let mut manager = manager();
+    let entity_id = manager.spawn((Health(1),));
+
+    manager.set_entity_enabled(entity_id, false);
+    // Components added or changed while disabled stay hidden too.
+    manager.add_component_to_entity(entity_id, Poisoned);
+    manager.borrow_component_mut::<Health>(entity_id).unwrap().0 = 5;
+    assert_eq!(manager.query::<&Poisoned>().unwrap().count(), 0);
+    // Disabling twice doesn't change anything.
+    manager.set_entity_enabled(entity_id, false);
+
+    manager.set_entity_enabled(entity_id, true);
+    assert_eq!(healths(&mut manager), [5]);
+    assert_eq!(manager.query::<&Poisoned>().unwrap().count(), 1);
+    let (health, _) = manager
+        .query_entity::<(&Health, &Poisoned)>(entity_id)
+        .unwrap();
+    assert_eq!(health.0, 5);
+
+    manager.set_entity_enabled(entity_id, false);
+    manager.set_entity_enabled(entity_id, true);
+    assert_eq!(healths(&mut manager), [5]);
+}
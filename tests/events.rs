@@ -0,0 +1,102 @@
+use std::sync::{Arc, Mutex};
+use taconite::*;
+
+struct Damage(u32);
+
+fn read(reader: &mut EventReader<Damage>, events: &Events<Damage>) -> Vec<u32> {
+    reader.read(events).map(|damage| damage.0).collect()
+}
+
+#[test]
+fn events_live_for_two_updates() {
+    let mut events = Events::default();
+    events.send(Damage(1));
+    events.update();
+    events.send(Damage(2));
+
+    // A new reader still sees the events from the previous update.
+    assert_eq!(read(&mut EventReader::default(), &events), [1, 2]);
+
+    events.update();
+    assert_eq!(read(&mut EventReader::default(), &events), [2]);
+
+    events.update();
+    assert!(events.is_empty());
+    assert_eq!(events.event_count(), 2);
+}
+
+#[test]
+fn readers_have_their_own_cursors() {
+    let mut events = Events::default();
+    let mut first = EventReader::default();
+    let mut second = EventReader::default();
+    events.send(Damage(1));
+    events.send(Damage(2));
+
+    assert_eq!(read(&mut first, &events), [1, 2]);
+    assert!(read(&mut first, &events).is_empty());
+
+    events.update();
+    events.send(Damage(3));
+    assert_eq!(read(&mut first, &events), [3]);
+    assert_eq!(read(&mut second, &events), [1, 2, 3]);
+
+    events.send(Damage(4));
+    second.clear(&events);
+    assert_eq!(read(&mut first, &events), [4]);
+    assert!(read(&mut second, &events).is_empty());
+}
+
+/// Reads damage in the pre-update stage, before it's sent in the update stage.
+struct DamageLog(EventReader<Damage>, Arc<Mutex<Vec<u32>>>);
+
+impl System for DamageLog {
+    fn update(
+        &mut self,
+        _manager: &mut EntityManager,
+        _accessor: &mut EntityIdAccessor,
+        _commands: &mut Commands,
+        resources: &mut Resources,
+    ) -> Option<()> {
+        let mut log = self.1.lock().unwrap();
+        log.extend(self.0.read_resource(resources).map(|damage| damage.0));
+        Some(())
+    }
+}
+
+struct DealDamage(u32);
+
+impl System for DealDamage {
+    fn update(
+        &mut self,
+        _manager: &mut EntityManager,
+        _accessor: &mut EntityIdAccessor,
+        commands: &mut Commands,
+        _resources: &mut Resources,
+    ) -> Option<()> {
+        self.0 += 1;
+        commands.send_event(Damage(self.0));
+        Some(())
+    }
+}
+
+#[test]
+fn systems_see_events_sent_later_in_the_previous_update() {
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let mut world = World::default();
+    world
+        .add_event::<Damage>()
+        .add_system_to_stage(
+            Stage::PreUpdate,
+            DamageLog(EventReader::default(), log.clone()),
+            SystemConfig::default(),
+        )
+        .add_system(DealDamage(0));
+
+    world.update();
+    assert!(log.lock().unwrap().is_empty());
+
+    world.update();
+    world.update();
+    assert_eq!(*log.lock().unwrap(), [1, 2]);
+}
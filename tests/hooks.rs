@@ -0,0 +1,60 @@
+use std::sync::{Arc, Mutex};
+use taconite::*;
+
+struct Health(u32);
+
+impl Component for Health {}
+
+/// The hooks that ran, with the health they saw, in order.
+type HookLog = Arc<Mutex<Vec<(&'static str, u32)>>>;
+
+fn manager(log: &HookLog) -> EntityManager {
+    let hook = |name: &'static str, log: &HookLog| {
+        let log = log.clone();
+        move |health: &Health, _: EntityId, _: &mut Commands| {
+            log.lock().unwrap().push((name, health.0));
+        }
+    };
+
+    let mut manager = EntityManager::default();
+    manager.register::<Health>().set_component_hooks(
+        ComponentHooks::default()
+            .on_add(hook("add", log))
+            .on_insert(hook("insert", log))
+            .on_remove(hook("remove", log)),
+    );
+    manager
+}
+
+#[test]
+fn hooks_run_on_add_replace_and_remove() {
+    let log = HookLog::default();
+    let mut manager = manager(&log);
+    let entity_id = manager.create_entity();
+
+    manager.add_component_to_entity(entity_id, Health(1));
+    assert_eq!(
+        log.lock().unwrap().drain(..).collect::<Vec<_>>(),
+        [("add", 1), ("insert", 1)]
+    );
+
+    // The replaced component is removed before the new one is inserted.
+    manager.insert_component(entity_id, Health(2));
+    assert_eq!(
+        log.lock().unwrap().drain(..).collect::<Vec<_>>(),
+        [("remove", 1), ("insert", 2)]
+    );
+
+    manager.remove_component_from_entity::<Health>(entity_id);
+    assert_eq!(
+        log.lock().unwrap().drain(..).collect::<Vec<_>>(),
+        [("remove", 2)]
+    );
+
+    manager.insert_component(entity_id, Health(3));
+    manager.remove_entity(entity_id);
+    assert_eq!(
+        log.lock().unwrap().drain(..).collect::<Vec<_>>(),
+        [("add", 3), ("insert", 3), ("remove", 3)]
+    );
+}
@@ -0,0 +1,64 @@
+use taconite::*;
+
+fn tagged(manager: &EntityManager, tag: &str) -> Vec<EntityId> {
+    manager.entities_with_tag(tag).collect()
+}
+
+#[test]
+fn renames_and_removals_update_the_lookup() {
+    let mut manager = EntityManager::default();
+    let first = manager.spawn((Name::new("enemy"),));
+    let second = manager.spawn((Name::new("enemy"),));
+    assert_eq!(manager.find_by_name("enemy"), Some(first));
+
+    manager.insert_component(first, Name::new("boss"));
+    assert_eq!(manager.find_by_name("boss"), Some(first));
+    assert_eq!(manager.find_by_name("enemy"), Some(second));
+
+    // Getting the name back puts the entity after the ones that kept it.
+    manager.insert_component(first, Name::new("enemy"));
+    assert_eq!(manager.find_by_name("boss"), None);
+    assert_eq!(manager.find_by_name("enemy"), Some(second));
+
+    manager.remove_component_from_entity::<Name>(second);
+    assert_eq!(manager.find_by_name("enemy"), Some(first));
+
+    manager.remove_entity(first);
+    assert_eq!(manager.find_by_name("enemy"), None);
+}
+
+#[test]
+fn despawning_removes_tags_from_the_lookup() {
+    let mut manager = EntityManager::default();
+    let parent = manager.create_entity();
+    let child = manager.create_entity();
+    manager
+        .set_parent(child, parent)
+        .add_tag(parent, "flying")
+        .add_tag(child, "flying")
+        .add_tag(child, "small");
+    assert_eq!(tagged(&manager, "flying"), [parent, child]);
+
+    manager.remove_tag(parent, "flying");
+    assert_eq!(tagged(&manager, "flying"), [child]);
+    assert!(manager.has_tag(child, "small"));
+
+    // Children go along with their parent.
+    manager.remove_entity(parent);
+    assert!(tagged(&manager, "flying").is_empty());
+    assert!(tagged(&manager, "small").is_empty());
+}
+
+#[test]
+fn names_and_tags_move_to_other_worlds() {
+    let mut world = World::default();
+    let mut other = World::default();
+    let entity_id = world.spawn((Name::new("player"),));
+    world.add_tag(entity_id, "hero");
+
+    let moved = world.move_entity(entity_id, &mut other).unwrap();
+    assert_eq!(world.find_by_name("player"), None);
+    assert_eq!(world.entities_with_tag("hero").count(), 0);
+    assert_eq!(other.find_by_name("player"), Some(moved));
+    assert!(other.has_tag(moved, "hero"));
+}
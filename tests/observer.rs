@@ -0,0 +1,86 @@
+use std::sync::{Arc, Mutex};
+use taconite::*;
+
+struct Click;
+
+/// The observers that ran, with the target and current entity they saw, in order.
+type TriggerLog = Arc<Mutex<Vec<(&'static str, EntityId, EntityId)>>>;
+
+fn observer(
+    name: &'static str,
+    log: &TriggerLog,
+    stop: bool,
+) -> impl FnMut(&mut Trigger<'_, Click>, &mut EntityManager, &mut Commands, &mut Resources) + Send {
+    let log = log.clone();
+    move |trigger, _, _, _| {
+        log.lock()
+            .unwrap()
+            .push((name, trigger.target(), trigger.entity()));
+        if stop {
+            trigger.stop_propagation();
+        }
+    }
+}
+
+/// A button inside a panel inside a window.
+fn hierarchy(world: &mut World) -> [EntityId; 3] {
+    let window = world.spawn(());
+    let panel = world.spawn(());
+    let button = world.spawn(());
+    world.set_parent(panel, window).set_parent(button, panel);
+    [window, panel, button]
+}
+
+#[test]
+fn triggers_bubble_up_to_the_ancestors() {
+    let log = TriggerLog::default();
+    let mut world = World::default();
+    let [window, panel, button] = hierarchy(&mut world);
+    world
+        .add_observer(observer("global", &log, false))
+        .observe(window, observer("window", &log, false))
+        .observe(button, observer("button", &log, false));
+
+    world.trigger(Click, button);
+    assert_eq!(
+        *log.lock().unwrap(),
+        [
+            ("button", button, button),
+            ("window", button, window),
+            ("global", button, button),
+        ]
+    );
+
+    // Triggering on an ancestor doesn't reach its children.
+    log.lock().unwrap().clear();
+    world.trigger(Click, panel);
+    assert_eq!(
+        *log.lock().unwrap(),
+        [("window", panel, window), ("global", panel, panel)]
+    );
+}
+
+#[test]
+fn stopping_propagation_keeps_the_event_from_the_ancestors() {
+    let log = TriggerLog::default();
+    let mut world = World::default();
+    let [window, panel, button] = hierarchy(&mut world);
+    world
+        .add_observer(observer("global", &log, false))
+        .observe(window, observer("window", &log, false))
+        .observe(panel, observer("panel", &log, true))
+        .observe(panel, observer("other panel", &log, false))
+        .observe(button, observer("button", &log, false));
+
+    world.trigger(Click, button);
+    // Every observer of the entity that stopped it still runs, and so do the global ones.
+    assert_eq!(
+        *log.lock().unwrap(),
+        [
+            ("button", button, button),
+            ("panel", button, panel),
+            ("other panel", button, panel),
+            ("global", button, button),
+        ]
+    );
+}
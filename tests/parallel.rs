@@ -0,0 +1,280 @@
+use std::sync::{Arc, Mutex};
+use taconite::*;
+
+struct Position(f32);
+struct Velocity(f32);
+
+impl Component for Position {}
+impl Component for Velocity {}
+
+/// The frame each system last ran in, and the count it saw, by name, recorded every time one
+/// runs.
+type RunLog = Arc<Mutex<Vec<(&'static str, Option<u64>, u32)>>>;
+
+/// Counts the systems that ran, through commands.
+struct RunCount(u32);
+
+struct Recorder {
+    name: &'static str,
+    reads: bool,
+    writes: bool,
+    log: RunLog,
+}
+
+impl Recorder {
+    fn new(name: &'static str, reads: bool, writes: bool, log: &RunLog) -> Self {
+        Self {
+            name,
+            reads,
+            writes,
+            log: log.clone(),
+        }
+    }
+}
+
+impl ParallelSystem for Recorder {
+    fn access(&self, access: &mut SystemAccess) {
+        if self.reads {
+            access.read::<Velocity>();
+        }
+        if self.writes {
+            access.write::<Position>();
+        }
+    }
+
+    fn update(
+        &mut self,
+        view: &mut SystemView<'_>,
+        commands: &mut Commands,
+        resources: &Resources,
+    ) -> Option<()> {
+        let count = resources.get::<RunCount>().map_or(0, |count| count.0);
+        self.log
+            .lock()
+            .unwrap()
+            .push((self.name, view.last_run(), count));
+        commands.insert_resource(RunCount(count + 1));
+        Some(())
+    }
+}
+
+struct Mover;
+
+impl ParallelSystem for Mover {
+    fn access(&self, access: &mut SystemAccess) {
+        access.read::<Velocity>().write::<Position>();
+    }
+
+    fn update(
+        &mut self,
+        view: &mut SystemView<'_>,
+        commands: &mut Commands,
+        _resources: &Resources,
+    ) -> Option<()> {
+        for (entity_id, (mut position, velocity)) in
+            view.query::<(&mut Position, &Velocity)>().ok()?
+        {
+            position.0 += velocity.0;
+            commands.send_event(entity_id);
+        }
+        commands.insert_resource(1u32);
+        Some(())
+    }
+}
+
+/// What a system got back for each access it tried, by whether it was declared.
+type AccessLog = Arc<Mutex<Vec<(&'static str, Result<(), String>)>>>;
+
+/// Only declares that it reads `Velocity`.
+struct Sneaky(AccessLog);
+
+impl ParallelSystem for Sneaky {
+    fn access(&self, access: &mut SystemAccess) {
+        access.read::<Velocity>();
+    }
+
+    fn update(
+        &mut self,
+        view: &mut SystemView<'_>,
+        _commands: &mut Commands,
+        _resources: &Resources,
+    ) -> Option<()> {
+        let result = |result: Result<(), EcsError>| result.map_err(|e| e.to_string());
+        let mut log = self.0.lock().unwrap();
+        log.push((
+            "read Velocity",
+            result(view.query::<&Velocity>().map(|_| ())),
+        ));
+        log.push((
+            "write Velocity",
+            result(view.query::<&mut Velocity>().map(|_| ())),
+        ));
+        log.push((
+            "read Position",
+            result(view.query::<&Position>().map(|_| ())),
+        ));
+        log.push((
+            "filter Position",
+            result(
+                view.query_filtered::<&Velocity, With<Position>>()
+                    .map(|_| ()),
+            ),
+        ));
+        log.push((
+            "removed Velocity",
+            result(view.removed::<Velocity>().map(|_| ())),
+        ));
+        log.push((
+            "removed Position",
+            result(view.removed::<Position>().map(|_| ())),
+        ));
+        Some(())
+    }
+}
+
+#[test]
+fn undeclared_access_is_an_error() {
+    let log = AccessLog::default();
+    let mut world = World::default();
+    world
+        .register_component::<Position>()
+        .register_component::<Velocity>()
+        .add_parallel_system(Sneaky(log.clone()));
+
+    world.update();
+
+    let undeclared = |name: &'static str| Err(EcsError::UndeclaredAccess(name).to_string());
+    assert_eq!(
+        *log.lock().unwrap(),
+        [
+            ("read Velocity", Ok(())),
+            (
+                "write Velocity",
+                undeclared(std::any::type_name::<Velocity>())
+            ),
+            (
+                "read Position",
+                undeclared(std::any::type_name::<Position>())
+            ),
+            (
+                "filter Position",
+                undeclared(std::any::type_name::<Position>())
+            ),
+            ("removed Velocity", Ok(())),
+            (
+                "removed Position",
+                undeclared(std::any::type_name::<Position>())
+            ),
+        ]
+    );
+}
+
+/// Run a world with the systems `add` adds twice, and get what they recorded each time.
+fn record(
+    execution_mode: ExecutionMode,
+    add: impl FnOnce(&mut World, &RunLog),
+) -> Vec<(&'static str, Option<u64>, u32)> {
+    let log = RunLog::default();
+    let mut world = World::default();
+    world
+        .set_execution_mode(execution_mode)
+        .register_component::<Position>()
+        .register_component::<Velocity>();
+    add(&mut world, &log);
+
+    world.update();
+    world.update();
+    let log = log.lock().unwrap().clone();
+    log
+}
+
+fn last_run(log: &[(&'static str, Option<u64>, u32)], name: &str) -> Option<u64> {
+    log.iter()
+        .rev()
+        .find(|(system, ..)| *system == name)
+        .unwrap()
+        .1
+}
+
+fn add_conflicting_systems(world: &mut World, log: &RunLog) {
+    world
+        .add_parallel_system(Recorder::new("writer", false, true, log))
+        .add_parallel_system(Recorder::new("reader", true, false, log))
+        .add_parallel_system(Recorder::new("other writer", false, true, log))
+        .add_parallel_system(Recorder::new("other reader", true, false, log));
+}
+
+#[test]
+fn only_systems_without_conflicts_share_a_batch() {
+    for execution_mode in [ExecutionMode::Parallel, ExecutionMode::Serial] {
+        let log = record(execution_mode, add_conflicting_systems);
+
+        // Systems in the same batch last ran in the same frame.
+        assert_eq!(last_run(&log, "writer"), last_run(&log, "reader"));
+        assert_eq!(
+            last_run(&log, "other writer"),
+            last_run(&log, "other reader")
+        );
+        assert_ne!(last_run(&log, "writer"), last_run(&log, "other writer"));
+    }
+}
+
+#[test]
+fn ordered_systems_dont_share_a_batch() {
+    let log = record(ExecutionMode::Parallel, |world, log| {
+        world
+            .add_parallel_system_to_stage(
+                Stage::Update,
+                Recorder::new("reader", true, false, log),
+                SystemConfig {
+                    label: Some("reader"),
+                    ..Default::default()
+                },
+            )
+            .add_parallel_system_to_stage(
+                Stage::Update,
+                Recorder::new("later reader", true, false, log),
+                SystemConfig {
+                    after: vec!["reader"],
+                    ..Default::default()
+                },
+            );
+    });
+
+    assert_ne!(last_run(&log, "reader"), last_run(&log, "later reader"));
+}
+
+#[test]
+fn serial_runs_match_parallel_runs() {
+    let mut parallel = record(ExecutionMode::Parallel, add_conflicting_systems);
+    let mut serial = record(ExecutionMode::Serial, add_conflicting_systems);
+
+    // Systems in a batch only see the commands of the batches before theirs.
+    let counts = serial.iter().map(|(.., count)| *count).collect::<Vec<_>>();
+    assert_eq!(counts, [0, 0, 1, 1, 2, 2, 3, 3]);
+    parallel.sort();
+    serial.sort();
+    assert_eq!(parallel, serial);
+}
+
+#[test]
+fn commands_send_events_and_insert_resources() {
+    let mut world = World::default();
+    world.set_execution_mode(ExecutionMode::Parallel);
+    world
+        .register_component::<Position>()
+        .register_component::<Velocity>()
+        .add_event::<EntityId>()
+        .add_parallel_system(Mover);
+    let entity_id = world.spawn((Position(0.0), Velocity(2.0)));
+
+    world.update();
+
+    let mut reader = EventReader::<EntityId>::default();
+    let events = world.get_resource::<Events<EntityId>>().unwrap();
+    assert_eq!(
+        reader.read(events).copied().collect::<Vec<_>>(),
+        [entity_id]
+    );
+    assert_eq!(world.get_resource::<u32>(), Some(&1));
+}
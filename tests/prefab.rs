@@ -0,0 +1,70 @@
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use taconite::*;
+
+#[derive(Clone, Serialize, Deserialize)]
+struct Health(u32);
+
+impl Component for Health {}
+
+/// The hooks that ran, with the health they saw, in order.
+type HookLog = Arc<Mutex<Vec<(&'static str, u32)>>>;
+
+fn world(log: &HookLog) -> World {
+    let hook = |name: &'static str, log: &HookLog| {
+        let log = log.clone();
+        move |health: &Health, _: EntityId, _: &mut Commands| {
+            log.lock().unwrap().push((name, health.0));
+        }
+    };
+
+    let mut world = World::default();
+    world
+        .register_serializable_component::<Health>()
+        .set_component_hooks(
+            ComponentHooks::default()
+                .on_add(hook("add", log))
+                .on_insert(hook("insert", log))
+                .on_remove(hook("remove", log)),
+        );
+    world
+}
+
+fn take_log(log: &HookLog) -> Vec<(&'static str, u32)> {
+    log.lock().unwrap().drain(..).collect()
+}
+
+#[test]
+fn overrides_are_the_only_components_added() {
+    let log = HookLog::default();
+    let mut world = world(&log);
+    world.register_prefab("orc", (Health(1), Name::new("orc")));
+
+    let boss = world
+        .spawn_prefab("orc", (Health(5), Name::new("boss")))
+        .unwrap();
+    assert_eq!(take_log(&log), [("add", 5), ("insert", 5)]);
+    assert_eq!(world.find_by_name("boss"), Some(boss));
+    assert!(world.find_by_name("orc").is_none());
+
+    // Components that aren't overridden come from the prefab.
+    let orc = world.spawn_prefab("orc", ()).unwrap();
+    assert_eq!(take_log(&log), [("add", 1), ("insert", 1)]);
+    assert_eq!(world.find_by_name("orc"), Some(orc));
+}
+
+#[test]
+fn scene_overrides_are_the_only_components_added() {
+    let log = HookLog::default();
+    let mut world = world(&log);
+    let scene = r#"{
+        "prefabs": { "goblin": { "Health": 1, "Name": "goblin" } },
+        "entities": [
+            { "id": { "index": 0, "generation": 0 }, "prefab": "goblin", "components": { "Health": 9 } }
+        ]
+    }"#;
+
+    world.load_scene(scene).unwrap();
+    assert_eq!(take_log(&log), [("add", 9), ("insert", 9)]);
+    assert!(world.find_by_name("goblin").is_some());
+}
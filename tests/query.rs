@@ -0,0 +1,208 @@
+use taconite::*;
+
+struct Position(i32);
+struct Velocity;
+struct Sprite;
+struct Health(u32);
+struct Mass(u32);
+
+impl Component for Position {}
+impl Component for Velocity {}
+impl Component for Sprite {}
+impl Component for Health {}
+impl Component for Mass {}
+
+fn manager() -> EntityManager {
+    let mut manager = EntityManager::default();
+    manager
+        .register::<Position>()
+        .register::<Velocity>()
+        .register::<Sprite>()
+        .register::<Health>()
+        .register::<Mass>();
+    manager
+}
+
+fn sorted<T>(results: impl Iterator<Item = (EntityId, T)>) -> Vec<EntityId> {
+    let mut entity_ids = results.map(|(entity_id, _)| entity_id).collect::<Vec<_>>();
+    entity_ids.sort();
+    entity_ids
+}
+
+#[test]
+fn aliasing_borrows_conflict() {
+    let mut manager = manager();
+    let entity = manager.create_entity();
+    manager
+        .add_component_to_entity(entity, Position(0))
+        .add_component_to_entity(entity, Velocity);
+
+    assert!(matches!(
+        manager.query::<(&mut Position, &Position)>().err(),
+        Some(EcsError::BorrowConflict(_))
+    ));
+    assert!(matches!(
+        manager.query::<(&mut Position, &mut Position)>().err(),
+        Some(EcsError::BorrowConflict(_))
+    ));
+    assert!(matches!(
+        manager
+            .query_entity::<(&Velocity, &mut Velocity)>(entity)
+            .err(),
+        Some(EcsError::BorrowConflict(_))
+    ));
+
+    // Shared borrows of the same component, and borrows of different ones, don't conflict.
+    assert!(manager.query::<(&Position, &Position)>().is_ok());
+    assert!(manager.query::<(&mut Position, &Velocity)>().is_ok());
+}
+
+#[test]
+fn stale_id_after_reuse() {
+    let mut manager = manager();
+    let old = manager.create_entity();
+    manager.add_component_to_entity(old, Position(1));
+    manager.remove_entity(old);
+
+    let new = manager.create_entity();
+    assert_eq!(new.index(), old.index());
+    assert_ne!(new, old);
+    manager.add_component_to_entity(new, Position(2));
+
+    assert!(!manager.entity_exists(old));
+    assert!(manager.borrow_component::<Position>(old).is_none());
+    assert_eq!(manager.borrow_component::<Position>(new).unwrap().0, 2);
+
+    // The stale ID can't be used to change the entity that reused its index.
+    manager.add_component_to_entity(old, Velocity);
+    manager.remove_entity(old);
+    assert!(manager.entity_exists(new));
+    assert!(manager.borrow_component::<Velocity>(new).is_none());
+}
+
+#[test]
+fn cached_query_sees_structure_changes() {
+    let mut manager = manager();
+    let mut accessor = EntityIdAccessor::default();
+    let first = manager.create_entity();
+    manager.add_component_to_entity(first, Position(0));
+    assert_eq!(
+        accessor.query::<&Position>(&mut manager).unwrap().count(),
+        1
+    );
+
+    let second = manager.create_entity();
+    manager.add_component_to_entity(second, Position(0));
+    assert_eq!(
+        accessor.query::<&Position>(&mut manager).unwrap().count(),
+        2
+    );
+
+    manager.remove_component_from_entity::<Position>(first);
+    let ids: Vec<_> = accessor
+        .query::<&Position>(&mut manager)
+        .unwrap()
+        .map(|(entity_id, _)| entity_id)
+        .collect();
+    assert_eq!(ids, vec![second]);
+
+    manager.remove_entity(second);
+    assert_eq!(
+        accessor.query::<&Position>(&mut manager).unwrap().count(),
+        0
+    );
+}
+
+#[test]
+fn filters_narrow_down_the_entities() {
+    let mut manager = manager();
+    let still = manager.create_entity();
+    let moving = manager.create_entity();
+    let drawn = manager.create_entity();
+    let bare = manager.create_entity();
+    manager
+        .add_component_to_entity(still, Position(0))
+        .add_component_to_entity(moving, Position(1))
+        .add_component_to_entity(moving, Velocity)
+        .add_component_to_entity(drawn, Position(2))
+        .add_component_to_entity(drawn, Sprite)
+        .add_component_to_entity(bare, Sprite);
+
+    let with = manager.query_filtered::<&Position, With<Velocity>>();
+    assert_eq!(sorted(with.unwrap()), [moving]);
+    let without = manager.query_filtered::<&Position, Without<Velocity>>();
+    assert_eq!(sorted(without.unwrap()), [still, drawn]);
+    let both = manager.query_filtered::<&Position, (Without<Velocity>, Without<Sprite>)>();
+    assert_eq!(sorted(both.unwrap()), [still]);
+    let either = manager.query_filtered::<(), Or<(With<Velocity>, With<Sprite>)>>();
+    assert_eq!(sorted(either.unwrap()), [moving, drawn, bare]);
+
+    // `Option` doesn't narrow anything down, it only fetches the component when it's there.
+    let mut positions = manager
+        .query::<(&Position, Option<&Velocity>)>()
+        .unwrap()
+        .map(|(_, (position, velocity))| (position.0, velocity.is_some()))
+        .collect::<Vec<_>>();
+    positions.sort();
+    assert_eq!(positions, [(0, false), (1, true), (2, false)]);
+}
+
+#[test]
+fn cached_exclusions_see_structure_changes() {
+    let mut manager = manager();
+    let mut accessor = EntityIdAccessor::default();
+    let first = manager.create_entity();
+    let second = manager.create_entity();
+    manager
+        .add_component_to_entity(first, Position(0))
+        .add_component_to_entity(second, Position(0));
+
+    let still = |accessor: &mut EntityIdAccessor, manager: &mut EntityManager| {
+        sorted(
+            accessor
+                .query_filtered::<&Position, Without<Velocity>>(manager)
+                .unwrap(),
+        )
+    };
+    assert_eq!(still(&mut accessor, &mut manager), [first, second]);
+
+    // Only the excluded component changes, which the cache has to notice.
+    manager.add_component_to_entity(first, Velocity);
+    assert_eq!(still(&mut accessor, &mut manager), [second]);
+    // The same required components with nothing excluded are cached on their own.
+    assert_eq!(
+        sorted(accessor.query::<&Position>(&mut manager).unwrap()),
+        [first, second]
+    );
+
+    manager.remove_component_from_entity::<Velocity>(first);
+    assert_eq!(still(&mut accessor, &mut manager), [first, second]);
+
+    manager.add_component_to_entity(second, Velocity);
+    manager.remove_entity(first);
+    assert!(still(&mut accessor, &mut manager).is_empty());
+}
+
+#[test]
+fn queries_take_more_than_four_components() {
+    let mut manager = manager();
+    let heavy = manager.spawn((Position(0), Velocity, Sprite, Health(3), Mass(4)));
+    let light = manager.spawn((Position(0), Velocity, Health(1), Mass(2)));
+    // Missing one component is enough to be left out.
+    manager.spawn((Position(0), Velocity, Sprite, Health(5)));
+
+    let query = manager.query::<(&mut Position, &Velocity, &Health, &Mass, Option<&Sprite>)>();
+    let mut matched = Vec::new();
+    for (entity_id, (mut position, _, health, mass, sprite)) in query.unwrap() {
+        position.0 = (health.0 * mass.0) as i32;
+        matched.push((entity_id, sprite.is_some()));
+    }
+    matched.sort();
+    assert_eq!(matched, [(heavy, true), (light, false)]);
+
+    let (position, _, _, health, mass) = manager
+        .query_entity::<(&Position, &Velocity, &Sprite, &Health, &Mass)>(heavy)
+        .unwrap();
+    assert_eq!((position.0, health.0, mass.0), (12, 3, 4));
+    assert_eq!(manager.borrow_component::<Position>(light).unwrap().0, 2);
+}
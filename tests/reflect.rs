@@ -0,0 +1,142 @@
+use serde_json::json;
+use taconite::*;
+
+#[derive(Reflect, Default)]
+struct Stats {
+    health: i32,
+    speed: f32,
+    mode: Mode,
+    spawn: Vector2,
+}
+
+impl Component for Stats {}
+
+#[derive(Reflect, Default, Debug, PartialEq)]
+enum Mode {
+    #[default]
+    Idle,
+    Running,
+}
+
+#[derive(Reflect, Default)]
+struct Range(u8, u8);
+
+#[test]
+fn derived_structs_list_their_fields() {
+    let info = Stats::type_info();
+    assert_eq!(info.short_name(), "Stats");
+    let fields = info
+        .fields()
+        .iter()
+        .map(|field| (field.name(), field.type_name()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        fields,
+        [
+            ("health", "i32"),
+            ("speed", "f32"),
+            ("mode", std::any::type_name::<Mode>()),
+            ("spawn", std::any::type_name::<Vector2>()),
+        ]
+    );
+
+    let mut stats = Stats::default();
+    *stats
+        .field_mut("health")
+        .unwrap()
+        .as_any_mut()
+        .downcast_mut::<i32>()
+        .unwrap() = 3;
+    assert_eq!(stats.health, 3);
+    assert!(stats.field("mana").is_none());
+    assert_eq!(
+        format!("{:?}", &stats as &dyn Reflect),
+        "Stats { health: 3, speed: 0.0, mode: Idle, spawn: Vector2 { x: 0.0, y: 0.0 } }"
+    );
+
+    // Tuple struct fields are named by their index.
+    let range = Range(1, 2);
+    assert_eq!(range.to_value(), json!({"0": 1, "1": 2}));
+    assert_eq!(format!("{:?}", &range as &dyn Reflect), "Range(1, 2)");
+}
+
+#[test]
+fn derived_unit_enums_switch_variants() {
+    assert!(matches!(
+        Mode::type_info().kind(),
+        TypeKind::Enum(variants) if variants == &["Idle", "Running"]
+    ));
+
+    let mut mode = Mode::Idle;
+    assert_eq!(mode.variant(), Some("Idle"));
+    assert!(mode.set_variant("Running"));
+    assert_eq!(mode, Mode::Running);
+    assert!(!mode.set_variant("Flying"));
+    assert_eq!(mode.to_value(), json!("Running"));
+}
+
+#[test]
+fn values_are_applied_field_by_field() {
+    let mut stats = Stats::default();
+    stats
+        .apply_value(&json!({"health": 5, "mode": "Running", "spawn": {"y": 2.0}}))
+        .unwrap();
+    assert_eq!(stats.health, 5);
+    assert_eq!(stats.mode, Mode::Running);
+    assert_eq!(stats.spawn, Vector2 { x: 0.0, y: 2.0 });
+
+    assert!(matches!(
+        stats.apply_value(&json!({"mana": 1})),
+        Err(ReflectError::UnknownField { field, .. }) if field == "mana"
+    ));
+    assert!(matches!(
+        stats.apply_value(&json!({"mode": "Flying"})),
+        Err(ReflectError::UnknownVariant { variant, .. }) if variant == "Flying"
+    ));
+    assert!(matches!(
+        stats.apply_value(&json!({"mode": 1})),
+        Err(ReflectError::InvalidValue { .. })
+    ));
+    assert!(matches!(
+        stats.apply_value(&json!([1, 2])),
+        Err(ReflectError::InvalidValue { .. })
+    ));
+
+    // The fields before the one that failed are already set.
+    let result = stats.apply_value(&json!({"health": 7, "speed": "fast"}));
+    assert!(matches!(
+        result,
+        Err(ReflectError::InvalidValue {
+            type_name: "f32",
+            ..
+        })
+    ));
+    assert_eq!(stats.health, 7);
+}
+
+#[test]
+fn entities_are_printed_with_their_reflected_components() {
+    let mut world = World::default();
+    world.register_reflect_component::<Stats>();
+    let player = world.spawn((Name::new("player"), Stats::default()));
+    let nameless = world.spawn(());
+
+    world
+        .reflect_component_mut(player, "Stats")
+        .unwrap()
+        .apply_value(&json!({"health": 10}))
+        .unwrap();
+    let spawn = "Vector2 { x: 0.0, y: 0.0 }";
+    let stats = format!("Stats {{ health: 10, speed: 0.0, mode: Idle, spawn: {spawn} }}");
+    assert_eq!(
+        world.debug_entity(player).unwrap(),
+        format!("{player} \"player\" [{stats}]")
+    );
+    assert_eq!(
+        world.debug_entity(nameless).unwrap(),
+        format!("{nameless} []")
+    );
+
+    world.remove_entity(nameless);
+    assert!(world.debug_entity(nameless).is_none());
+}
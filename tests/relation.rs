@@ -0,0 +1,53 @@
+use taconite::*;
+
+struct Docked;
+
+impl Relationship for Docked {}
+
+fn related(manager: &EntityManager, source: EntityId) -> Vec<EntityId> {
+    manager.related::<Docked>(source).collect()
+}
+
+#[test]
+fn restore_drops_relationships_first_made_after_the_snapshot() {
+    let mut manager = EntityManager::default();
+    let ship = manager.create_entity();
+    let station = manager.create_entity();
+    let snapshot = manager.snapshot();
+
+    manager.relate::<Docked>(ship, station);
+    assert_eq!(related(&manager, ship), [station]);
+
+    manager.restore(&snapshot);
+    assert!(related(&manager, ship).is_empty());
+    assert!(manager.related_to::<Docked>(station).next().is_none());
+
+    // Relationships in the snapshot come back.
+    manager.relate::<Docked>(ship, station);
+    let snapshot = manager.snapshot();
+    manager.unrelate::<Docked>(ship, station);
+    manager.restore(&snapshot);
+    assert!(manager.is_related::<Docked>(ship, station));
+}
+
+#[test]
+fn relationships_are_dropped_with_either_entity() {
+    let mut manager = EntityManager::default();
+    let ship = manager.create_entity();
+    let station = manager.create_entity();
+    let other_station = manager.create_entity();
+    manager
+        .relate::<Docked>(ship, station)
+        .relate::<Docked>(ship, other_station);
+
+    // Removing the target drops it from the source's relation.
+    manager.remove_entity(station);
+    assert_eq!(related(&manager, ship), [other_station]);
+
+    // Removing the source drops it from the target's.
+    manager.remove_entity(ship);
+    assert!(manager.related_to::<Docked>(other_station).next().is_none());
+    assert!(manager
+        .borrow_component::<RelatedBy<Docked>>(other_station)
+        .is_none());
+}
@@ -0,0 +1,109 @@
+use std::sync::{Arc, Mutex};
+use taconite::*;
+
+struct Score(u32);
+
+/// Whether the score changed since the system last ran, every time it ran.
+type ChangeLog = Arc<Mutex<Vec<bool>>>;
+
+/// How a `Scorer` changes the score the next time it runs.
+#[derive(Clone, Copy)]
+enum Change {
+    None,
+    Borrow,
+    Command,
+}
+
+/// Records whether the score changed, then changes it as asked.
+struct Scorer(ChangeLog, Arc<Mutex<Change>>);
+
+impl System for Scorer {
+    fn update(
+        &mut self,
+        _manager: &mut EntityManager,
+        accessor: &mut EntityIdAccessor,
+        commands: &mut Commands,
+        resources: &mut Resources,
+    ) -> Option<()> {
+        let changed = resources.is_changed::<Score>(accessor.last_run());
+        self.0.lock().unwrap().push(changed);
+
+        let change = std::mem::replace(&mut *self.1.lock().unwrap(), Change::None);
+        match change {
+            Change::None => {}
+            Change::Borrow => resources.get_mut::<Score>()?.0 += 1,
+            Change::Command => {
+                commands.insert_resource(Score(0));
+            }
+        }
+        Some(())
+    }
+}
+
+fn take_log(log: &ChangeLog) -> Vec<bool> {
+    log.lock().unwrap().drain(..).collect()
+}
+
+#[test]
+fn resources_remember_when_they_changed() {
+    let mut resources = Resources::default();
+    assert_eq!(resources.changed_tick::<Score>(), None);
+    assert!(!resources.is_changed::<Score>(None));
+
+    resources.insert(Score(0));
+    assert_eq!(resources.changed_tick::<Score>(), Some(0));
+    assert!(resources.is_changed::<Score>(None));
+    assert!(!resources.is_changed::<Score>(Some(0)));
+
+    // Reading isn't a change.
+    assert!(resources.get::<Score>().is_some());
+    assert!(!resources.is_changed::<Score>(Some(0)));
+
+    resources.remove::<Score>();
+    assert_eq!(resources.changed_tick::<Score>(), None);
+    assert!(!resources.is_changed::<Score>(None));
+}
+
+#[test]
+fn systems_see_changes_made_since_they_last_ran() {
+    let (early, late) = (ChangeLog::default(), ChangeLog::default());
+    let early_change = Arc::new(Mutex::new(Change::None));
+    let late_change = Arc::new(Mutex::new(Change::None));
+    let mut world = World::default();
+    world
+        .insert_resource(Score(0))
+        .add_system(Scorer(early.clone(), early_change.clone()))
+        .add_system_to_stage(
+            Stage::PostUpdate,
+            Scorer(late.clone(), late_change.clone()),
+            SystemConfig::default(),
+        );
+
+    world.update();
+    assert_eq!(take_log(&early), [true]);
+    world.update();
+    assert_eq!(take_log(&early), [false]);
+
+    // Changes made between updates are seen by every system.
+    world.get_resource_mut::<Score>().unwrap().0 += 1;
+    world.update();
+    assert_eq!(take_log(&early), [true]);
+    assert_eq!(take_log(&late), [true, false, true]);
+
+    // A system doesn't see its own changes, but the systems after it do, and so do the ones
+    // before it once they run again.
+    for change in [Change::Borrow, Change::Command] {
+        *early_change.lock().unwrap() = change;
+        world.update();
+        assert_eq!(take_log(&late), [true]);
+        world.update();
+        assert_eq!(take_log(&early), [false, false]);
+        assert_eq!(take_log(&late), [false]);
+
+        *late_change.lock().unwrap() = change;
+        world.update();
+        world.update();
+        assert_eq!(take_log(&early), [false, true]);
+        assert_eq!(take_log(&late), [false, false]);
+    }
+}
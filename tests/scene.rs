@@ -0,0 +1,98 @@
+use taconite::*;
+
+const FAMILY: &str = r#"{
+    "entities": [
+        { "id": { "index": 7, "generation": 1 }, "components": { "Name": "parent" } },
+        {
+            "id": { "index": 3, "generation": 0 },
+            "components": {
+                "Name": "child",
+                "Parent": { "index": 7, "generation": 1 }
+            }
+        }
+    ]
+}"#;
+
+#[test]
+fn parents_are_set_when_loading() {
+    let mut world = World::default();
+    world.create_entity();
+    let entity_map = world.load_scene(FAMILY).unwrap();
+
+    let parent = world.find_by_name("parent").unwrap();
+    let child = world.find_by_name("child").unwrap();
+    let loaded = |index| {
+        entity_map
+            .iter()
+            .find(|(saved, _)| saved.index() == index)
+            .map(|(_, loaded)| *loaded)
+    };
+    assert_eq!(loaded(7), Some(parent));
+    assert_eq!(loaded(3), Some(child));
+
+    // The parent's `Children` were filled in, so removing it removes the child too.
+    world.remove_entity(parent);
+    assert!(world.find_by_name("child").is_none());
+}
+
+#[test]
+fn references_outside_the_scene_are_rejected() {
+    let mut world = World::default();
+    let outside = world.spawn((Name::new("outside"),));
+    let scene = FAMILY.replace(
+        r#""Parent": { "index": 7, "generation": 1 }"#,
+        &format!(
+            r#""Parent": {{ "index": {}, "generation": {} }}"#,
+            outside.index(),
+            outside.generation()
+        ),
+    );
+
+    let error = world.load_scene(&scene).unwrap_err();
+    assert!(
+        matches!(error, SceneError::UnmappedEntity(entity_id) if entity_id == outside),
+        "{error}"
+    );
+    // Nothing was loaded.
+    assert!(world.find_by_name("parent").is_none());
+    assert!(world.find_by_name("child").is_none());
+}
+
+#[test]
+fn prefabs_cant_refer_to_entities() {
+    let mut world = World::default();
+    let scene = r#"{
+        "prefabs": { "orphan": { "Parent": { "index": 0, "generation": 0 } } }
+    }"#;
+
+    let error = world.load_scene(scene).unwrap_err();
+    assert!(matches!(error, SceneError::UnmappedEntity(_)), "{error}");
+}
+
+#[test]
+fn parent_cycles_are_rejected() {
+    let mut world = World::default();
+    let scene = FAMILY.replace(
+        r#""Name": "parent""#,
+        r#""Name": "parent", "Parent": { "index": 3, "generation": 0 }"#,
+    );
+
+    let error = world.load_scene(&scene).unwrap_err();
+    assert!(matches!(error, SceneError::ParentCycle(_)), "{error}");
+    // Nothing was loaded, so the world is still empty.
+    assert!(world.find_by_name("parent").is_none());
+    assert!(world.find_by_name("child").is_none());
+    let first = world.create_entity();
+    assert_eq!((first.index(), first.generation()), (0, 0));
+
+    let scene = r#"{
+        "entities": [
+            {
+                "id": { "index": 0, "generation": 0 },
+                "components": { "Parent": { "index": 0, "generation": 0 } }
+            }
+        ]
+    }"#;
+    let error = world.load_scene(scene).unwrap_err();
+    assert!(matches!(error, SceneError::ParentCycle(_)), "{error}");
+}
@@ -0,0 +1,99 @@
+use std::sync::{Arc, Mutex};
+use taconite::*;
+
+/// The systems that ran, in order.
+type RunLog = Arc<Mutex<Vec<&'static str>>>;
+
+struct Recorder(&'static str, RunLog);
+
+impl System for Recorder {
+    fn update(
+        &mut self,
+        _manager: &mut EntityManager,
+        _accessor: &mut EntityIdAccessor,
+        _commands: &mut Commands,
+        _resources: &mut Resources,
+    ) -> Option<()> {
+        self.1.lock().unwrap().push(self.0);
+        Some(())
+    }
+}
+
+fn config(label: &'static str, before: &[&'static str], after: &[&'static str]) -> SystemConfig {
+    SystemConfig {
+        label: Some(label),
+        before: before.to_vec(),
+        after: after.to_vec(),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn systems_are_ordered_across_labels() {
+    let log = RunLog::default();
+    let mut world = World::default();
+    let mut add = |name, config| {
+        world.add_system_to_stage(Stage::Update, Recorder(name, log.clone()), config);
+    };
+    // Added in the opposite order to the one they have to run in.
+    add("animation", config("animation", &[], &["physics"]));
+    add("collisions", config("physics", &[], &[]));
+    add("movement", config("physics", &[], &["input"]));
+    add("unordered", SystemConfig::default());
+    add("input", config("input", &["physics"], &[]));
+
+    world.build_schedule().unwrap();
+    world.update();
+    assert_eq!(
+        *log.lock().unwrap(),
+        ["unordered", "input", "collisions", "movement", "animation"]
+    );
+}
+
+#[test]
+fn cycles_are_reported_by_build_schedule() {
+    let log = RunLog::default();
+    let mut world = World::default();
+    world
+        .add_system_to_stage(
+            Stage::PostUpdate,
+            Recorder("bystander", log.clone()),
+            config("bystander", &[], &["first"]),
+        )
+        .add_system_to_stage(
+            Stage::PostUpdate,
+            Recorder("first", log.clone()),
+            config("first", &["second"], &[]),
+        )
+        .add_system_to_stage(
+            Stage::PostUpdate,
+            Recorder("second", log.clone()),
+            config("second", &["third"], &[]),
+        )
+        .add_system_to_stage(
+            Stage::PostUpdate,
+            Recorder("third", log.clone()),
+            config("third", &["first"], &[]),
+        )
+        .add_system(Recorder("update", log.clone()));
+
+    match world.build_schedule() {
+        Err(EcsError::SystemOrderCycle(Stage::PostUpdate, cycle)) => {
+            let mut labels = cycle
+                .split(" -> ")
+                .map(|system| system.rsplit('`').nth(1).unwrap())
+                .collect::<Vec<_>>();
+            // The cycle can start anywhere, but comes back around to where it started.
+            assert_eq!(labels.pop(), labels.first().copied(), "{cycle}");
+            let start = labels.iter().position(|&label| label == "first").unwrap();
+            labels.rotate_left(start);
+            // The system waiting on the cycle isn't part of it.
+            assert_eq!(labels, ["first", "second", "third"], "{cycle}");
+        }
+        result => panic!("Expected a cycle, got {result:?}"),
+    }
+
+    // Nothing runs while the schedule can't be built, even in the other stages.
+    world.update();
+    assert!(log.lock().unwrap().is_empty());
+}
@@ -0,0 +1,124 @@
+use std::sync::{Arc, Mutex};
+use taconite::*;
+
+#[derive(Clone)]
+struct Health(u32);
+
+impl Component for Health {}
+
+/// Left behind when an entity loses its health.
+struct Corpse;
+
+impl Component for Corpse {}
+
+/// The hooks that ran, with the health they saw, in order.
+type HookLog = Arc<Mutex<Vec<(&'static str, u32)>>>;
+
+fn hooked_manager(log: &HookLog) -> EntityManager {
+    let hook = |name: &'static str, log: &HookLog| {
+        let log = log.clone();
+        move |health: &Health, _: EntityId, _: &mut Commands| {
+            log.lock().unwrap().push((name, health.0));
+        }
+    };
+
+    let mut manager = EntityManager::default();
+    manager.register_cloneable::<Health>().set_component_hooks(
+        ComponentHooks::default()
+            .on_add(hook("add", log))
+            .on_insert(hook("insert", log))
+            .on_remove(hook("remove", log)),
+    );
+    manager
+}
+
+#[test]
+fn restore_doesnt_reuse_generations() {
+    let mut manager = EntityManager::default();
+    let kept = manager.create_entity();
+    let snapshot = manager.snapshot();
+
+    // `created` reuses the index of `kept`, and `added` takes a new one.
+    manager.remove_entity(kept);
+    let created = manager.create_entity();
+    let added = manager.create_entity();
+    assert_eq!(created.index(), kept.index());
+
+    manager.restore(&snapshot);
+    assert!(manager.entity_exists(kept));
+    assert!(!manager.entity_exists(created));
+    assert!(!manager.entity_exists(added));
+
+    // Neither ID handed out since the snapshot comes back.
+    manager.remove_entity(kept);
+    let entity_ids = (0..4).map(|_| manager.create_entity()).collect::<Vec<_>>();
+    for entity_id in [kept, created, added] {
+        assert!(!entity_ids.contains(&entity_id), "{entity_id} was reused");
+    }
+
+    // Going back again still skips them.
+    manager.restore(&snapshot);
+    let entity_ids = (0..4).map(|_| manager.create_entity()).collect::<Vec<_>>();
+    for entity_id in [created, added] {
+        assert!(!entity_ids.contains(&entity_id), "{entity_id} was reused");
+    }
+}
+
+#[test]
+fn reserved_ids_stay_stale_after_restore() {
+    let mut manager = EntityManager::default();
+    let snapshot = manager.snapshot();
+    let mut commands = manager.commands();
+    let reserved = commands.spawn().id();
+
+    manager.restore(&snapshot);
+    assert_ne!(manager.create_entity(), reserved);
+}
+
+#[test]
+fn restore_runs_hooks_for_the_components_it_swaps() {
+    let log = HookLog::default();
+    let mut manager = hooked_manager(&log);
+    let kept = manager.create_entity();
+    let removed = manager.create_entity();
+    manager.add_component_to_entity(kept, Health(1));
+    let snapshot = manager.snapshot();
+
+    manager.insert_component(kept, Health(2));
+    manager.remove_component_from_entity::<Health>(kept);
+    manager.remove_entity(removed);
+    let added = manager.create_entity();
+    manager.add_component_to_entity(added, Health(3));
+    log.lock().unwrap().clear();
+
+    // The component added since is dropped, and the one in the snapshot comes back.
+    manager.restore(&snapshot);
+    assert_eq!(
+        log.lock().unwrap().drain(..).collect::<Vec<_>>(),
+        [("remove", 3), ("add", 1), ("insert", 1)]
+    );
+}
+
+#[test]
+fn entities_spawned_by_hooks_outlive_the_restore() {
+    let mut manager = EntityManager::default();
+    manager.register::<Corpse>();
+    manager.register_cloneable::<Health>().set_component_hooks(
+        ComponentHooks::default().on_remove(|_: &Health, _, commands: &mut Commands| {
+            commands.spawn().insert(Corpse);
+        }),
+    );
+    let snapshot = manager.snapshot();
+    let added = manager.create_entity();
+    manager.add_component_to_entity(added, Health(1));
+
+    manager.restore(&snapshot);
+    assert!(!manager.entity_exists(added));
+    let corpses = manager
+        .query::<&Corpse>()
+        .unwrap()
+        .map(|(entity_id, _)| entity_id)
+        .collect::<Vec<_>>();
+    assert_eq!(corpses.len(), 1);
+    assert_ne!(corpses[0], added);
+}
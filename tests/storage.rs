@@ -0,0 +1,99 @@
+use taconite::*;
+
+#[derive(Clone)]
+struct Frozen;
+
+impl Component for Frozen {
+    const STORAGE: StorageKind = StorageKind::Tag;
+}
+
+struct Health(u32);
+
+impl Component for Health {}
+
+fn frozen(manager: &mut EntityManager) -> Vec<EntityId> {
+    let mut entity_ids = manager
+        .query::<&Frozen>()
+        .unwrap()
+        .map(|(entity_id, _)| entity_id)
+        .collect::<Vec<_>>();
+    entity_ids.sort();
+    entity_ids
+}
+
+#[test]
+fn tags_are_added_removed_and_iterated() {
+    let mut manager = EntityManager::default();
+    manager.register::<Frozen>();
+    // Enough entities to span several bitset blocks.
+    let entity_ids = (0..150)
+        .map(|_| manager.create_entity())
+        .collect::<Vec<_>>();
+    for entity_id in entity_ids.iter().step_by(2) {
+        manager.add_component_to_entity(*entity_id, Frozen);
+    }
+    assert_eq!(
+        frozen(&mut manager),
+        entity_ids.iter().step_by(2).copied().collect::<Vec<_>>()
+    );
+
+    manager.remove_component_from_entity::<Frozen>(entity_ids[64]);
+    manager.remove_entity(entity_ids[128]);
+    assert!(manager.borrow_component::<Frozen>(entity_ids[64]).is_none());
+    assert!(manager.borrow_component::<Frozen>(entity_ids[66]).is_some());
+    let mut removed = manager
+        .removed_components::<Frozen>(None)
+        .collect::<Vec<_>>();
+    removed.sort();
+    assert_eq!(removed, [entity_ids[64], entity_ids[128]]);
+    assert_eq!(
+        frozen(&mut manager),
+        entity_ids
+            .iter()
+            .step_by(2)
+            .filter(|entity_id| ![entity_ids[64], entity_ids[128]].contains(entity_id))
+            .copied()
+            .collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn tags_are_restored_from_snapshots() {
+    let mut manager = EntityManager::default();
+    manager.register_cloneable::<Frozen>();
+    let first = manager.create_entity();
+    let second = manager.create_entity();
+    manager.add_component_to_entity(first, Frozen);
+    let snapshot = manager.snapshot();
+
+    manager.remove_component_from_entity::<Frozen>(first);
+    manager.add_component_to_entity(second, Frozen);
+    assert_eq!(frozen(&mut manager), [second]);
+
+    manager.restore(&snapshot);
+    assert_eq!(frozen(&mut manager), [first]);
+}
+
+#[test]
+fn only_zero_sized_components_are_tags() {
+    assert_eq!(
+        ComponentManager::<Frozen>::with_storage(StorageKind::Tag).storage_kind(),
+        StorageKind::Tag
+    );
+    // Anything bigger falls back to a table.
+    assert_eq!(
+        ComponentManager::<Health>::with_storage(StorageKind::Tag).storage_kind(),
+        StorageKind::Table
+    );
+
+    let mut manager = EntityManager::default();
+    manager.register_with_storage::<Health>(StorageKind::Tag);
+    let entity_id = manager.create_entity();
+    manager.add_component_to_entity(entity_id, Health(3));
+    assert_eq!(
+        manager
+            .borrow_component::<Health>(entity_id)
+            .map(|health| health.0),
+        Some(3)
+    );
+}
@@ -0,0 +1,167 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use taconite::*;
+
+/// The systems that ran, with the delta time they saw, in order.
+type RunLog = Arc<Mutex<Vec<(&'static str, Duration)>>>;
+
+struct Recorder(&'static str, RunLog);
+
+impl System for Recorder {
+    fn update(
+        &mut self,
+        _manager: &mut EntityManager,
+        _accessor: &mut EntityIdAccessor,
+        _commands: &mut Commands,
+        resources: &mut Resources,
+    ) -> Option<()> {
+        let delta = resources.get::<Time>()?.delta();
+        self.1.lock().unwrap().push((self.0, delta));
+        Some(())
+    }
+}
+
+fn take_log(log: &RunLog) -> Vec<(&'static str, Duration)> {
+    log.lock().unwrap().drain(..).collect()
+}
+
+fn ms(millis: u64) -> Duration {
+    Duration::from_millis(millis)
+}
+
+fn fixed_world(log: &RunLog, max_steps: u32) -> World {
+    let mut world = World::default();
+    world
+        .set_execution_mode(ExecutionMode::Serial)
+        .set_fixed_timestep(FixedTimestep::from_timestep(ms(10)).with_max_steps(max_steps))
+        .add_system_to_stage(
+            Stage::FixedUpdate,
+            Recorder("fixed", log.clone()),
+            SystemConfig::default(),
+        )
+        .add_system(Recorder("update", log.clone()));
+    world
+}
+
+fn alpha(world: &World) -> f32 {
+    world.get_resource::<Time>().unwrap().alpha()
+}
+
+#[test]
+fn timestep_from_hz() {
+    let fixed_timestep = FixedTimestep::from_hz(50.0);
+    assert_eq!(fixed_timestep.timestep(), ms(20));
+    assert_eq!(fixed_timestep.max_steps(), 5);
+}
+
+#[test]
+fn invalid_timesteps_fall_back_to_the_default() {
+    for hz in [0.0, -60.0, f64::NAN, f64::INFINITY] {
+        assert_eq!(FixedTimestep::from_hz(hz), FixedTimestep::default(), "{hz}");
+    }
+    assert_eq!(
+        FixedTimestep::from_timestep(Duration::ZERO),
+        FixedTimestep::default()
+    );
+    assert_eq!(FixedTimestep::default().with_max_steps(0).max_steps(), 5);
+}
+
+#[test]
+fn fixed_updates_step_through_the_time_passed() {
+    let log = RunLog::default();
+    let mut world = fixed_world(&log, 5);
+
+    // Fixed updates see the fixed timestep as their delta.
+    world.update_with_delta(ms(25));
+    assert_eq!(
+        take_log(&log),
+        [("fixed", ms(10)), ("fixed", ms(10)), ("update", ms(25))]
+    );
+    assert!((alpha(&world) - 0.5).abs() < 1e-4);
+
+    // The leftover time carries over to the next update.
+    world.update_with_delta(ms(5));
+    assert_eq!(take_log(&log), [("fixed", ms(10)), ("update", ms(5))]);
+    assert!(alpha(&world).abs() < 1e-4);
+
+    world.update_with_delta(ms(4));
+    assert_eq!(take_log(&log), [("update", ms(4))]);
+    assert!((alpha(&world) - 0.4).abs() < 1e-4);
+}
+
+#[test]
+fn time_beyond_the_most_steps_is_dropped() {
+    let log = RunLog::default();
+    let mut world = fixed_world(&log, 3);
+
+    world.update_with_delta(ms(105));
+    let fixed_steps = take_log(&log)
+        .iter()
+        .filter(|(system, _)| *system == "fixed")
+        .count();
+    assert_eq!(fixed_steps, 3);
+    // Only the partial step is kept.
+    assert!((alpha(&world) - 0.5).abs() < 1e-4);
+
+    world.update_with_delta(ms(5));
+    assert_eq!(take_log(&log), [("fixed", ms(10)), ("update", ms(5))]);
+    assert_eq!(world.get_resource::<Time>().unwrap().elapsed(), ms(110));
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum GameState {
+    Menu,
+    Playing,
+}
+
+struct Score(u32);
+
+#[test]
+fn run_conditions() {
+    let log = RunLog::default();
+    let mut world = World::default();
+    world
+        .set_execution_mode(ExecutionMode::Serial)
+        .insert_resource(State::new(GameState::Menu))
+        .add_system_to_stage(
+            Stage::Update,
+            Recorder("playing", log.clone()),
+            SystemConfig::default().run_if(in_state(GameState::Playing)),
+        )
+        .add_system_to_stage(
+            Stage::Update,
+            Recorder("scoring", log.clone()),
+            SystemConfig::default().run_if(resource_exists::<Score>),
+        )
+        .add_system_to_stage(
+            Stage::Update,
+            Recorder("score changed", log.clone()),
+            SystemConfig::default().run_if(resource_changed::<Score>),
+        );
+    let names = |log: &RunLog| {
+        take_log(log)
+            .into_iter()
+            .map(|(system, _)| system)
+            .collect::<Vec<_>>()
+    };
+
+    world.update_with_delta(ms(1));
+    assert!(names(&log).is_empty());
+
+    world
+        .insert_resource(Score(0))
+        .get_resource_mut::<State<GameState>>()
+        .unwrap()
+        .set(GameState::Playing);
+    world.update_with_delta(ms(1));
+    assert_eq!(names(&log), ["playing", "scoring", "score changed"]);
+
+    // The score didn't change since the system last ran.
+    world.update_with_delta(ms(1));
+    assert_eq!(names(&log), ["playing", "scoring"]);
+
+    world.get_resource_mut::<Score>().unwrap().0 += 1;
+    world.remove_resource::<State<GameState>>();
+    world.update_with_delta(ms(1));
+    assert_eq!(names(&log), ["scoring", "score changed"]);
+}
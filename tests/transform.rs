@@ -0,0 +1,190 @@
+use std::sync::{Arc, Mutex};
+use taconite::*;
+
+/// The x position of every `GlobalTransform` that changed, by entity.
+type ChangeLog = Arc<Mutex<Vec<(EntityId, f32)>>>;
+
+struct ChangeRecorder(ChangeLog);
+
+impl System for ChangeRecorder {
+    fn update(
+        &mut self,
+        manager: &mut EntityManager,
+        accessor: &mut EntityIdAccessor,
+        _commands: &mut Commands,
+        _resources: &mut Resources,
+    ) -> Option<()> {
+        let mut log = self.0.lock().unwrap();
+        for (entity_id, global_transform) in accessor
+            .query_filtered::<&GlobalTransform, Changed<GlobalTransform>>(manager)
+            .ok()?
+        {
+            log.push((entity_id, global_transform.position.x));
+        }
+        Some(())
+    }
+}
+
+/// Moves entities to the x positions queued for them in the next update.
+type Moves = Arc<Mutex<Vec<(EntityId, f32)>>>;
+
+struct Mover(Moves);
+
+impl System for Mover {
+    fn update(
+        &mut self,
+        manager: &mut EntityManager,
+        _accessor: &mut EntityIdAccessor,
+        _commands: &mut Commands,
+        _resources: &mut Resources,
+    ) -> Option<()> {
+        for (entity_id, x) in self.0.lock().unwrap().drain(..) {
+            manager
+                .borrow_component_mut::<Transform>(entity_id)?
+                .position
+                .x = x;
+        }
+        Some(())
+    }
+}
+
+fn world(log: &ChangeLog, moves: &Moves) -> World {
+    let mut world = World::default();
+    world
+        .register_component::<Transform>()
+        .register_component::<GlobalTransform>()
+        .add_system(Mover(moves.clone()))
+        .add_system_to_stage(
+            Stage::PostUpdate,
+            TransformPropagationSystem {},
+            SystemConfig {
+                label: Some("transform_propagation"),
+                ..Default::default()
+            },
+        )
+        .add_system_to_stage(
+            Stage::PostUpdate,
+            ChangeRecorder(log.clone()),
+            SystemConfig {
+                after: vec!["transform_propagation"],
+                ..Default::default()
+            },
+        );
+    world
+}
+
+fn take_changes(log: &ChangeLog) -> Vec<(EntityId, f32)> {
+    let mut changes = std::mem::take(&mut *log.lock().unwrap());
+    changes.sort_by_key(|(entity_id, _)| *entity_id);
+    changes
+}
+
+fn at(x: f32) -> Transform {
+    Transform::new(Vector3::new(x, 0.0, 0.0))
+}
+
+#[test]
+fn global_transforms_follow_the_hierarchy() {
+    let log = ChangeLog::default();
+    let moves = Moves::default();
+    let mut world = world(&log, &moves);
+    let root = world.spawn((at(1.0),));
+    let child = world.spawn((at(2.0),));
+    let grandchild = world.spawn((at(4.0),));
+    let other = world.spawn((at(8.0),));
+    world.set_parent(child, root).set_parent(grandchild, child);
+
+    // Every entity with a `Transform` gets a `GlobalTransform`.
+    world.update();
+    assert_eq!(
+        take_changes(&log),
+        [(root, 1.0), (child, 3.0), (grandchild, 7.0), (other, 8.0)]
+    );
+
+    world.update();
+    assert_eq!(take_changes(&log), []);
+
+    // Moving an entity moves its descendants, and leaves the rest alone.
+    moves.lock().unwrap().push((root, 11.0));
+    world.update();
+    assert_eq!(
+        take_changes(&log),
+        [(root, 11.0), (child, 13.0), (grandchild, 17.0)]
+    );
+
+    moves.lock().unwrap().push((grandchild, 5.0));
+    world.update();
+    assert_eq!(take_changes(&log), [(grandchild, 18.0)]);
+
+    // An entity that loses its parent becomes a root.
+    world.remove_parent(child);
+    world.update();
+    assert_eq!(take_changes(&log), [(child, 2.0), (grandchild, 7.0)]);
+}
+
+fn assert_near(actual: Vector3, expected: Vector3) {
+    let close = |a: f32, b: f32| (a - b).abs() < 1e-5;
+    assert!(
+        close(actual.x, expected.x) && close(actual.y, expected.y) && close(actual.z, expected.z),
+        "{actual:?} isn't {expected:?}"
+    );
+}
+
+#[test]
+fn rotations_are_composed_in_order() {
+    use std::f32::consts::FRAC_PI_2;
+
+    // Quaternions rotate the same way as euler angles.
+    let rotation = Vector3::new(0.3, -1.2, 2.5);
+    let point = Vector3::new(1.0, 2.0, 3.0);
+    assert_near(
+        Quaternion::from_euler(rotation).rotate(point),
+        point.rotate(rotation),
+    );
+    assert_near(Quaternion::from_euler(rotation).to_euler(), rotation);
+
+    // A parent rotated around x, a child rotated around z, and a point in front of the child.
+    let parent = GlobalTransform::from(Transform {
+        position: Vector3::splat(0.0),
+        rotation: Vector3::new(FRAC_PI_2, 0.0, 0.0),
+    });
+    let child = parent.mul_transform(&Transform {
+        position: Vector3::new(0.0, 1.0, 0.0),
+        rotation: Vector3::new(0.0, 0.0, FRAC_PI_2),
+    });
+    let point = child.mul_transform(&at(1.0));
+    // The child turns x into y, and the parent turns that into z.
+    assert_near(child.position, Vector3::new(0.0, 0.0, 1.0));
+    assert_near(point.position, Vector3::new(0.0, 0.0, 2.0));
+    assert_near(
+        point.rotation.rotate(Vector3::new(1.0, 0.0, 0.0)),
+        Vector3::new(0.0, 0.0, 1.0),
+    );
+}
+
+#[test]
+fn the_hierarchy_is_only_changed_through_set_parent() {
+    let mut manager = EntityManager::default();
+    let parent = manager.spawn(());
+    let child = manager.spawn(());
+    let other = manager.spawn(());
+    manager.set_parent(child, parent);
+
+    let stolen = manager.borrow_component::<Parent>(child).unwrap().clone();
+    manager.add_component_to_entity(other, stolen);
+    manager.remove_component_from_entity::<Parent>(child);
+    assert!(manager.borrow_component_mut::<Children>(parent).is_none());
+    assert!(matches!(
+        manager.query::<&mut Parent>(),
+        Err(EcsError::HierarchyComponent(_))
+    ));
+
+    // None of that got through, so the parent and its children still agree.
+    assert!(manager.borrow_component::<Parent>(other).is_none());
+    assert_eq!(
+        manager.borrow_component::<Parent>(child).map(Parent::get),
+        Some(parent)
+    );
+    let children = manager.borrow_component::<Children>(parent).unwrap();
+    assert_eq!(children.iter().copied().collect::<Vec<_>>(), [child]);
+}
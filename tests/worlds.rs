@@ -0,0 +1,76 @@
+use std::sync::Arc;
+use taconite::*;
+
+#[test]
+fn worlds_are_added_found_and_removed() {
+    let worlds = Worlds::default();
+    let ui = worlds.insert("ui", World::default()).unwrap();
+    let loading = worlds.insert("loading", World::default()).unwrap();
+    assert_eq!(worlds.names(), ["ui", "loading"]);
+    assert!(Arc::ptr_eq(&worlds.get("ui").unwrap(), &ui));
+    assert!(worlds.get("menu").is_none());
+
+    // A world with the same name is replaced, keeping its place.
+    let new_ui = worlds.insert("ui", World::default()).unwrap();
+    assert_eq!(worlds.names(), ["ui", "loading"]);
+    assert!(Arc::ptr_eq(&worlds.get("ui").unwrap(), &new_ui));
+
+    assert!(Arc::ptr_eq(&worlds.remove("loading").unwrap(), &loading));
+    assert!(worlds.remove("loading").is_none());
+    assert_eq!(worlds.names(), ["ui"]);
+}
+
+#[test]
+fn main_world_cant_be_replaced_or_removed() {
+    let mut taconite = Taconite::default();
+    let main_world = taconite.get_world(MAIN_WORLD).unwrap();
+
+    assert!(taconite.add_world(MAIN_WORLD).is_none());
+    assert!(taconite
+        .worlds()
+        .insert(MAIN_WORLD, World::default())
+        .is_none());
+    assert!(taconite.worlds().remove(MAIN_WORLD).is_none());
+
+    // `Taconite` still works on the world that's run.
+    let entity_id = taconite.spawn((Name::new("player"),));
+    assert!(Arc::ptr_eq(
+        &taconite.get_world(MAIN_WORLD).unwrap(),
+        &main_world
+    ));
+    assert_eq!(
+        main_world.lock().unwrap().find_by_name("player"),
+        Some(entity_id)
+    );
+}
+
+#[test]
+fn entities_move_across_worlds_with_their_children() {
+    let mut taconite = Taconite::default();
+    let ui = taconite.add_world("ui").unwrap();
+    let button = {
+        let mut ui = ui.lock().unwrap();
+        let button = ui.spawn((Name::new("button"),));
+        let label = ui.spawn((Name::new("label"),));
+        ui.set_parent(label, button);
+        button
+    };
+
+    let moved = taconite.move_entity(button, "ui", MAIN_WORLD).unwrap();
+    assert_eq!(taconite.find_by_name("button"), Some(moved));
+    assert!(taconite.find_by_name("label").is_some());
+    {
+        let ui = ui.lock().unwrap();
+        assert!(ui.find_by_name("button").is_none());
+        assert!(ui.find_by_name("label").is_none());
+    }
+
+    let back = taconite.move_entity(moved, MAIN_WORLD, "ui").unwrap();
+    assert_eq!(ui.lock().unwrap().find_by_name("button"), Some(back));
+    assert!(taconite.find_by_name("button").is_none());
+
+    // Moving within a world, or to a world that doesn't exist, fails and leaves it in place.
+    assert!(taconite.move_entity(back, "ui", "ui").is_none());
+    assert!(taconite.move_entity(back, "ui", "menu").is_none());
+    assert_eq!(ui.lock().unwrap().find_by_name("button"), Some(back));
+}